@@ -0,0 +1,129 @@
+use crate::client::{
+    Character, CharacterPerson, Client, CollectionDetail, CollectionEntry, CollectionStatus,
+    CreditedSubject, Episode, EpisodeStatus, Person, RelatedSubject, SearchResult,
+    SubjectCharacter, SubjectPerson, SubjectSmall, User,
+};
+use crate::transport::Transport;
+use futures::prelude::*;
+use std::collections::HashMap;
+
+mod fake;
+pub use self::fake::{FakeApi, FakeData};
+
+pub type ApiFuture<T> = Box<dyn Future<Item = T, Error = failure::Error> + Send>;
+
+/**
+ * The bgm.tv endpoints used by bgmTTY.
+ *
+ * `Client` talks to bgm.tv, while `FakeApi` serves scripted data from memory
+ */
+pub trait BangumiApi: Send + Sync + 'static {
+    fn user(&self, uid: Option<u64>) -> ApiFuture<User>;
+    fn collection(&self, uid: Option<u64>) -> ApiFuture<Vec<CollectionEntry>>;
+    fn collection_detail(&self, id: u64) -> ApiFuture<Option<CollectionDetail>>;
+    fn update_collection_detail(
+        &self,
+        id: u64,
+        status: CollectionStatus,
+        aux: Option<CollectionDetail>,
+    ) -> ApiFuture<CollectionDetail>;
+
+    fn subject(&self, id: u64) -> ApiFuture<SubjectSmall>;
+    fn related_subjects(&self, id: u64) -> ApiFuture<Vec<RelatedSubject>>;
+    fn subject_characters(&self, id: u64) -> ApiFuture<Vec<SubjectCharacter>>;
+    fn subject_persons(&self, id: u64) -> ApiFuture<Vec<SubjectPerson>>;
+
+    fn character(&self, id: u64) -> ApiFuture<Character>;
+    fn character_subjects(&self, id: u64) -> ApiFuture<Vec<CreditedSubject>>;
+    fn character_persons(&self, id: u64) -> ApiFuture<Vec<CharacterPerson>>;
+
+    fn person(&self, id: u64) -> ApiFuture<Person>;
+    fn person_subjects(&self, id: u64) -> ApiFuture<Vec<CreditedSubject>>;
+
+    fn progress(&self, coll: &CollectionEntry, ep: Option<u64>, vol: Option<u64>) -> ApiFuture<()>;
+    fn episodes(&self, id: u64) -> ApiFuture<Vec<Episode>>;
+    fn episode_progress(&self, id: u64) -> ApiFuture<HashMap<u64, EpisodeStatus>>;
+    fn update_episode(&self, ep: u64, status: Option<EpisodeStatus>) -> ApiFuture<()>;
+
+    fn search(&self, keywords: &str, len: usize, skip: usize) -> ApiFuture<SearchResult>;
+}
+
+// Inherent methods take precedence, so these forward to the requests in client.rs
+impl<T: Transport> BangumiApi for Client<T> {
+    fn user(&self, uid: Option<u64>) -> ApiFuture<User> {
+        Box::new(self.user(uid))
+    }
+
+    fn collection(&self, uid: Option<u64>) -> ApiFuture<Vec<CollectionEntry>> {
+        Box::new(self.collection(uid))
+    }
+
+    fn collection_detail(&self, id: u64) -> ApiFuture<Option<CollectionDetail>> {
+        Box::new(self.collection_detail(id))
+    }
+
+    fn update_collection_detail(
+        &self,
+        id: u64,
+        status: CollectionStatus,
+        aux: Option<CollectionDetail>,
+    ) -> ApiFuture<CollectionDetail> {
+        Box::new(self.update_collection_detail(id, status, aux))
+    }
+
+    fn subject(&self, id: u64) -> ApiFuture<SubjectSmall> {
+        Box::new(self.subject(id))
+    }
+
+    fn related_subjects(&self, id: u64) -> ApiFuture<Vec<RelatedSubject>> {
+        Box::new(self.related_subjects(id))
+    }
+
+    fn subject_characters(&self, id: u64) -> ApiFuture<Vec<SubjectCharacter>> {
+        Box::new(self.subject_characters(id))
+    }
+
+    fn subject_persons(&self, id: u64) -> ApiFuture<Vec<SubjectPerson>> {
+        Box::new(self.subject_persons(id))
+    }
+
+    fn character(&self, id: u64) -> ApiFuture<Character> {
+        Box::new(self.character(id))
+    }
+
+    fn character_subjects(&self, id: u64) -> ApiFuture<Vec<CreditedSubject>> {
+        Box::new(self.character_subjects(id))
+    }
+
+    fn character_persons(&self, id: u64) -> ApiFuture<Vec<CharacterPerson>> {
+        Box::new(self.character_persons(id))
+    }
+
+    fn person(&self, id: u64) -> ApiFuture<Person> {
+        Box::new(self.person(id))
+    }
+
+    fn person_subjects(&self, id: u64) -> ApiFuture<Vec<CreditedSubject>> {
+        Box::new(self.person_subjects(id))
+    }
+
+    fn progress(&self, coll: &CollectionEntry, ep: Option<u64>, vol: Option<u64>) -> ApiFuture<()> {
+        Box::new(self.progress(coll, ep, vol))
+    }
+
+    fn episodes(&self, id: u64) -> ApiFuture<Vec<Episode>> {
+        Box::new(self.episodes(id))
+    }
+
+    fn episode_progress(&self, id: u64) -> ApiFuture<HashMap<u64, EpisodeStatus>> {
+        Box::new(self.episode_progress(id))
+    }
+
+    fn update_episode(&self, ep: u64, status: Option<EpisodeStatus>) -> ApiFuture<()> {
+        Box::new(self.update_episode(ep, status))
+    }
+
+    fn search(&self, keywords: &str, len: usize, skip: usize) -> ApiFuture<SearchResult> {
+        Box::new(self.search(keywords, len, skip))
+    }
+}
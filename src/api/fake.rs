@@ -0,0 +1,311 @@
+use super::{ApiFuture, BangumiApi};
+use crate::client::{
+    Character, CharacterPerson, CollectionDetail, CollectionEntry, CollectionStatus,
+    CreditedSubject, Episode, EpisodeStatus, Person, RelatedSubject, SearchResult,
+    SubjectCharacter, SubjectPerson, SubjectSmall, User,
+};
+use crate::transport::ConnectionError;
+use failure::format_err;
+use futures::future;
+use futures::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::timer::Delay;
+
+/**
+ * Everything a `FakeApi` answers with. Missing entries are reported as request failures
+ */
+#[derive(Default)]
+pub struct FakeData {
+    pub user: Option<User>,
+    pub collection: Vec<CollectionEntry>,
+    pub collection_detail: HashMap<u64, CollectionDetail>,
+
+    pub subject: HashMap<u64, SubjectSmall>,
+    pub relations: HashMap<u64, Vec<RelatedSubject>>,
+    pub subject_characters: HashMap<u64, Vec<SubjectCharacter>>,
+    pub subject_persons: HashMap<u64, Vec<SubjectPerson>>,
+
+    /**
+     * Episodes by subject id
+     */
+    pub episodes: HashMap<u64, Vec<Episode>>,
+
+    /**
+     * Marks by episode id
+     */
+    pub episode_status: HashMap<u64, EpisodeStatus>,
+
+    pub character: HashMap<u64, Character>,
+    pub character_subjects: HashMap<u64, Vec<CreditedSubject>>,
+    pub character_persons: HashMap<u64, Vec<CharacterPerson>>,
+
+    pub person: HashMap<u64, Person>,
+    pub person_subjects: HashMap<u64, Vec<CreditedSubject>>,
+
+    /**
+     * Delay before every response
+     */
+    pub latency: Duration,
+
+    /**
+     * Makes every call fail like a dropped connection
+     */
+    pub offline: bool,
+
+    /**
+     * Makes the next call to the named method fail with the given message, like a rejection
+     */
+    pub failures: HashMap<&'static str, String>,
+
+    /**
+     * Names of the methods called so far, in order
+     */
+    pub calls: Vec<&'static str>,
+}
+
+/**
+ * An in-memory bgm.tv, for running the UI without network access.
+ *
+ * Clones share the same data, so a test can keep one to inspect the writes
+ * made through another. Latency is simulated with tokio timers, so the
+ * futures must be polled inside a tokio runtime
+ */
+#[derive(Clone, Default)]
+pub struct FakeApi {
+    data: Arc<Mutex<FakeData>>,
+}
+
+impl FakeApi {
+    pub fn new() -> FakeApi {
+        FakeApi::default()
+    }
+
+    pub fn with_data(data: FakeData) -> FakeApi {
+        FakeApi {
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    /**
+     * Modifies the scripted data in place
+     */
+    pub fn script<R, F: FnOnce(&mut FakeData) -> R>(&self, f: F) -> R {
+        f(&mut self.data.lock().unwrap())
+    }
+
+    pub fn latency(&self, latency: Duration) -> &Self {
+        self.script(|data| data.latency = latency);
+        self
+    }
+
+    pub fn fail_next(&self, method: &'static str, message: &str) -> &Self {
+        self.script(|data| data.failures.insert(method, message.to_string()));
+        self
+    }
+
+    pub fn offline(&self, offline: bool) -> &Self {
+        self.script(|data| data.offline = offline);
+        self
+    }
+
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.script(|data| data.calls.clone())
+    }
+
+    fn respond<T, F>(&self, method: &'static str, f: F) -> ApiFuture<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut FakeData) -> Result<T, failure::Error>,
+    {
+        let (latency, result) = self.script(|data| {
+            data.calls.push(method);
+            let result = if data.offline {
+                Err(ConnectionError::new(format_err!("Network is unreachable")).into())
+            } else {
+                match data.failures.remove(method) {
+                    Some(message) => Err(format_err!("{}", message)),
+                    None => f(data),
+                }
+            };
+            (data.latency, result)
+        });
+
+        if latency == Duration::from_secs(0) {
+            return Box::new(future::result(result));
+        }
+
+        Box::new(
+            Delay::new(Instant::now() + latency)
+                .map_err(|e| e.into())
+                .and_then(move |_| result),
+        )
+    }
+}
+
+fn lookup<T: Clone>(map: &HashMap<u64, T>, kind: &str, id: u64) -> Result<T, failure::Error> {
+    map.get(&id)
+        .cloned()
+        .ok_or_else(|| format_err!("No scripted {} for {}", kind, id))
+}
+
+impl BangumiApi for FakeApi {
+    fn user(&self, _uid: Option<u64>) -> ApiFuture<User> {
+        self.respond("user", |data| {
+            data.user
+                .clone()
+                .ok_or_else(|| format_err!("No scripted user"))
+        })
+    }
+
+    fn collection(&self, _uid: Option<u64>) -> ApiFuture<Vec<CollectionEntry>> {
+        self.respond("collection", |data| Ok(data.collection.clone()))
+    }
+
+    fn collection_detail(&self, id: u64) -> ApiFuture<Option<CollectionDetail>> {
+        self.respond("collection_detail", |data| {
+            Ok(data.collection_detail.get(&id).cloned())
+        })
+    }
+
+    fn update_collection_detail(
+        &self,
+        id: u64,
+        status: CollectionStatus,
+        aux: Option<CollectionDetail>,
+    ) -> ApiFuture<CollectionDetail> {
+        self.respond("update_collection_detail", |data| {
+            let mut detail = aux
+                .or_else(|| data.collection_detail.get(&id).cloned())
+                .unwrap_or_else(|| CollectionDetail {
+                    status: status.clone(),
+                    rating: 0,
+                    comment: String::new(),
+                    tag: Vec::new(),
+                });
+            detail.status = status;
+            data.collection_detail.insert(id, detail.clone());
+            Ok(detail)
+        })
+    }
+
+    fn subject(&self, id: u64) -> ApiFuture<SubjectSmall> {
+        self.respond("subject", |data| lookup(&data.subject, "subject", id))
+    }
+
+    fn related_subjects(&self, id: u64) -> ApiFuture<Vec<RelatedSubject>> {
+        self.respond("related_subjects", |data| {
+            Ok(data.relations.get(&id).cloned().unwrap_or_default())
+        })
+    }
+
+    fn subject_characters(&self, id: u64) -> ApiFuture<Vec<SubjectCharacter>> {
+        self.respond("subject_characters", |data| {
+            Ok(data
+                .subject_characters
+                .get(&id)
+                .cloned()
+                .unwrap_or_default())
+        })
+    }
+
+    fn subject_persons(&self, id: u64) -> ApiFuture<Vec<SubjectPerson>> {
+        self.respond("subject_persons", |data| {
+            Ok(data.subject_persons.get(&id).cloned().unwrap_or_default())
+        })
+    }
+
+    fn character(&self, id: u64) -> ApiFuture<Character> {
+        self.respond("character", |data| lookup(&data.character, "character", id))
+    }
+
+    fn character_subjects(&self, id: u64) -> ApiFuture<Vec<CreditedSubject>> {
+        self.respond("character_subjects", |data| {
+            Ok(data
+                .character_subjects
+                .get(&id)
+                .cloned()
+                .unwrap_or_default())
+        })
+    }
+
+    fn character_persons(&self, id: u64) -> ApiFuture<Vec<CharacterPerson>> {
+        self.respond("character_persons", |data| {
+            Ok(data.character_persons.get(&id).cloned().unwrap_or_default())
+        })
+    }
+
+    fn person(&self, id: u64) -> ApiFuture<Person> {
+        self.respond("person", |data| lookup(&data.person, "person", id))
+    }
+
+    fn person_subjects(&self, id: u64) -> ApiFuture<Vec<CreditedSubject>> {
+        self.respond("person_subjects", |data| {
+            Ok(data.person_subjects.get(&id).cloned().unwrap_or_default())
+        })
+    }
+
+    fn progress(&self, coll: &CollectionEntry, ep: Option<u64>, vol: Option<u64>) -> ApiFuture<()> {
+        let id = coll.subject.id;
+        self.respond("progress", |data| {
+            let entry = data
+                .collection
+                .iter_mut()
+                .find(|entry| entry.subject.id == id)
+                .ok_or_else(|| format_err!("Subject {} is not collected", id))?;
+
+            entry.ep_status = ep.unwrap_or(entry.ep_status);
+            entry.vol_status = vol.unwrap_or(entry.vol_status);
+            entry.lasttouch = chrono::Utc::now();
+            Ok(())
+        })
+    }
+
+    fn episodes(&self, id: u64) -> ApiFuture<Vec<Episode>> {
+        self.respond("episodes", |data| {
+            Ok(data.episodes.get(&id).cloned().unwrap_or_default())
+        })
+    }
+
+    fn episode_progress(&self, id: u64) -> ApiFuture<HashMap<u64, EpisodeStatus>> {
+        self.respond("episode_progress", |data| {
+            let eps = data.episodes.get(&id).map(Vec::as_slice).unwrap_or(&[]);
+            Ok(eps
+                .iter()
+                .filter_map(|ep| data.episode_status.get(&ep.id).map(|s| (ep.id, *s)))
+                .collect())
+        })
+    }
+
+    fn update_episode(&self, ep: u64, status: Option<EpisodeStatus>) -> ApiFuture<()> {
+        self.respond("update_episode", |data| {
+            match status {
+                Some(status) => data.episode_status.insert(ep, status),
+                None => data.episode_status.remove(&ep),
+            };
+            Ok(())
+        })
+    }
+
+    fn search(&self, keywords: &str, len: usize, skip: usize) -> ApiFuture<SearchResult> {
+        let keywords = keywords.to_lowercase();
+        self.respond("search", |data| {
+            let mut matched: Vec<SubjectSmall> = data
+                .subject
+                .values()
+                .filter(|s| {
+                    s.name.to_lowercase().contains(&keywords)
+                        || s.name_cn.to_lowercase().contains(&keywords)
+                })
+                .cloned()
+                .collect();
+            matched.sort_by_key(|s| s.id);
+
+            Ok(SearchResult {
+                count: matched.len(),
+                list: matched.into_iter().skip(skip).take(len).collect(),
+            })
+        })
+    }
+}
@@ -1,18 +1,16 @@
 use crate::consts::*;
-use futures::future;
-use futures::future::{Future, FutureResult};
-use futures::sync::oneshot;
-use hyper::server::{conn, Server};
-use hyper::service::{MakeService, Service};
-use hyper::{Body, Request, Response};
-use reqwest;
-use reqwest::r#async::Client;
+use crate::lenient;
+use crate::settings::AuthHandle;
+use crate::transport::{Request, ReqwestTransport, Response, Transport};
+use futures::future::{self, Future};
 use serde_derive::{Deserialize, Serialize};
-use std::borrow::Cow;
-use std::cell::RefCell;
-use std::ops::Deref;
 use std::str;
-use url::form_urlencoded;
+use std::time::Instant;
+
+#[cfg(feature = "oauth-server")]
+mod server;
+#[cfg(feature = "oauth-server")]
+pub use self::server::{request_code, RequestCodeError};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthInfo {
@@ -72,141 +70,177 @@ pub struct RespError {
     error_description: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(untagged)]
-pub enum AuthResp {
-    Success(AuthInfo),
-    Error(RespError),
-}
-
-struct CodeService {
-    sender: RefCell<Option<oneshot::Sender<String>>>,
-}
-
-impl Service for CodeService {
-    type ReqBody = Body;
-    type ResBody = Body;
-    type Error = !;
-    type Future = FutureResult<Response<Body>, !>;
-
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
-        if let Some(inner) = self.sender.replace(None) {
-            let queries = form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes());
-
-            for (k, v) in queries {
-                if k == Cow::Borrowed("code") {
-                    inner.send(v.to_string()).unwrap();
-                    break;
-                }
-            }
-        };
-
-        future::ok(Response::new(Body::from(
-            "<body>You can close this page now</body>",
-        )))
+impl RespError {
+    pub fn error(&self) -> &str {
+        &self.error
     }
-}
 
-struct MkCodeService {
-    sender: RefCell<Option<oneshot::Sender<String>>>,
-}
-
-impl MkCodeService {
-    fn new(sender: oneshot::Sender<String>) -> MkCodeService {
-        MkCodeService {
-            sender: RefCell::new(Some(sender)),
-        }
+    pub fn description(&self) -> &str {
+        &self.error_description
     }
 }
 
-impl MakeService<&conn::AddrStream> for MkCodeService {
-    type ResBody = Body;
-    type ReqBody = Body;
-    type Error = !;
-    type Service = CodeService;
-    type MakeError = !;
-    type Future = FutureResult<Self::Service, !>;
+/**
+ * What bgm.tv knows about an access token
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenStatus {
+    pub client_id: String,
+    #[serde(deserialize_with = "lenient::u64_or_string")]
+    pub user_id: u64,
 
-    fn make_service(&mut self, _: &conn::AddrStream) -> Self::Future {
-        future::ok(CodeService {
-            sender: RefCell::new(self.sender.replace(None)),
-        })
-    }
+    /**
+     * Unix timestamp
+     */
+    pub expires: i64,
+    pub scope: Option<String>,
 }
 
 #[derive(Debug)]
-pub enum RequestCodeError {
-    Server(hyper::error::Error),
-    Channel,
+pub enum TokenState {
+    Valid(TokenStatus),
+    Rejected { status: u16, error: RespError },
 }
 
-pub fn request_code(
-    client_id: &str,
-) -> (
-    String,
-    impl Future<Item = (String, String), Error = RequestCodeError>,
-) {
-    let port = 8478;
-
-    let (p, c) = oneshot::channel::<String>();
-
-    let recv = c.shared();
-    let shutdown = recv.clone().map(|_| ());
-
-    let addr = &([127, 0, 0, 1], port).into();
-
-    let factory = MkCodeService::new(p);
-
-    let server = Server::bind(addr)
-        .serve(factory)
-        .with_graceful_shutdown(shutdown)
-        .map_err(|e| RequestCodeError::Server(e));
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum AuthResp {
+    Success(AuthInfo),
+    Error(RespError),
+}
 
-    let redirect = format!("http://localhost:{}/", port);
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenStatusResp {
+    Valid(TokenStatus),
+    Error(RespError),
+}
 
-    let uri = format!(
+/**
+ * Where the user should be sent to grant access. bgm.tv redirects back to
+ * `redirect` with the code in the query string
+ */
+pub fn authorize_url(client_id: &str, redirect: &str) -> String {
+    format!(
         "{}?client_id={}&response_type=code&redirect_uri={}",
-        OAUTH_AUTHORIZE,
-        client_id,
-        redirect.clone()
-    );
-
-    (
-        uri,
-        recv.map_err(|_| RequestCodeError::Channel)
-            .join(server)
-            .map(|(result, _)| (result.deref().clone(), redirect)),
+        OAUTH_AUTHORIZE, client_id, redirect
     )
 }
 
-fn fetch_code(payload: AuthPayload) -> impl Future<Item = AuthResp, Error = reqwest::Error> {
-    let client = Client::new();
-    let pending = client.post(OAUTH_ACCESS_TOKEN).json(&payload).send();
+fn log_response(endpoint: &str, start: Instant, result: &Result<Response, failure::Error>) {
+    let elapsed = start.elapsed();
+    let latency = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+    match result {
+        Ok(ref resp) => debug!(
+            target: "bgmtv::request",
+            "method=POST endpoint={} status={} latency={}ms retry=0",
+            endpoint,
+            resp.status,
+            latency
+        ),
+        Err(ref e) => warn!(
+            target: "bgmtv::request",
+            "method=POST endpoint={} error={} latency={}ms retry=0",
+            endpoint,
+            e,
+            latency
+        ),
+    }
+}
 
-    pending.and_then(|mut resp| resp.json())
+/**
+ * Sends the request as is, without the access token or any retries
+ */
+fn post<T: Transport>(
+    transport: &T,
+    endpoint: &'static str,
+    req: Result<Request, failure::Error>,
+) -> impl Future<Item = Response, Error = failure::Error> {
+    let start = Instant::now();
+    let pending = match req {
+        Ok(req) => future::Either::A(transport.send(req)),
+        Err(e) => future::Either::B(future::err(e)),
+    };
+
+    pending.then(move |result| {
+        log_response(endpoint, start, &result);
+        result
+    })
+}
+
+fn fetch_code<T: Transport>(
+    transport: &T,
+    payload: AuthPayload,
+) -> impl Future<Item = AuthResp, Error = failure::Error> {
+    let req = Request::post(OAUTH_ACCESS_TOKEN.to_string()).json(&payload);
+    post(transport, OAUTH_ACCESS_TOKEN, req).and_then(|resp| resp.json())
 }
 
 pub fn request_token(
     app_cred: AppCred,
     code: String,
     redirect: String,
-) -> impl Future<Item = AuthResp, Error = reqwest::Error> {
-    fetch_code(AuthPayload::AuthorizationCode {
-        app_cred: app_cred,
-        code: code,
-        redirect_uri: redirect,
-        state: None,
-    })
+) -> impl Future<Item = AuthResp, Error = failure::Error> {
+    request_token_with(&ReqwestTransport::new(), app_cred, code, redirect)
+}
+
+pub fn request_token_with<T: Transport>(
+    transport: &T,
+    app_cred: AppCred,
+    code: String,
+    redirect: String,
+) -> impl Future<Item = AuthResp, Error = failure::Error> {
+    fetch_code(
+        transport,
+        AuthPayload::AuthorizationCode {
+            app_cred,
+            code,
+            redirect_uri: redirect,
+            state: None,
+        },
+    )
 }
 
 pub fn refresh_token(
     app_cred: AppCred,
     refresh: String,
     redirect: String,
-) -> impl Future<Item = AuthResp, Error = reqwest::Error> {
-    fetch_code(AuthPayload::RefreshToken {
-        app_cred: app_cred,
-        refresh_token: refresh,
-        redirect_uri: redirect,
+) -> impl Future<Item = AuthResp, Error = failure::Error> {
+    refresh_token_with(&ReqwestTransport::new(), app_cred, refresh, redirect)
+}
+
+pub fn refresh_token_with<T: Transport>(
+    transport: &T,
+    app_cred: AppCred,
+    refresh: String,
+    redirect: String,
+) -> impl Future<Item = AuthResp, Error = failure::Error> {
+    fetch_code(
+        transport,
+        AuthPayload::RefreshToken {
+            app_cred,
+            refresh_token: refresh,
+            redirect_uri: redirect,
+        },
+    )
+}
+
+pub fn token_status(handle: &AuthHandle) -> impl Future<Item = TokenState, Error = failure::Error> {
+    token_status_with(&ReqwestTransport::new(), handle)
+}
+
+pub fn token_status_with<T: Transport>(
+    transport: &T,
+    handle: &AuthHandle,
+) -> impl Future<Item = TokenState, Error = failure::Error> {
+    let req = Request::post(OAUTH_TOKEN_STATUS.to_string())
+        .form(&[("access_token", handle.info.access_token.as_str())]);
+
+    post(transport, OAUTH_TOKEN_STATUS, req).and_then(|resp| {
+        let status = resp.status;
+        resp.json().map(move |body| match body {
+            TokenStatusResp::Valid(info) => TokenState::Valid(info),
+            TokenStatusResp::Error(error) => TokenState::Rejected { status, error },
+        })
     })
 }
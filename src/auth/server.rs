@@ -0,0 +1,110 @@
+// The local HTTP server receiving the OAuth callback, so the user doesn't
+// have to copy the code by hand
+
+use super::authorize_url;
+use futures::future;
+use futures::future::{Future, FutureResult};
+use futures::sync::oneshot;
+use hyper::server::{conn, Server};
+use hyper::service::{MakeService, Service};
+use hyper::{Body, Request, Response};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::ops::Deref;
+use url::form_urlencoded;
+
+struct CodeService {
+    sender: RefCell<Option<oneshot::Sender<String>>>,
+}
+
+impl Service for CodeService {
+    type ReqBody = Body;
+    type ResBody = Body;
+    type Error = Infallible;
+    type Future = FutureResult<Response<Body>, Infallible>;
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if let Some(inner) = self.sender.replace(None) {
+            let queries = form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes());
+
+            for (k, v) in queries {
+                if k == Cow::Borrowed("code") {
+                    inner.send(v.to_string()).unwrap();
+                    break;
+                }
+            }
+        };
+
+        future::ok(Response::new(Body::from(
+            "<body>You can close this page now</body>",
+        )))
+    }
+}
+
+struct MkCodeService {
+    sender: RefCell<Option<oneshot::Sender<String>>>,
+}
+
+impl MkCodeService {
+    fn new(sender: oneshot::Sender<String>) -> MkCodeService {
+        MkCodeService {
+            sender: RefCell::new(Some(sender)),
+        }
+    }
+}
+
+impl MakeService<&conn::AddrStream> for MkCodeService {
+    type ResBody = Body;
+    type ReqBody = Body;
+    type Error = Infallible;
+    type Service = CodeService;
+    type MakeError = Infallible;
+    type Future = FutureResult<Self::Service, Infallible>;
+
+    fn make_service(&mut self, _: &conn::AddrStream) -> Self::Future {
+        future::ok(CodeService {
+            sender: RefCell::new(self.sender.replace(None)),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum RequestCodeError {
+    Server(hyper::error::Error),
+    Channel,
+}
+
+pub fn request_code(
+    client_id: &str,
+) -> (
+    String,
+    impl Future<Item = (String, String), Error = RequestCodeError>,
+) {
+    let port = 8478;
+
+    let (p, c) = oneshot::channel::<String>();
+
+    let recv = c.shared();
+    let shutdown = recv.clone().map(|_| ());
+
+    let addr = &([127, 0, 0, 1], port).into();
+
+    let factory = MkCodeService::new(p);
+
+    let server = Server::bind(addr)
+        .serve(factory)
+        .with_graceful_shutdown(shutdown)
+        .map_err(RequestCodeError::Server);
+
+    let redirect = format!("http://localhost:{}/", port);
+
+    let uri = authorize_url(client_id, &redirect);
+
+    (
+        uri,
+        recv.map_err(|_| RequestCodeError::Channel)
+            .join(server)
+            .map(|(result, _)| (result.deref().clone(), redirect)),
+    )
+}
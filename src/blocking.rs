@@ -0,0 +1,112 @@
+use crate::api::{ApiFuture, BangumiApi};
+use crate::auth::{self, AppCred, AuthResp};
+use crate::client::{
+    CollectionDetail, CollectionEntry, CollectionStatus, Episode, EpisodeStatus, SearchResult,
+    SubjectSmall, User,
+};
+use failure::Error;
+use std::collections::HashMap;
+use tokio::runtime::Runtime;
+
+/**
+ * Runs each request to completion on a private runtime,
+ * for programs that don't have an event loop of their own
+ */
+pub struct Client<A = crate::client::Client> {
+    api: A,
+    rt: Runtime,
+}
+
+impl<A: BangumiApi> Client<A> {
+    pub fn new(api: A) -> Result<Client<A>, Error> {
+        Ok(Client {
+            api,
+            rt: Runtime::new()?,
+        })
+    }
+
+    pub fn api(&self) -> &A {
+        &self.api
+    }
+
+    /**
+     * Waits for any request, including the ones without a shorthand below
+     */
+    pub fn call<T, F>(&mut self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&A) -> ApiFuture<T>,
+    {
+        let fut = f(&self.api);
+        self.rt.block_on(fut)
+    }
+
+    pub fn user(&mut self, uid: Option<u64>) -> Result<User, Error> {
+        self.call(|api| api.user(uid))
+    }
+
+    pub fn collection(&mut self, uid: Option<u64>) -> Result<Vec<CollectionEntry>, Error> {
+        self.call(|api| api.collection(uid))
+    }
+
+    pub fn collection_detail(&mut self, id: u64) -> Result<Option<CollectionDetail>, Error> {
+        self.call(|api| api.collection_detail(id))
+    }
+
+    pub fn update_collection_detail(
+        &mut self,
+        id: u64,
+        status: CollectionStatus,
+        aux: Option<CollectionDetail>,
+    ) -> Result<CollectionDetail, Error> {
+        self.call(|api| api.update_collection_detail(id, status, aux))
+    }
+
+    pub fn subject(&mut self, id: u64) -> Result<SubjectSmall, Error> {
+        self.call(|api| api.subject(id))
+    }
+
+    pub fn search(
+        &mut self,
+        keywords: &str,
+        len: usize,
+        skip: usize,
+    ) -> Result<SearchResult, Error> {
+        self.call(|api| api.search(keywords, len, skip))
+    }
+
+    pub fn progress(
+        &mut self,
+        coll: &CollectionEntry,
+        ep: Option<u64>,
+        vol: Option<u64>,
+    ) -> Result<(), Error> {
+        self.call(|api| api.progress(coll, ep, vol))
+    }
+
+    pub fn episodes(&mut self, id: u64) -> Result<Vec<Episode>, Error> {
+        self.call(|api| api.episodes(id))
+    }
+
+    pub fn episode_progress(&mut self, id: u64) -> Result<HashMap<u64, EpisodeStatus>, Error> {
+        self.call(|api| api.episode_progress(id))
+    }
+
+    pub fn update_episode(&mut self, ep: u64, status: Option<EpisodeStatus>) -> Result<(), Error> {
+        self.call(|api| api.update_episode(ep, status))
+    }
+}
+
+pub fn request_token(app_cred: AppCred, code: String, redirect: String) -> Result<AuthResp, Error> {
+    let fut = auth::request_token(app_cred, code, redirect);
+    Runtime::new()?.block_on(fut)
+}
+
+pub fn refresh_token(
+    app_cred: AppCred,
+    refresh: String,
+    redirect: String,
+) -> Result<AuthResp, Error> {
+    let fut = auth::refresh_token(app_cred, refresh, redirect);
+    Runtime::new()?.block_on(fut)
+}
@@ -1,15 +1,24 @@
-use crate::settings::Settings;
+use crate::clock::{Clock, SystemClock};
+use crate::lenient;
+use crate::settings::{Listener, Settings};
+use crate::transport::{Method, Request, ReqwestTransport, Response, ResponseFuture, Transport};
+use chrono::{Duration, NaiveDate};
+use failure::format_err;
+use futures::future;
+use futures::future::Loop;
 use futures::prelude::*;
-use reqwest::r#async as req;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration as StdDuration, Instant};
+use tokio::timer::Delay;
 use url::form_urlencoded;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct User {
-    username: String,
-    nickname: String,
+    pub username: String,
+    pub nickname: String,
 }
 
 enum_number!(SubjectType {
@@ -23,25 +32,261 @@ enum_number!(SubjectType {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SubjectSmall {
     pub id: u64,
-    pub air_date: String,
-    pub air_weekday: u8,
+    #[serde(default)]
+    pub air_date: AirDate,
+    #[serde(default)]
+    pub air_weekday: Weekday,
 
     pub name: String,
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub name_cn: String,
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub summary: String,
 
     #[serde(rename = "type")]
     pub subject_type: SubjectType,
 
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub url: String,
 
     pub vols_count: Option<u64>,
     pub eps_count: Option<u64>,
 }
 
+impl SubjectSmall {
+    /**
+     * Assumes one episode per week, starting from the air date
+     */
+    pub fn aired_eps(&self, date: NaiveDate) -> Option<u64> {
+        let start = self.air_date.date?;
+        if date < start {
+            return Some(0);
+        }
+
+        let aired = (date - start).num_days() as u64 / 7 + 1;
+        Some(match self.eps_count {
+            Some(total) if total > 0 && total < aired => total,
+            _ => aired,
+        })
+    }
+
+    /**
+     * The first airing on or after the given date. None if the subject has finished airing,
+     * or its schedule is unknown
+     */
+    pub fn next_airing(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let start = self.air_date.date?;
+        if date <= start {
+            return Some(start);
+        }
+
+        let aired = (date - start).num_days() / 7;
+        let next = if (date - start).num_days() % 7 == 0 {
+            aired
+        } else {
+            aired + 1
+        };
+
+        match self.eps_count {
+            Some(total) if total > 0 && next as u64 >= total => None,
+            _ => Some(start + Duration::weeks(next)),
+        }
+    }
+
+    pub fn is_airing(&self, date: NaiveDate) -> bool {
+        match self.air_date.date {
+            Some(start) => start <= date && self.next_airing(date).is_some(),
+            None => false,
+        }
+    }
+}
+
+/**
+ * bgm.tv uses "0000-00-00" or an empty string for unknown dates, so the raw value is kept
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AirDate {
+    pub raw: String,
+    pub date: Option<NaiveDate>,
+}
+
+impl AirDate {
+    pub fn parse(raw: String) -> AirDate {
+        let date = NaiveDate::parse_from_str(&raw, "%Y-%m-%d").ok();
+        AirDate { raw, date }
+    }
+}
+
+impl serde::Serialize for AirDate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for AirDate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        lenient::null_default(deserializer).map(AirDate::parse)
+    }
+}
+
+/**
+ * 1 to 7 for Monday to Sunday, 0 if unknown
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Weekday(#[serde(deserialize_with = "lenient::null_default")] pub u8);
+
+impl Weekday {
+    pub fn get(&self) -> Option<chrono::Weekday> {
+        use chrono::Weekday::*;
+        match self.0 {
+            1 => Some(Mon),
+            2 => Some(Tue),
+            3 => Some(Wed),
+            4 => Some(Thu),
+            5 => Some(Fri),
+            6 => Some(Sat),
+            7 => Some(Sun),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubjectRelation {
+    Adaptation,
+    Prequel,
+    Sequel,
+    Summary,
+    FullStory,
+    SideStory,
+    Character,
+    SameSetting,
+    AlternativeSetting,
+    AlternativeVersion,
+    SpinOff,
+    ParentStory,
+    Series,
+    Offprint,
+    Other,
+}
+
+impl SubjectRelation {
+    pub fn parse(relation: &str) -> SubjectRelation {
+        use crate::client::SubjectRelation::*;
+        match relation {
+            "改编" => Adaptation,
+            "前传" => Prequel,
+            "续集" => Sequel,
+            "总集篇" => Summary,
+            "全集" => FullStory,
+            "番外篇" => SideStory,
+            "角色出演" => Character,
+            "相同世界观" => SameSetting,
+            "不同世界观" => AlternativeSetting,
+            "不同演绎" => AlternativeVersion,
+            "衍生" => SpinOff,
+            "主线故事" => ParentStory,
+            "系列" => Series,
+            "单行本" => Offprint,
+            _ => Other,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RelatedSubject {
+    pub id: u64,
+
+    pub name: String,
+    pub name_cn: String,
+
+    #[serde(rename = "type")]
+    pub subject_type: SubjectType,
+
+    // Kept as-is, bgm.tv adds new relation types from time to time
+    pub relation: String,
+}
+
+impl RelatedSubject {
+    pub fn kind(&self) -> SubjectRelation {
+        SubjectRelation::parse(&self.relation)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Character {
+    pub id: u64,
+    pub name: String,
+
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Person {
+    pub id: u64,
+    pub name: String,
+
+    #[serde(default)]
+    pub career: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Actor {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubjectCharacter {
+    pub id: u64,
+    pub name: String,
+
+    // e.g. 主角, 配角
+    pub relation: String,
+
+    #[serde(default)]
+    pub actors: Vec<Actor>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubjectPerson {
+    pub id: u64,
+    pub name: String,
+
+    // e.g. 导演, 脚本
+    pub relation: String,
+}
+
+/**
+ * A subject that a character appeared in, or a person worked on
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreditedSubject {
+    pub id: u64,
+    pub name: String,
+    pub name_cn: String,
+
+    pub staff: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CharacterPerson {
+    pub id: u64,
+    pub name: String,
+
+    pub subject_id: u64,
+    pub subject_name: String,
+    pub subject_name_cn: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CollectionEntry {
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub ep_status: u64,
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub vol_status: u64,
 
     #[serde(with = "chrono::serde::ts_seconds")]
@@ -53,11 +298,7 @@ pub struct CollectionEntry {
 impl CollectionEntry {
     pub fn step_ep(&self, dist: i64) -> u64 {
         if dist < 0 {
-            if self.ep_status < (-dist) as u64 {
-                0
-            } else {
-                self.ep_status - ((-dist) as u64)
-            }
+            self.ep_status.saturating_sub((-dist) as u64)
         } else {
             let pending = self.ep_status + dist as u64;
             match self.subject.eps_count {
@@ -69,11 +310,7 @@ impl CollectionEntry {
 
     pub fn step_vol(&self, dist: i64) -> u64 {
         if dist < 0 {
-            if self.vol_status < (-dist) as u64 {
-                0
-            } else {
-                self.vol_status - ((-dist) as u64)
-            }
+            self.vol_status.saturating_sub((-dist) as u64)
         } else {
             let pending = self.vol_status + dist as u64;
             match self.subject.vols_count {
@@ -84,27 +321,26 @@ impl CollectionEntry {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
+/**
+ * Serialized as `{ "type": "wish" }`, which is how bgm.tv returns it
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum CollectionStatus {
-    #[serde(rename = "wish")]
     Wished,
-
-    #[serde(rename = "collect")]
     Done,
-
-    #[serde(rename = "do")]
+    #[default]
     Doing,
-
-    #[serde(rename = "on_hold")]
     OnHold,
-
-    #[serde(rename = "dropped")]
     Dropped,
+
+    /**
+     * A status bgm.tv added after this crate was written
+     */
+    Unknown(String),
 }
 
 impl CollectionStatus {
-    fn id(&self) -> &'static str {
+    fn id(&self) -> &str {
         use crate::client::CollectionStatus::*;
         match self {
             Wished => "wish",
@@ -112,24 +348,251 @@ impl CollectionStatus {
             Doing => "do",
             OnHold => "on_hold",
             Dropped => "dropped",
+            Unknown(id) => id,
+        }
+    }
+
+    fn from_id(id: String) -> CollectionStatus {
+        use crate::client::CollectionStatus::*;
+        match id.as_str() {
+            "wish" => Wished,
+            "collect" => Done,
+            "do" => Doing,
+            "on_hold" => OnHold,
+            "dropped" => Dropped,
+            _ => Unknown(id),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CollectionStatusRepr {
+    #[serde(rename = "type")]
+    id: String,
+}
+
+impl serde::Serialize for CollectionStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CollectionStatusRepr {
+            id: self.id().to_string(),
         }
+        .serialize(serializer)
     }
 }
 
-impl Default for CollectionStatus {
-    fn default() -> Self {
-        CollectionStatus::Doing
+impl<'de> serde::Deserialize<'de> for CollectionStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        CollectionStatusRepr::deserialize(deserializer)
+            .map(|repr| CollectionStatus::from_id(repr.id))
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CollectionDetail {
     pub status: CollectionStatus,
+
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub rating: u8,
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub comment: String,
+    #[serde(default, deserialize_with = "lenient::null_default")]
     pub tag: Vec<String>,
 }
 
+enum_number!(EpisodeType {
+    Main = 0,
+    Special = 1,
+    Opening = 2,
+    Ending = 3,
+    Trailer = 4,
+    Mad = 5,
+    Other = 6,
+});
+
+/**
+ * Whether an episode is on air yet, as bgm.tv sees it
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AirStatus {
+    #[serde(rename = "Air")]
+    Aired,
+    Today,
+    #[serde(rename = "NA")]
+    NotAired,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Episode {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub episode_type: EpisodeType,
+
+    /**
+     * The episode number, counted separately for each type
+     */
+    #[serde(default, deserialize_with = "lenient::null_default")]
+    pub sort: f64,
+
+    #[serde(default, deserialize_with = "lenient::null_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "lenient::null_default")]
+    pub name_cn: String,
+    #[serde(default)]
+    pub airdate: AirDate,
+    #[serde(default, deserialize_with = "lenient::null_default")]
+    pub status: AirStatus,
+}
+
+impl Episode {
+    /**
+     * The episode number without a trailing `.0`
+     */
+    pub fn number(&self) -> String {
+        if self.sort.fract() == 0.0 {
+            format!("{}", self.sort as i64)
+        } else {
+            format!("{}", self.sort)
+        }
+    }
+
+    pub fn is_aired(&self) -> bool {
+        self.is_aired_at(&SystemClock)
+    }
+
+    /**
+     * Falls back to the air date in local time when bgm.tv doesn't know
+     */
+    pub fn is_aired_at(&self, clock: &dyn Clock) -> bool {
+        let today = clock
+            .now()
+            .with_timezone(&chrono::Local)
+            .naive_local()
+            .date();
+        match self.status {
+            AirStatus::Aired | AirStatus::Today => true,
+            AirStatus::NotAired => false,
+            AirStatus::Unknown => self.airdate.date.is_some_and(|date| date <= today),
+        }
+    }
+}
+
+/**
+ * The mark of a single episode. Serialized as `{ "id": 2 }`, which is how bgm.tv returns it
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EpisodeStatus {
+    Queued,
+    Watched,
+    Dropped,
+}
+
+impl EpisodeStatus {
+    fn id(self) -> u64 {
+        match self {
+            EpisodeStatus::Queued => 1,
+            EpisodeStatus::Watched => 2,
+            EpisodeStatus::Dropped => 3,
+        }
+    }
+
+    fn from_id(id: u64) -> Option<EpisodeStatus> {
+        match id {
+            1 => Some(EpisodeStatus::Queued),
+            2 => Some(EpisodeStatus::Watched),
+            3 => Some(EpisodeStatus::Dropped),
+            _ => None,
+        }
+    }
+
+    /**
+     * The name used by `/ep/{id}/status/{name}`
+     */
+    fn url_name(self) -> &'static str {
+        match self {
+            EpisodeStatus::Queued => "queue",
+            EpisodeStatus::Watched => "watched",
+            EpisodeStatus::Dropped => "drop",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EpisodeStatusRepr {
+    id: u64,
+}
+
+impl serde::Serialize for EpisodeStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EpisodeStatusRepr { id: self.id() }.serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for EpisodeStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = EpisodeStatusRepr::deserialize(deserializer)?;
+        EpisodeStatus::from_id(repr.id)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown episode status {}", repr.id)))
+    }
+}
+
+#[derive(Deserialize)]
+struct SubjectEpisodes {
+    #[serde(default, deserialize_with = "lenient::list")]
+    eps: Vec<Episode>,
+}
+
+#[derive(Deserialize)]
+struct EpisodeProgress {
+    id: u64,
+    status: EpisodeStatus,
+}
+
+/**
+ * `null` if nothing of the subject is marked
+ */
+#[derive(Deserialize)]
+struct SubjectProgress {
+    #[serde(default, deserialize_with = "lenient::list")]
+    eps: Vec<EpisodeProgress>,
+}
+
+/**
+ * Returned by writes, even the failed ones, with the HTTP status in `code`
+ */
+#[derive(Deserialize)]
+struct StatusResp {
+    code: u16,
+    #[serde(default, deserialize_with = "lenient::null_default")]
+    error: String,
+}
+
+/**
+ * Fails if bgm.tv rejected the write, either by the HTTP status or by the code in the body
+ */
+fn accepted(resp: &Response) -> Result<(), failure::Error> {
+    let body = resp.json::<StatusResp>().ok();
+    let code = match body {
+        Some(ref body) if body.code >= 400 => body.code,
+        _ => resp.status,
+    };
+
+    if code < 400 {
+        return Ok(());
+    }
+
+    match body {
+        Some(ref body) if !body.error.is_empty() => Err(format_err!("{}: {}", code, body.error)),
+        _ => Err(format_err!(
+            "{}: {}",
+            code,
+            String::from_utf8_lossy(&resp.body)
+        )),
+    }
+}
+
 #[derive(Serialize)]
 struct ProgressPayload {
     pub watched_eps: String,
@@ -143,97 +606,381 @@ pub enum APIResp<T> {
     Success(T),
 }
 
-pub struct Client {
-    settings: Settings,
+/**
+ * Clones share the same transport and settings,
+ * so a token refreshed through one of them is seen by all
+ */
+pub struct Client<T = ReqwestTransport> {
+    inner: Arc<ClientInner<T>>,
+    retries: u32,
+    retry_delay: StdDuration,
 }
 
-trait ClientAuthBearer {
-    fn apply_auth(self, info: &Client) -> Self;
+/**
+ * Wait before the first retry, doubled for each one after
+ */
+const RETRY_DELAY_MS: u64 = 500;
+
+struct ClientInner<T> {
+    settings: RwLock<Settings>,
+    transport: T,
+    listeners: Mutex<Vec<Arc<Listener>>>,
+
+    /**
+     * The refresh after a 401, shared by all the requests rejected meanwhile
+     */
+    refreshing: Mutex<Option<RefreshFuture>>,
 }
 
-impl ClientAuthBearer for req::RequestBuilder {
-    fn apply_auth(self, info: &Client) -> Self {
-        if let Some(handle) = info.settings.auth() {
-            self.header(
-                "Authorization",
-                format!("Bearer {}", handle.info.access_token),
-            )
-        } else {
-            self
+type RefreshFuture =
+    future::Shared<Box<dyn Future<Item = Settings, Error = failure::Error> + Send>>;
+
+impl<T> Clone for Client<T> {
+    fn clone(&self) -> Self {
+        Client {
+            inner: self.inner.clone(),
+            retries: self.retries,
+            retry_delay: self.retry_delay,
         }
     }
 }
 
+type RetryFuture = Box<dyn Future<Item = Loop<Response, u32>, Error = failure::Error> + Send>;
+
+/**
+ * Starts attempt number `retry` after waiting for `delay`
+ */
+fn retry_after(retry: u32, delay: StdDuration) -> RetryFuture {
+    if delay == StdDuration::from_secs(0) {
+        return Box::new(future::ok(Loop::Continue(retry)));
+    }
+
+    Box::new(
+        Delay::new(Instant::now() + delay)
+            .map_err(failure::Error::from)
+            .map(move |_| Loop::Continue(retry)),
+    )
+}
+
 #[derive(Deserialize, Clone)]
 pub struct SearchResultRaw {
     #[serde(rename = "results")]
     pub count: usize,
-    pub list: Option<Vec<SubjectSmall>>,
+    #[serde(default, deserialize_with = "lenient::list")]
+    pub list: Vec<SubjectSmall>,
 }
 
-#[derive(Clone)]
+/**
+ * The collection list, without the entries that failed to decode
+ */
+#[derive(Deserialize)]
+struct CollectionList(#[serde(deserialize_with = "lenient::list")] Vec<CollectionEntry>);
+
+#[derive(Clone, Default)]
 pub struct SearchResult {
     pub count: usize,
     pub list: Vec<SubjectSmall>,
 }
 
-impl Default for SearchResult {
-    fn default() -> Self {
-        Self {
-            count: 0,
-            list: Vec::new(),
-        }
+impl Client {
+    pub fn new(settings: Settings) -> Client {
+        Client::with_transport(settings, ReqwestTransport::new())
     }
 }
 
-impl Client {
-    pub fn new(settings: Settings) -> Client {
-        Client { settings: settings }
+impl<T: Transport> Client<T> {
+    pub fn with_transport(settings: Settings, transport: T) -> Client<T> {
+        Client {
+            inner: Arc::new(ClientInner {
+                settings: RwLock::new(settings),
+                transport,
+                listeners: Mutex::new(Vec::new()),
+                refreshing: Mutex::new(None),
+            }),
+            retries: 0,
+            retry_delay: StdDuration::from_millis(RETRY_DELAY_MS),
+        }
+    }
+
+    /**
+     * Failed GET requests are attempted again up to `retries` times.
+     * Other requests are never retried
+     */
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /**
+     * Wait before the first retry. Each retry after that waits twice as long as the previous one,
+     * so that a struggling server or a flaky connection gets some time to recover
+     */
+    pub fn retry_delay(mut self, delay: StdDuration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.inner.transport
+    }
+
+    /**
+     * A snapshot of the current settings, e.g. for saving after a refresh
+     */
+    pub fn settings(&self) -> Settings {
+        self.inner.settings.read().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: Settings) {
+        *self.inner.settings.write().unwrap() = settings;
+    }
+
+    /**
+     * Called with the updated settings whenever the token is refreshed after bgm.tv
+     * rejected it with 401, so that they can be persisted
+     */
+    pub fn on_refresh(&self, listener: Listener) {
+        self.inner
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Arc::new(listener));
+    }
+
+    /**
+     * Refreshes the access token for all clones of this client.
+     * Resolves to the updated settings, which the caller should persist
+     */
+    pub fn refresh_auth(&self) -> impl Future<Item = Settings, Error = failure::Error> {
+        let settings = self.settings();
+        let handle = match settings.auth() {
+            Some(handle) => handle.clone(),
+            None => return future::Either::B(future::err(format_err!("Not logged in"))),
+        };
+
+        let inner = self.inner.clone();
+        let fut = handle
+            .refresh_with(&self.inner.transport, settings.cred().clone(), &SystemClock)
+            .and_then(move |resp| match resp {
+                Ok(handle) => {
+                    let mut guard = inner.settings.write().unwrap();
+                    *guard = guard.clone().update_handle(handle);
+                    Ok(guard.clone())
+                }
+                Err(e) => Err(format_err!("{}: {}", e.error(), e.description())),
+            });
+
+        future::Either::A(fut)
+    }
+
+    /**
+     * Refreshes the token unless a refresh is already on its way, notifying the listeners once done
+     */
+    fn refresh_once(&self) -> RefreshFuture {
+        let mut refreshing = self.inner.refreshing.lock().unwrap();
+        if let Some(ref fut) = *refreshing {
+            return fut.clone();
+        }
+
+        let inner = self.inner.clone();
+        let fut: Box<dyn Future<Item = Settings, Error = failure::Error> + Send> =
+            Box::new(self.refresh_auth().then(move |result| {
+                inner.refreshing.lock().unwrap().take();
+
+                if let Ok(ref settings) = result {
+                    // Listeners may register more listeners
+                    let listeners = inner.listeners.lock().unwrap().clone();
+                    for listener in listeners.iter() {
+                        listener(settings);
+                    }
+                }
+                result
+            }));
+
+        let shared = fut.shared();
+        *refreshing = Some(shared.clone());
+        shared
+    }
+
+    fn user_id(&self) -> u64 {
+        self.inner
+            .settings
+            .read()
+            .unwrap()
+            .auth()
+            .as_ref()
+            .unwrap()
+            .info
+            .user_id
+    }
+
+    fn access_token(&self) -> Option<String> {
+        self.inner
+            .settings
+            .read()
+            .unwrap()
+            .auth()
+            .as_ref()
+            .map(|handle| handle.info.access_token.clone())
+    }
+
+    /**
+     * Sends the request, refreshing the token and sending it once more if bgm.tv rejects
+     * the token, as allowed by the refresh policy
+     */
+    fn send(&self, req: Request) -> ResponseFuture {
+        let token = self.access_token();
+        let client = self.clone();
+        let retry = req.clone();
+
+        let fut = self.send_once(req).and_then(move |resp| {
+            let policy = client.settings().refresh_policy().clone();
+            if token.is_none() || !policy.refresh_on(resp.status) {
+                return Box::new(future::ok(resp)) as ResponseFuture;
+            }
+
+            // Another request refreshed it in the meantime
+            if client.access_token() != token {
+                return client.send_once(retry);
+            }
+
+            warn!(target: "bgmtv::request", "token rejected, refreshing");
+            Box::new(client.refresh_once().then(move |result| match result {
+                Ok(_) => client.send_once(retry),
+                Err(e) => {
+                    warn!(target: "bgmtv::request", "unable to refresh token: {}", e);
+                    Box::new(future::ok(resp))
+                }
+            }))
+        });
+
+        Box::new(fut)
+    }
+
+    fn send_once(&self, req: Request) -> ResponseFuture {
+        let req = if let Some(handle) = self.inner.settings.read().unwrap().auth() {
+            req.header(
+                "Authorization",
+                format!("Bearer {}", handle.info.access_token),
+            )
+        } else {
+            req
+        };
+
+        let inner = self.inner.clone();
+        let retries = if req.method == Method::Get {
+            self.retries
+        } else {
+            0
+        };
+        let retry_delay = self.retry_delay;
+
+        let fut = future::loop_fn(0, move |retry| {
+            trace!(
+                target: "bgmtv::request",
+                "method={} endpoint={} headers={:?} retry={}",
+                req.method,
+                req.endpoint(),
+                req.redacted_headers(),
+                retry
+            );
+
+            let method = req.method;
+            let endpoint = req.endpoint().to_string();
+            let start = Instant::now();
+            let backoff = retry_delay * 2u32.pow(retry.min(16));
+
+            inner.transport.send(req.clone()).then(move |result| {
+                let elapsed = start.elapsed();
+                let latency = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+
+                match result {
+                    Ok(resp) => {
+                        debug!(
+                            target: "bgmtv::request",
+                            "method={} endpoint={} status={} latency={}ms retry={}",
+                            method,
+                            endpoint,
+                            resp.status,
+                            latency,
+                            retry
+                        );
+
+                        if resp.status >= 500 && retry < retries {
+                            retry_after(retry + 1, backoff)
+                        } else {
+                            Box::new(future::ok(Loop::Break(resp)))
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            target: "bgmtv::request",
+                            "method={} endpoint={} error={} latency={}ms retry={}",
+                            method,
+                            endpoint,
+                            e,
+                            latency,
+                            retry
+                        );
+
+                        if retry < retries {
+                            retry_after(retry + 1, backoff)
+                        } else {
+                            Box::new(future::err(e))
+                        }
+                    }
+                }
+            })
+        });
+
+        Box::new(fut)
+    }
+
+    /**
+     * Sends a request whose body may have failed to serialize
+     */
+    fn try_send(
+        &self,
+        req: Result<Request, failure::Error>,
+    ) -> future::Either<ResponseFuture, future::FutureResult<Response, failure::Error>> {
+        match req {
+            Ok(req) => future::Either::A(self.send(req)),
+            Err(e) => future::Either::B(future::err(e)),
+        }
     }
 
     pub fn user(&self, uid: Option<u64>) -> impl Future<Item = User, Error = failure::Error> {
-        let c = req::Client::new();
-        let uid = uid.unwrap_or(self.settings.auth().as_ref().unwrap().info.user_id);
-        c.get(&format!("{}/user/{}", API_ROOT!(), uid))
-            .apply_auth(self)
-            .send()
-            .and_then(|mut resp| resp.json())
-            .map_err(|e| e.into())
+        let uid = uid.unwrap_or_else(|| self.user_id());
+        self.send(Request::get(format!("{}/user/{}", API_ROOT!(), uid)))
+            .and_then(|resp| resp.json())
     }
 
     pub fn collection(
         &self,
         uid: Option<u64>,
     ) -> impl Future<Item = Vec<CollectionEntry>, Error = failure::Error> {
-        let c = req::Client::new();
-        let uid = uid.unwrap_or(self.settings.auth().as_ref().unwrap().info.user_id);
-        c.get(&format!(
+        let uid = uid.unwrap_or_else(|| self.user_id());
+        self.send(Request::get(format!(
             "{}/user/{}/collection?cat=all_watching",
             API_ROOT!(),
             uid
-        ))
-        .apply_auth(self)
-        .send()
-        .and_then(|mut resp| resp.json())
-        .map_err(|e| e.into())
+        )))
+        .and_then(|resp| resp.json())
+        .map(|list: CollectionList| list.0)
     }
 
     pub fn collection_detail(
         &self,
         id: u64,
     ) -> impl Future<Item = Option<CollectionDetail>, Error = failure::Error> {
-        let c = req::Client::new();
-        c.get(&format!("{}/collection/{}", API_ROOT!(), id))
-            .apply_auth(self)
-            .send()
-            .and_then(|mut resp| resp.json())
+        self.send(Request::get(format!("{}/collection/{}", API_ROOT!(), id)))
+            .and_then(|resp| resp.json())
             .map(|resp: APIResp<CollectionDetail>| {
                 match resp {
                     APIResp::Error { .. } => None, // TODO: handle other errors
                     APIResp::Success(payload) => Some(payload),
                 }
             })
-            .map_err(|e| e.into())
     }
 
     pub fn update_collection_detail(
@@ -242,8 +989,6 @@ impl Client {
         status: CollectionStatus,
         aux: Option<CollectionDetail>,
     ) -> impl Future<Item = CollectionDetail, Error = failure::Error> {
-        let c = req::Client::new();
-
         let mut payload = HashMap::new();
         payload.insert("status", status.id().to_string());
         if let Some(content) = aux {
@@ -252,21 +997,101 @@ impl Client {
             payload.insert("tags", content.tag.join(","));
         }
 
-        c.post(&format!("{}/collection/{}/update", API_ROOT!(), id,))
-            .form(&payload)
-            .apply_auth(self)
-            .send()
-            .and_then(|mut resp| resp.json())
-            .map_err(|e| e.into())
+        self.try_send(
+            Request::post(format!("{}/collection/{}/update", API_ROOT!(), id,)).form(&payload),
+        )
+        .and_then(|resp| resp.json())
     }
 
     pub fn subject(&self, id: u64) -> impl Future<Item = SubjectSmall, Error = failure::Error> {
-        let c = req::Client::new();
-        c.get(&format!("{}/subject/{}", API_ROOT!(), id))
-            .apply_auth(self)
-            .send()
-            .and_then(|mut resp| resp.json())
-            .map_err(|e| e.into())
+        self.send(Request::get(format!("{}/subject/{}", API_ROOT!(), id)))
+            .and_then(|resp| resp.json())
+    }
+
+    pub fn related_subjects(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = Vec<RelatedSubject>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/subjects/{}/subjects",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+    }
+
+    pub fn subject_characters(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = Vec<SubjectCharacter>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/subjects/{}/characters",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+    }
+
+    pub fn subject_persons(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = Vec<SubjectPerson>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/subjects/{}/persons",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+    }
+
+    pub fn character(&self, id: u64) -> impl Future<Item = Character, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/characters/{}",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+    }
+
+    pub fn character_subjects(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = Vec<CreditedSubject>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/characters/{}/subjects",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+    }
+
+    pub fn character_persons(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = Vec<CharacterPerson>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/characters/{}/persons",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+    }
+
+    pub fn person(&self, id: u64) -> impl Future<Item = Person, Error = failure::Error> {
+        self.send(Request::get(format!("{}/v0/persons/{}", API_ROOT!(), id)))
+            .and_then(|resp| resp.json())
+    }
+
+    pub fn person_subjects(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = Vec<CreditedSubject>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/v0/persons/{}/subjects",
+            API_ROOT!(),
+            id
+        )))
+        .and_then(|resp| resp.json())
     }
 
     pub fn progress(
@@ -287,17 +1112,68 @@ impl Client {
             },
         };
 
-        let c = req::Client::new();
-        c.post(&format!(
-            "{}/subject/{}/update/watched_eps",
+        self.try_send(
+            Request::post(format!(
+                "{}/subject/{}/update/watched_eps",
+                API_ROOT!(),
+                coll.subject.id,
+            ))
+            .form(&payload),
+        )
+        .and_then(|resp| accepted(&resp))
+    }
+
+    pub fn episodes(&self, id: u64) -> impl Future<Item = Vec<Episode>, Error = failure::Error> {
+        self.send(Request::get(format!("{}/subject/{}/ep", API_ROOT!(), id)))
+            .and_then(|resp| resp.json())
+            .map(|eps: SubjectEpisodes| eps.eps)
+    }
+
+    /**
+     * Marks of the episodes of a subject, by episode id. Unmarked episodes are left out
+     */
+    pub fn episode_progress(
+        &self,
+        id: u64,
+    ) -> impl Future<Item = HashMap<u64, EpisodeStatus>, Error = failure::Error> {
+        self.send(Request::get(format!(
+            "{}/user/{}/progress?subject_id={}",
             API_ROOT!(),
-            coll.subject.id,
-        ))
-        .apply_auth(self)
-        .form(&payload)
-        .send()
-        .map(|_| ()) // TODO: handle response
-        .map_err(|e| e.into())
+            self.user_id(),
+            id
+        )))
+        .and_then(|resp| resp.json())
+        .map(|progress: Option<SubjectProgress>| {
+            progress
+                .map(|p| p.eps.into_iter().map(|ep| (ep.id, ep.status)).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    /**
+     * Marks a single episode, or removes its mark with `None`
+     */
+    pub fn update_episode(
+        &self,
+        ep: u64,
+        status: Option<EpisodeStatus>,
+    ) -> impl Future<Item = (), Error = failure::Error> {
+        let name = status.map_or("remove", EpisodeStatus::url_name);
+
+        self.send(Request::post(format!(
+            "{}/ep/{}/status/{}",
+            API_ROOT!(),
+            ep,
+            name
+        )))
+        .and_then(|resp| resp.json())
+        .and_then(|resp: StatusResp| {
+            if resp.code >= 400 {
+                Err(format_err!("{}: {}", resp.code, resp.error))
+            } else {
+                Ok(())
+            }
+        })
     }
 
     pub fn search(
@@ -308,24 +1184,532 @@ impl Client {
     ) -> impl Future<Item = SearchResult, Error = failure::Error> {
         let keywords = itertools::join(form_urlencoded::byte_serialize(keywords.as_bytes()), "");
 
-        let c = req::Client::new();
-        c.get(&format!(
+        self.send(Request::get(format!(
             "{}/search/subject/{}?start={}&max_results={}",
             API_ROOT!(),
             keywords,
             skip,
             len,
-        ))
-        .apply_auth(self)
-        .send()
-        .and_then(|mut resp| resp.json())
+        )))
+        .and_then(|resp| resp.json())
         .map(|resp: APIResp<SearchResultRaw>| match resp {
             APIResp::Success(r) => SearchResult {
                 count: r.count,
-                list: r.list.unwrap_or_else(Vec::new),
+                list: r.list,
             },
             APIResp::Error { .. } => SearchResult::default(),
         })
-        .map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AppCred, AuthInfo};
+    use crate::clock::FixedClock;
+    use crate::consts::OAUTH_ACCESS_TOKEN;
+    use crate::settings::AuthHandle;
+    use crate::transport::{is_connection_error, FakeTransport};
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn settings(token: &str) -> Settings {
+        let auth = AuthHandle {
+            info: AuthInfo {
+                access_token: token.to_string(),
+                user_id: 42,
+                refresh_token: "refresh".to_string(),
+                expires_in: 604800,
+            },
+            time: chrono::Utc::now(),
+            redirect: String::new(),
+        };
+        Settings::new(
+            AppCred::new("id".to_string(), "secret".to_string()),
+            Some(auth),
+        )
+    }
+
+    fn client() -> (Client<FakeTransport>, FakeTransport) {
+        let transport = FakeTransport::new();
+        (
+            Client::with_transport(settings("token"), transport.clone()),
+            transport,
+        )
+    }
+
+    fn subject(id: u64, subject_type: u64) -> serde_json::Value {
+        json!({
+            "id": id,
+            "name": format!("Subject {}", id),
+            "name_cn": null,
+            "type": subject_type,
+            "eps_count": 12,
+            "vols_count": null,
+        })
+    }
+
+    fn respond_refresh(transport: &FakeTransport, token: &str) {
+        transport.respond_json(
+            Method::Post,
+            OAUTH_ACCESS_TOKEN,
+            &json!({
+                "access_token": token,
+                "user_id": 42,
+                "refresh_token": "refreshed",
+                "expires_in": 604800,
+            }),
+        );
+    }
+
+    #[test]
+    fn decodes_collection() {
+        let (client, transport) = client();
+        transport.respond_json(
+            Method::Get,
+            "https://api.bgm.tv/user/42/collection",
+            &json!([
+                { "ep_status": 3, "vol_status": null, "lasttouch": 1546300800, "subject": subject(1, 2) },
+                { "ep_status": 1, "lasttouch": "broken", "subject": subject(2, 2) },
+                { "ep_status": 0, "vol_status": 2, "lasttouch": 1546300800, "subject": subject(3, 1) },
+            ]),
+        );
+
+        let collection = client.collection(None).wait().unwrap();
+        assert_eq!(collection.len(), 2);
+        assert_eq!(collection[0].subject.id, 1);
+        assert_eq!(collection[0].ep_status, 3);
+        assert_eq!(collection[0].vol_status, 0);
+        assert_eq!(collection[0].lasttouch.timestamp(), 1546300800);
+        assert_eq!(collection[0].subject.name_cn, "");
+        assert_eq!(collection[1].subject.subject_type, SubjectType::Book);
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url,
+            "https://api.bgm.tv/user/42/collection?cat=all_watching"
+        );
+        assert!(requests[0]
+            .headers
+            .contains(&("Authorization".to_string(), "Bearer token".to_string())));
+    }
+
+    #[test]
+    fn decodes_missing_collection_detail() {
+        let (client, transport) = client();
+        transport.respond_json(
+            Method::Get,
+            "https://api.bgm.tv/collection/1",
+            &json!({ "code": 400, "error": "Nothing found with that ID" }),
+        );
+
+        assert!(client.collection_detail(1).wait().unwrap().is_none());
+    }
+
+    #[test]
+    fn keeps_unknown_subject_types() {
+        let subject: SubjectSmall = serde_json::from_value(subject(1, 99)).unwrap();
+        assert_eq!(subject.subject_type, SubjectType::Unknown(99));
+        assert_eq!(subject.subject_type.value(), 99);
+        assert_eq!(serde_json::to_value(&subject).unwrap()["type"], 99);
+
+        let known: SubjectType = serde_json::from_value(json!(2)).unwrap();
+        assert_eq!(known, SubjectType::Anime);
+    }
+
+    #[test]
+    fn keeps_unknown_collection_statuses() {
+        let detail: CollectionDetail = serde_json::from_value(json!({
+            "status": { "type": "new" },
+            "rating": 7,
+        }))
+        .unwrap();
+        assert_eq!(detail.status, CollectionStatus::Unknown("new".to_string()));
+        assert_eq!(
+            serde_json::to_value(&detail.status).unwrap(),
+            json!({ "type": "new" })
+        );
+
+        let known: CollectionStatus = serde_json::from_value(json!({ "type": "on_hold" })).unwrap();
+        assert_eq!(known, CollectionStatus::OnHold);
+        assert_eq!(
+            serde_json::to_value(&known).unwrap(),
+            json!({ "type": "on_hold" })
+        );
+    }
+
+    #[test]
+    fn posts_progress_as_form() {
+        let (client, transport) = client();
+        transport.respond(
+            Method::Post,
+            "https://api.bgm.tv/subject/3/update/watched_eps",
+            200,
+            r#"{"request":"/subject/3/update/watched_eps","code":202,"error":"Accepted"}"#,
+        );
+
+        let entry: CollectionEntry = serde_json::from_value(json!({
+            "ep_status": 0,
+            "vol_status": 2,
+            "lasttouch": 1546300800,
+            "subject": subject(3, 1),
+        }))
+        .unwrap();
+        client.progress(&entry, Some(5), Some(3)).wait().unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::Post);
+        assert_eq!(
+            String::from_utf8(requests[0].body.clone()).unwrap(),
+            "watched_eps=5&watched_vols=3"
+        );
+        assert!(requests[0].headers.contains(&(
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded".to_string()
+        )));
+    }
+
+    #[test]
+    fn reports_rejected_progress() {
+        let (client, transport) = client();
+        let endpoint = "https://api.bgm.tv/subject/3/update/watched_eps";
+        let entry: CollectionEntry = serde_json::from_value(json!({
+            "ep_status": 0,
+            "lasttouch": 1546300800,
+            "subject": subject(3, 2),
+        }))
+        .unwrap();
+
+        transport.respond(
+            Method::Post,
+            endpoint,
+            200,
+            r#"{"code":400,"error":"Bad Request"}"#,
+        );
+        let err = client.progress(&entry, Some(5), None).wait().unwrap_err();
+        assert_eq!(err.to_string(), "400: Bad Request");
+        assert!(!is_connection_error(&err));
+
+        transport.respond(Method::Post, endpoint, 503, "<html>");
+        let err = client.progress(&entry, Some(5), None).wait().unwrap_err();
+        assert_eq!(err.to_string(), "503: <html>");
+    }
+
+    #[test]
+    fn retries_failed_gets_with_backoff() {
+        let (client, transport) = client();
+        let client = client.retries(2).retry_delay(StdDuration::from_millis(20));
+        let endpoint = "https://api.bgm.tv/subject/1";
+        transport
+            .fail_next(Method::Get, endpoint, "Connection reset")
+            .fail_next(Method::Get, endpoint, "Connection reset")
+            .respond_json(Method::Get, endpoint, &subject(1, 2));
+
+        let start = Instant::now();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let subject = runtime.block_on(client.subject(1)).unwrap();
+
+        assert_eq!(subject.id, 1);
+        assert_eq!(transport.requests().len(), 3);
+        // 20ms before the first retry, 40ms before the second
+        assert!(start.elapsed() >= StdDuration::from_millis(60));
+    }
+
+    #[test]
+    fn never_retries_posts() {
+        let (client, transport) = client();
+        let client = client.retries(2).retry_delay(StdDuration::from_secs(0));
+        let endpoint = "https://api.bgm.tv/collection/1/update";
+        transport
+            .fail_next(Method::Post, endpoint, "Connection reset")
+            .respond_json(
+                Method::Post,
+                endpoint,
+                &json!({ "status": { "type": "do" } }),
+            );
+
+        assert!(client
+            .update_collection_detail(1, CollectionStatus::Doing, None)
+            .wait()
+            .is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn resends_with_token_refreshed_meanwhile() {
+        let (client, transport) = client();
+        let endpoint = "https://api.bgm.tv/subject/1";
+        transport
+            .respond_next(
+                Method::Get,
+                endpoint,
+                401,
+                r#"{"code":401,"error":"Unauthorized"}"#,
+            )
+            .respond_json(Method::Get, endpoint, &subject(1, 2));
+
+        // Sent right away, but only looked at when polled
+        let fut = client.subject(1);
+        client.update_settings(settings("fresh"));
+        assert_eq!(fut.wait().unwrap().id, 1);
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1]
+            .headers
+            .contains(&("Authorization".to_string(), "Bearer fresh".to_string())));
+    }
+
+    #[test]
+    fn refreshes_through_transport() {
+        let (client, transport) = client();
+        respond_refresh(&transport, "fresh");
+
+        let settings = client.refresh_auth().wait().unwrap();
+        assert_eq!(client.access_token(), Some("fresh".to_string()));
+        assert_eq!(
+            settings.auth().as_ref().unwrap().info.refresh_token,
+            "refreshed"
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["grant_type"], "refresh_token");
+        assert_eq!(body["refresh_token"], "refresh");
+    }
+
+    #[test]
+    fn refreshes_and_resends_on_unauthorized() {
+        let (client, transport) = client();
+        let endpoint = "https://api.bgm.tv/subject/1";
+        transport
+            .respond_next(
+                Method::Get,
+                endpoint,
+                401,
+                r#"{"code":401,"error":"Unauthorized"}"#,
+            )
+            .respond_json(Method::Get, endpoint, &subject(1, 2));
+        respond_refresh(&transport, "fresh");
+
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let listener = saved.clone();
+        let registrar = client.clone();
+        client.on_refresh(Box::new(move |settings: &Settings| {
+            let token = settings.auth().as_ref().unwrap().info.access_token.clone();
+            listener.lock().unwrap().push(token);
+            // Must not deadlock
+            registrar.on_refresh(Box::new(|_| {}));
+        }));
+
+        assert_eq!(client.subject(1).wait().unwrap().id, 1);
+        assert_eq!(*saved.lock().unwrap(), vec!["fresh".to_string()]);
+
+        let requests = transport.requests();
+        let endpoints = requests.iter().map(|r| r.endpoint()).collect::<Vec<_>>();
+        assert_eq!(endpoints, vec![endpoint, OAUTH_ACCESS_TOKEN, endpoint]);
+        assert!(requests[2]
+            .headers
+            .contains(&("Authorization".to_string(), "Bearer fresh".to_string())));
+    }
+
+    #[test]
+    fn shares_refresh_in_flight() {
+        let (client, transport) = client();
+        respond_refresh(&transport, "fresh");
+
+        let count = Arc::new(Mutex::new(0));
+        let listener = count.clone();
+        client.on_refresh(Box::new(move |_| *listener.lock().unwrap() += 1));
+
+        let first = client.refresh_once();
+        let second = client.refresh_once();
+        first.join(second).wait().unwrap();
+
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(*count.lock().unwrap(), 1);
+
+        // Done, so the next rejection refreshes again
+        client.refresh_once().wait().unwrap();
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn keeps_rejection_without_refresh_policy() {
+        let (client, transport) = client();
+        let mut policy = client.settings().refresh_policy().clone();
+        policy.on_unauthorized = false;
+        client.update_settings(client.settings().set_refresh_policy(policy));
+
+        let endpoint = "https://api.bgm.tv/subject/1";
+        transport.respond(
+            Method::Get,
+            endpoint,
+            401,
+            r#"{"code":401,"error":"Unauthorized"}"#,
+        );
+
+        assert!(client.subject(1).wait().is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn episode_aired_by_status_or_date() {
+        let episode = |status: &str, airdate: &str| -> Episode {
+            serde_json::from_value(json!({
+                "id": 1,
+                "type": 0,
+                "sort": 1,
+                "airdate": airdate,
+                "status": status,
+            }))
+            .unwrap()
+        };
+        let noon = |date: &str| {
+            let local = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            chrono::Local
+                .from_local_datetime(&local)
+                .unwrap()
+                .with_timezone(&chrono::Utc)
+        };
+        let clock = FixedClock::new(noon("2019-01-10"));
+
+        assert!(episode("Air", "2019-02-01").is_aired_at(&clock));
+        assert!(!episode("NA", "2019-01-01").is_aired_at(&clock));
+        assert!(episode("", "2019-01-10").is_aired_at(&clock));
+        assert!(!episode("", "2019-01-11").is_aired_at(&clock));
+        assert!(!episode("", "").is_aired_at(&clock));
+
+        clock.set(noon("2019-01-11"));
+        assert!(episode("", "2019-01-11").is_aired_at(&clock));
+    }
+
+    fn airing(air_date: &str, eps_count: u64) -> SubjectSmall {
+        serde_json::from_value(json!({
+            "id": 1,
+            "name": "Subject 1",
+            "type": 2,
+            "air_date": air_date,
+            "air_weekday": 1,
+            "eps_count": eps_count,
+        }))
+        .unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn keeps_unknown_air_dates() {
+        for raw in ["0000-00-00", ""].iter() {
+            let subject = airing(raw, 12);
+            assert_eq!(subject.air_date.raw, *raw);
+            assert_eq!(subject.air_date.date, None);
+            assert_eq!(serde_json::to_value(&subject).unwrap()["air_date"], *raw);
+
+            assert_eq!(subject.aired_eps(date("2019-01-14")), None);
+            assert_eq!(subject.next_airing(date("2019-01-14")), None);
+            assert!(!subject.is_airing(date("2019-01-14")));
+        }
+
+        let subject: SubjectSmall = serde_json::from_value(json!({
+            "id": 1,
+            "name": "Subject 1",
+            "type": 2,
+            "air_date": null,
+            "air_weekday": null,
+        }))
+        .unwrap();
+        assert_eq!(subject.air_date, AirDate::default());
+        assert_eq!(subject.air_weekday.get(), None);
+    }
+
+    #[test]
+    fn reads_weekdays() {
+        assert_eq!(Weekday(0).get(), None);
+        assert_eq!(Weekday(1).get(), Some(chrono::Weekday::Mon));
+        assert_eq!(Weekday(7).get(), Some(chrono::Weekday::Sun));
+        assert_eq!(Weekday(8).get(), None);
+        assert_eq!(
+            airing("2019-01-07", 12).air_weekday.get(),
+            Some(chrono::Weekday::Mon)
+        );
+    }
+
+    #[test]
+    fn counts_aired_eps_as_of_a_date() {
+        let subject = airing("2019-01-07", 12);
+        assert_eq!(subject.aired_eps(date("2019-01-06")), Some(0));
+        assert_eq!(subject.aired_eps(date("2019-01-07")), Some(1));
+        assert_eq!(subject.aired_eps(date("2019-01-13")), Some(1));
+        assert_eq!(subject.aired_eps(date("2019-01-14")), Some(2));
+        assert_eq!(subject.aired_eps(date("2020-01-01")), Some(12));
+
+        // bgm.tv reports 0 eps when it doesn't know
+        assert_eq!(
+            airing("2019-01-07", 0).aired_eps(date("2020-01-01")),
+            Some(52)
+        );
+    }
+
+    #[test]
+    fn rolls_next_airing_over_to_next_week() {
+        let subject = airing("2019-01-07", 12);
+        let clock = FixedClock::new("2019-01-14T12:00:00Z".parse().unwrap());
+        let today = || clock.now().naive_utc().date();
+
+        // Airs today, then on the following Monday for the rest of the week
+        assert_eq!(subject.next_airing(today()), Some(date("2019-01-14")));
+        for _ in 0..6 {
+            clock.advance(Duration::days(1));
+            assert_eq!(subject.next_airing(today()), Some(date("2019-01-21")));
+        }
+        clock.advance(Duration::days(1));
+        assert_eq!(subject.next_airing(today()), Some(date("2019-01-21")));
+
+        assert_eq!(
+            subject.next_airing(date("2019-01-01")),
+            Some(date("2019-01-07"))
+        );
+        assert!(!subject.is_airing(date("2019-01-01")));
+        assert!(subject.is_airing(date("2019-01-07")));
+
+        // The 12th and last episode airs on 03-25
+        assert_eq!(
+            subject.next_airing(date("2019-03-25")),
+            Some(date("2019-03-25"))
+        );
+        assert!(subject.is_airing(date("2019-03-25")));
+        assert_eq!(subject.next_airing(date("2019-03-26")), None);
+        assert!(!subject.is_airing(date("2019-03-26")));
+    }
+
+    #[test]
+    fn reports_unscripted_requests() {
+        let (client, transport) = client();
+        transport.fail_next(
+            Method::Get,
+            "https://api.bgm.tv/subject/1",
+            "Connection reset",
+        );
+
+        let err = client.subject(1).wait().unwrap_err();
+        assert_eq!(err.to_string(), "Connection reset");
+        assert!(is_connection_error(&err));
+
+        let err = client.subject(1).wait().unwrap_err();
+        assert!(err.to_string().starts_with("No scripted response"));
+        assert!(is_connection_error(&err));
+
+        // Answered, just not usefully
+        transport.respond(Method::Get, "https://api.bgm.tv/subject/1", 200, "<html>");
+        let err = client.subject(1).wait().unwrap_err();
+        assert!(!is_connection_error(&err));
     }
 }
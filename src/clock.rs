@@ -0,0 +1,49 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Mutex;
+
+/**
+ * Source of the current time for token expiry calculations
+ */
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/**
+ * A clock that only moves when told to
+ */
+#[derive(Debug)]
+pub struct FixedClock {
+    time: Mutex<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(time: DateTime<Utc>) -> FixedClock {
+        FixedClock {
+            time: Mutex::new(time),
+        }
+    }
+
+    pub fn set(&self, time: DateTime<Utc>) {
+        *self.time.lock().unwrap() = time;
+    }
+
+    pub fn advance(&self, dur: Duration) {
+        let mut guard = self.time.lock().unwrap();
+        *guard += dur;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.time.lock().unwrap()
+    }
+}
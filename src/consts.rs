@@ -4,8 +4,9 @@ macro_rules! BGM_ROOT {
     };
 }
 
-pub(crate) const OAUTH_AUTHORIZE: &'static str = concat!(BGM_ROOT!(), "/oauth/authorize");
-pub(crate) const OAUTH_ACCESS_TOKEN: &'static str = concat!(BGM_ROOT!(), "/oauth/access_token");
+pub(crate) const OAUTH_AUTHORIZE: &str = concat!(BGM_ROOT!(), "/oauth/authorize");
+pub(crate) const OAUTH_ACCESS_TOKEN: &str = concat!(BGM_ROOT!(), "/oauth/access_token");
+pub(crate) const OAUTH_TOKEN_STATUS: &str = concat!(BGM_ROOT!(), "/oauth/token_status");
 
 macro_rules! API_ROOT {
     () => {
@@ -0,0 +1,61 @@
+// bgm.tv occasionally returns `null` for fields that are usually present,
+// and may add new kinds of data at any time. A single odd entry should not
+// fail the whole response.
+
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, Error};
+use serde_derive::Deserialize;
+
+/**
+ * Treats `null` as the default value of the field
+ */
+pub fn null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/**
+ * Decodes a list entry by entry, dropping entries that fail to decode.
+ * `null` is treated as an empty list
+ */
+pub fn list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let values = Option::<Vec<serde_json::Value>>::deserialize(deserializer)?;
+
+    Ok(values
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|value| match serde_json::from_value(value) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!(target: "bgmtv::lenient", "skipped malformed entry: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/**
+ * Some endpoints return ids as strings
+ */
+pub fn u64_or_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Number(u64),
+        Text(String),
+    }
+
+    match Repr::deserialize(deserializer)? {
+        Repr::Number(n) => Ok(n),
+        Repr::Text(s) => s.parse().map_err(D::Error::custom),
+    }
+}
@@ -1,12 +1,16 @@
-#![feature(custom_attribute)]
-#![feature(never_type)]
-#![feature(impl_trait_in_bindings)]
-#![feature(slice_concat_ext)]
+#[macro_use]
+extern crate log;
 
 #[macro_use]
 mod macros;
 #[macro_use]
 pub mod consts;
+pub mod api;
 pub mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod client;
+pub mod clock;
+mod lenient;
 pub mod settings;
+pub mod transport;
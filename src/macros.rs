@@ -1,8 +1,33 @@
 macro_rules! enum_number {
     ($name:ident { $($variant:ident = $value:expr, )* }) => {
-        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
         pub enum $name {
-            $($variant = $value,)*
+            $($variant,)*
+
+            /**
+             * A value bgm.tv added after this crate was written
+             */
+            Unknown(u64),
+        }
+
+        impl $name {
+            pub fn value(&self) -> u64 {
+                match self {
+                    $( $name::$variant => $value, )*
+                    $name::Unknown(value) => *value,
+                }
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> $name {
+                // Rust does not come with a simple way of converting a
+                // number to an enum, so use a big `match`.
+                match value {
+                    $( $value => $name::$variant, )*
+                    _ => $name::Unknown(value),
+                }
+            }
         }
 
         impl ::serde::Serialize for $name {
@@ -11,7 +36,7 @@ macro_rules! enum_number {
                 S: ::serde::Serializer,
             {
                 // Serialize the enum as a u64.
-                serializer.serialize_u64(*self as u64)
+                serializer.serialize_u64(self.value())
             }
         }
 
@@ -33,14 +58,7 @@ macro_rules! enum_number {
                     where
                         E: ::serde::de::Error,
                     {
-                        // Rust does not come with a simple way of converting a
-                        // number to an enum, so use a big `match`.
-                        match value {
-                            $( $value => Ok($name::$variant), )*
-                            _ => Err(E::custom(
-                                format!("unknown {} value: {}",
-                                stringify!($name), value))),
-                        }
+                        Ok($name::from(value))
                     }
                 }
 
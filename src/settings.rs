@@ -1,10 +1,69 @@
-use crate::auth::{refresh_token, AppCred, AuthInfo, AuthResp, RespError};
+use crate::auth::{refresh_token_with, AppCred, AuthInfo, AuthResp, RespError};
+use crate::clock::{Clock, SystemClock};
+use crate::transport::{ReqwestTransport, Transport};
 use chrono;
+use failure::Error;
 use futures::future::Future;
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+mod store;
+pub use self::store::{FileStore, Format, Listener, Store};
 
 const REFRESH_RATIO: f64 = 0.2;
 
+/**
+ * Decides when an access token should be refreshed before it expires
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RefreshPolicy {
+    /**
+     * Refresh once this fraction of the token lifetime has passed
+     */
+    pub ratio: f64,
+
+    /**
+     * Refresh once fewer than this many seconds are left
+     */
+    pub margin: Option<u64>,
+
+    /**
+     * Refresh instead of logging in again when bgm.tv rejects the token with 401,
+     * either when validated at startup or in response to any request made through `Client`
+     */
+    pub on_unauthorized: bool,
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        RefreshPolicy {
+            ratio: REFRESH_RATIO,
+            margin: None,
+            on_unauthorized: true,
+        }
+    }
+}
+
+impl RefreshPolicy {
+    pub fn requires_refresh(&self, handle: &AuthHandle, clock: &dyn Clock) -> bool {
+        let elapsed = handle.elapsed(clock);
+        if elapsed as f64 > handle.info.expires_in as f64 * self.ratio {
+            return true;
+        }
+
+        match self.margin {
+            Some(margin) => elapsed + margin as i64 > handle.info.expires_in as i64,
+            None => false,
+        }
+    }
+
+    pub fn refresh_on(&self, status: u16) -> bool {
+        self.on_unauthorized && status == 401
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthHandle {
     pub(crate) info: AuthInfo,
@@ -15,32 +74,66 @@ pub struct AuthHandle {
 }
 
 impl AuthHandle {
-    fn time_diff(&self) -> i64 {
-        let cur = chrono::Utc::now();
-        cur.timestamp() - self.time.timestamp()
+    /**
+     * Seconds since the token was issued
+     */
+    pub fn elapsed(&self, clock: &dyn Clock) -> i64 {
+        clock.now().timestamp() - self.time.timestamp()
+    }
+
+    pub fn outdated_at(&self, clock: &dyn Clock) -> bool {
+        self.elapsed(clock) > self.info.expires_in as i64
     }
 
     pub fn outdated(&self) -> bool {
-        self.time_diff() as u64 > self.info.expires_in
+        self.outdated_at(&SystemClock)
     }
 
     pub fn requires_refresh(&self) -> bool {
-        self.time_diff() as f64 > self.info.expires_in as f64 * REFRESH_RATIO
+        RefreshPolicy::default().requires_refresh(self, &SystemClock)
     }
 
     pub fn refresh(
         self,
         cred: AppCred,
-    ) -> impl Future<Item = Result<AuthHandle, RespError>, Error = reqwest::Error> {
+    ) -> impl Future<Item = Result<AuthHandle, RespError>, Error = failure::Error> {
+        self.refresh_at(cred, &SystemClock)
+    }
+
+    /**
+     * The new token is stamped with the time the request was sent,
+     * so it never outlives the lifetime bgm.tv gives it
+     */
+    pub fn refresh_at(
+        self,
+        cred: AppCred,
+        clock: &dyn Clock,
+    ) -> impl Future<Item = Result<AuthHandle, RespError>, Error = failure::Error> {
+        self.refresh_with(&ReqwestTransport::new(), cred, clock)
+    }
+
+    pub fn refresh_with<T: Transport>(
+        self,
+        transport: &T,
+        cred: AppCred,
+        clock: &dyn Clock,
+    ) -> impl Future<Item = Result<AuthHandle, RespError>, Error = failure::Error> {
         let redir = self.redirect.clone();
-        refresh_token(cred, self.info.refresh_token, self.redirect).map(|resp| match resp {
-            AuthResp::Error(err) => Err(err),
-            AuthResp::Success(info) => Ok(AuthHandle {
-                info: info,
-                time: chrono::Utc::now(),
-                redirect: redir,
-            }),
-        })
+        let time = clock.now();
+        refresh_token_with(transport, cred, self.info.refresh_token, self.redirect).map(
+            move |resp| match resp {
+                AuthResp::Error(err) => Err(err),
+                AuthResp::Success(info) => Ok(AuthHandle {
+                    info,
+                    time,
+                    redirect: redir,
+                }),
+            },
+        )
+    }
+
+    pub fn user_id(&self) -> u64 {
+        self.info.user_id
     }
 
     pub fn redir(&self) -> &str {
@@ -52,16 +145,47 @@ impl AuthHandle {
 pub struct Settings {
     credentials: AppCred,
     auth: Option<AuthHandle>,
+
+    #[serde(default)]
+    refresh: RefreshPolicy,
+
+    /**
+     * Sections owned by applications built on top of bgmtv
+     */
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl Settings {
     pub fn new(credentials: AppCred, auth: Option<AuthHandle>) -> Settings {
         Settings {
-            credentials: credentials,
-            auth: auth,
+            credentials,
+            auth,
+            refresh: RefreshPolicy::default(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    /**
+     * Reads an application-defined section. Missing sections are `None`
+     */
+    pub fn section<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, Error> {
+        match self.extra.get(name) {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
         }
     }
 
+    pub fn set_section<T: serde::Serialize>(
+        mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<Settings, Error> {
+        self.extra
+            .insert(name.to_string(), serde_json::to_value(value)?);
+        Ok(self)
+    }
+
     pub fn cred(&self) -> &AppCred {
         &self.credentials
     }
@@ -70,25 +194,133 @@ impl Settings {
         &self.auth
     }
 
-    pub fn logout(self) -> Settings {
-        Settings {
-            credentials: self.credentials,
-            auth: None,
+    pub fn refresh_policy(&self) -> &RefreshPolicy {
+        &self.refresh
+    }
+
+    pub fn set_refresh_policy(self, refresh: RefreshPolicy) -> Settings {
+        Settings { refresh, ..self }
+    }
+
+    /**
+     * Whether the stored token should be refreshed according to the configured policy
+     */
+    pub fn requires_refresh(&self, clock: &dyn Clock) -> bool {
+        match self.auth {
+            Some(ref handle) => self.refresh.requires_refresh(handle, clock),
+            None => false,
         }
     }
 
+    pub fn logout(self) -> Settings {
+        Settings { auth: None, ..self }
+    }
+
     pub fn update_auth(self, auth: AuthInfo, redirect: String) -> Settings {
+        self.update_auth_at(auth, redirect, &SystemClock)
+    }
+
+    pub fn update_auth_at(self, auth: AuthInfo, redirect: String, clock: &dyn Clock) -> Settings {
         self.update_handle(AuthHandle {
             info: auth,
-            time: chrono::Utc::now(),
-            redirect: redirect,
+            time: clock.now(),
+            redirect,
         })
     }
 
     pub fn update_handle(self, handle: AuthHandle) -> Settings {
         Settings {
-            credentials: self.credentials,
             auth: Some(handle),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use chrono::Duration;
+
+    fn handle(expires_in: u64) -> AuthHandle {
+        AuthHandle {
+            info: AuthInfo {
+                access_token: "token".to_string(),
+                user_id: 1,
+                refresh_token: "refresh".to_string(),
+                expires_in,
+            },
+            time: "2019-01-01T00:00:00Z".parse().unwrap(),
+            redirect: String::new(),
         }
     }
+
+    #[test]
+    fn refreshes_after_ratio_of_lifetime() {
+        let handle = handle(1000);
+        let clock = FixedClock::new(handle.time);
+        let policy = RefreshPolicy {
+            ratio: 0.5,
+            margin: None,
+            on_unauthorized: true,
+        };
+
+        clock.advance(Duration::seconds(500));
+        assert!(!policy.requires_refresh(&handle, &clock));
+        clock.advance(Duration::seconds(1));
+        assert!(policy.requires_refresh(&handle, &clock));
+    }
+
+    #[test]
+    fn refreshes_within_margin_of_expiry() {
+        let handle = handle(1000);
+        let clock = FixedClock::new(handle.time);
+        let policy = RefreshPolicy {
+            ratio: 1.0,
+            margin: Some(100),
+            on_unauthorized: true,
+        };
+
+        clock.advance(Duration::seconds(900));
+        assert!(!policy.requires_refresh(&handle, &clock));
+        clock.advance(Duration::seconds(1));
+        assert!(policy.requires_refresh(&handle, &clock));
+    }
+
+    #[test]
+    fn default_policy_refreshes_early() {
+        let handle = handle(604800);
+        let clock = FixedClock::new(handle.time);
+        let settings = Settings::new(AppCred::new("id".to_string(), "secret".to_string()), None);
+        assert!(!settings.requires_refresh(&clock));
+
+        let settings = settings.update_handle(handle.clone());
+        clock.advance(Duration::days(1));
+        assert!(!settings.requires_refresh(&clock));
+        clock.advance(Duration::days(1));
+        assert!(settings.requires_refresh(&clock));
+    }
+
+    #[test]
+    fn outdated_once_expired() {
+        let handle = handle(1000);
+        let clock = FixedClock::new(handle.time);
+        assert_eq!(handle.elapsed(&clock), 0);
+        assert!(!handle.outdated_at(&clock));
+
+        clock.set(handle.time + Duration::seconds(1000));
+        assert!(!handle.outdated_at(&clock));
+        clock.advance(Duration::seconds(1));
+        assert!(handle.outdated_at(&clock));
+    }
+
+    #[test]
+    fn refreshes_only_on_unauthorized() {
+        let mut policy = RefreshPolicy::default();
+        assert!(policy.refresh_on(401));
+        assert!(!policy.refresh_on(403));
+
+        policy.on_unauthorized = false;
+        assert!(!policy.refresh_on(401));
+    }
 }
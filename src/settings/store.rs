@@ -0,0 +1,357 @@
+use super::Settings;
+use failure::{format_err, Error};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/**
+ * Bumped whenever the layout of the settings file changes.
+ * Older files are migrated by `migrate` when loaded
+ */
+pub const SETTINGS_VERSION: u64 = 1;
+
+pub type Listener = Box<dyn Fn(&Settings) + Send + Sync>;
+
+/**
+ * Somewhere settings can be loaded from and saved to
+ */
+pub trait Store {
+    fn load(&self) -> Result<Settings, Error>;
+    fn save(&self, settings: &Settings) -> Result<(), Error>;
+
+    /**
+     * The listener is called with the new settings after every successful save
+     */
+    fn on_change(&self, listener: Listener);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl Format {
+    /**
+     * Guesses from the file extension, defaulting to YAML
+     */
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Format::Json,
+            Some("toml") => Format::Toml,
+            _ => Format::Yaml,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Yaml => "yml",
+            Format::Json => "json",
+            Format::Toml => "toml",
+        }
+    }
+
+    fn parse(&self, content: &str) -> Result<Value, Error> {
+        Ok(match self {
+            Format::Yaml => serde_yaml::from_str(content)?,
+            Format::Json => serde_json::from_str(content)?,
+            Format::Toml => toml::from_str(content)?,
+        })
+    }
+
+    fn serialize(&self, value: &Value) -> Result<String, Error> {
+        Ok(match self {
+            Format::Yaml => serde_yaml::to_string(value)?,
+            Format::Json => serde_json::to_string_pretty(value)?,
+            // Going through toml::Value puts plain values before tables, which TOML requires
+            Format::Toml => toml::to_string(&toml::Value::try_from(strip_nulls(value))?)?,
+        })
+    }
+}
+
+/**
+ * TOML has no null, absent keys are read back as `None` instead
+ */
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(list) => Value::Array(list.iter().map(strip_nulls).collect()),
+        _ => value.clone(),
+    }
+}
+
+pub struct FileStore {
+    path: PathBuf,
+    format: Format,
+    listeners: Mutex<Vec<Listener>>,
+}
+
+impl FileStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileStore {
+        let path = path.into();
+        let format = Format::from_path(&path);
+        FileStore::with_format(path, format)
+    }
+
+    pub fn with_format<P: Into<PathBuf>>(path: P, format: Format) -> FileStore {
+        FileStore {
+            path: path.into(),
+            format,
+            listeners: Mutex::new(Vec::new()),
+        }
+    }
+
+    /**
+     * Finds `<name>.yml`, `<name>.yaml`, `<name>.json` or `<name>.toml`
+     * in `$XDG_CONFIG_HOME`, or the platform config directory if unset.
+     * Falls back to `<name>.yml` there if none of them exists
+     */
+    pub fn discover(name: &str) -> FileStore {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(dirs::config_dir)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        let existing = ["yml", "yaml", "json", "toml"]
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|p| p.exists());
+
+        FileStore::new(existing.unwrap_or_else(|| dir.join(format!("{}.yml", name))))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+}
+
+impl Store for FileStore {
+    fn load(&self) -> Result<Settings, Error> {
+        let mut buf = String::new();
+        File::open(&self.path)?.read_to_string(&mut buf)?;
+
+        let value = migrate(self.format.parse(&buf)?)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    fn save(&self, settings: &Settings) -> Result<(), Error> {
+        let mut value = serde_json::to_value(settings)?;
+        if let Value::Object(ref mut map) = value {
+            map.insert("version".to_string(), SETTINGS_VERSION.into());
+        }
+
+        let serialized = self.format.serialize(&value)?;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Written next to the file and renamed over it, so that a crash never leaves it half written
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        let written = File::create(&temp).and_then(|mut f| {
+            f.write_all(serialized.as_bytes())?;
+            f.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| std::fs::rename(&temp, &self.path)) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.into());
+        }
+
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(settings);
+        }
+
+        Ok(())
+    }
+
+    fn on_change(&self, listener: Listener) {
+        self.listeners.lock().unwrap().push(listener);
+    }
+}
+
+/**
+ * Brings a settings document written by any earlier version up to `SETTINGS_VERSION`,
+ * and strips the version marker
+ */
+pub fn migrate(mut value: Value) -> Result<Value, Error> {
+    let map = match value {
+        Value::Object(ref mut map) => map,
+        _ => return Err(format_err!("Settings must be a map")),
+    };
+
+    let mut version = match map.remove("version") {
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| format_err!("Invalid settings version: {}", v))?,
+        None => 0,
+    };
+
+    if version > SETTINGS_VERSION {
+        return Err(format_err!(
+            "Settings version {} is newer than supported ({})",
+            version,
+            SETTINGS_VERSION
+        ));
+    }
+
+    while version < SETTINGS_VERSION {
+        match version {
+            // Files written before versioning was introduced share the layout of version 1
+            0 => {}
+            _ => unreachable!(),
+        }
+
+        version += 1;
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AppCred, AuthInfo};
+    use crate::settings::AuthHandle;
+    use std::sync::Arc;
+
+    fn settings() -> Settings {
+        let auth = AuthHandle {
+            info: AuthInfo {
+                access_token: "token".to_string(),
+                user_id: 42,
+                refresh_token: "refresh".to_string(),
+                expires_in: 604800,
+            },
+            time: "2019-01-01T00:00:00Z".parse().unwrap(),
+            redirect: String::new(),
+        };
+        Settings::new(
+            AppCred::new("id".to_string(), "secret".to_string()),
+            Some(auth),
+        )
+        .set_section(
+            "app",
+            &serde_json::json!({ "theme": "dark", "unset": null }),
+        )
+        .unwrap()
+    }
+
+    /**
+     * An empty directory of its own for each test
+     */
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bgmtv-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        let dir = scratch("round-trip");
+        let expected = serde_json::to_value(settings()).unwrap();
+
+        for format in [Format::Yaml, Format::Json, Format::Toml].iter() {
+            let path = dir.join(format!("settings.{}", format.extension()));
+            let store = FileStore::new(&path);
+            assert_eq!(store.format(), *format);
+
+            store.save(&settings()).unwrap();
+            let loaded = store.load().unwrap();
+            let value = serde_json::to_value(&loaded).unwrap();
+            assert_eq!(value["credentials"], expected["credentials"]);
+            assert_eq!(value["auth"], expected["auth"]);
+            assert_eq!(value["refresh"], expected["refresh"]);
+
+            // TOML drops the null, leaving the rest of the section
+            let app: Value = loaded.section("app").unwrap().unwrap();
+            assert_eq!(app["theme"], "dark");
+        }
+
+        // Nothing is left behind next to the files
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_unversioned_files() {
+        let dir = scratch("migrate");
+        let path = dir.join("settings.yml");
+        std::fs::write(
+            &path,
+            "credentials: { client_id: id, client_secret: secret }\nauth: ~\n",
+        )
+        .unwrap();
+
+        let store = FileStore::new(&path);
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.cred().get_client_id(), "id");
+
+        store.save(&loaded).unwrap();
+        let saved: Value = serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], SETTINGS_VERSION);
+
+        let newer = serde_json::json!({ "version": SETTINGS_VERSION + 1 });
+        assert!(migrate(newer).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn discovers_files_in_xdg_config_home() {
+        let dir = scratch("discover");
+        let previous = std::env::var_os("XDG_CONFIG_HOME");
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+
+        let missing = FileStore::discover("bgmtv-test");
+        assert_eq!(missing.path(), dir.join("bgmtv-test.yml").as_path());
+
+        std::fs::write(dir.join("bgmtv-test.json"), "{}").unwrap();
+        let found = FileStore::discover("bgmtv-test");
+        assert_eq!(found.path(), dir.join("bgmtv-test.json").as_path());
+        assert_eq!(found.format(), Format::Json);
+
+        match previous {
+            Some(previous) => std::env::set_var("XDG_CONFIG_HOME", previous),
+            None => std::env::remove_var("XDG_CONFIG_HOME"),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn notifies_listeners_after_saving() {
+        let dir = scratch("on-change");
+        let store = FileStore::new(dir.join("settings.yml"));
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let log = saved.clone();
+        store.on_change(Box::new(move |settings| {
+            log.lock()
+                .unwrap()
+                .push(settings.cred().get_client_id().to_string());
+        }));
+
+        store.save(&settings()).unwrap();
+        assert_eq!(*saved.lock().unwrap(), vec!["id".to_string()]);
+
+        // Failed saves aren't announced
+        let blocked = FileStore::new(dir.join("settings.yml").join("nested.yml"));
+        blocked.on_change(Box::new(|_| panic!("Notified of a failed save")));
+        assert!(blocked.save(&settings()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
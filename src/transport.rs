@@ -0,0 +1,213 @@
+use futures::prelude::*;
+use reqwest::r#async as req;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+mod fake;
+pub use self::fake::FakeTransport;
+
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "cache")]
+pub use self::cache::CachingTransport;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Get => write!(f, "GET"),
+            Method::Post => write!(f, "POST"),
+        }
+    }
+}
+
+/**
+ * A fully buffered HTTP request, independent of any HTTP library
+ */
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: Method, url: String) -> Request {
+        Request {
+            method,
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn get(url: String) -> Request {
+        Request::new(Method::Get, url)
+    }
+
+    pub fn post(url: String) -> Request {
+        Request::new(Method::Post, url)
+    }
+
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Request {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn form<T: Serialize>(self, payload: &T) -> Result<Request, failure::Error> {
+        let body = serde_urlencoded::to_string(payload)?;
+        let mut result = self.header("Content-Type", "application/x-www-form-urlencoded");
+        result.body = body.into_bytes();
+        Ok(result)
+    }
+
+    pub fn json<T: Serialize>(self, payload: &T) -> Result<Request, failure::Error> {
+        let body = serde_json::to_vec(payload)?;
+        let mut result = self.header("Content-Type", "application/json");
+        result.body = body;
+        Ok(result)
+    }
+
+    /**
+     * The URL without its query string
+     */
+    pub fn endpoint(&self) -> &str {
+        self.url.split('?').next().unwrap_or("")
+    }
+
+    /**
+     * Headers safe to be written into logs
+     */
+    pub fn redacted_headers(&self) -> Vec<(&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(key, value)| {
+                if key.eq_ignore_ascii_case("Authorization") {
+                    (key.as_str(), "<redacted>")
+                } else {
+                    (key.as_str(), value.as_str())
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, failure::Error> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/**
+ * A request which never got a response, e.g. because the network is down.
+ *
+ * Unlike a rejection by the server, sending the same request later may succeed
+ */
+#[derive(Debug)]
+pub struct ConnectionError {
+    inner: failure::Error,
+}
+
+impl ConnectionError {
+    pub fn new<E: Into<failure::Error>>(e: E) -> ConnectionError {
+        ConnectionError { inner: e.into() }
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+impl failure::Fail for ConnectionError {
+    fn cause(&self) -> Option<&dyn failure::Fail> {
+        Some(self.inner.as_fail())
+    }
+}
+
+/**
+ * Whether the error comes from a `ConnectionError`, rather than from the server
+ */
+pub fn is_connection_error(e: &failure::Error) -> bool {
+    e.downcast_ref::<ConnectionError>().is_some()
+}
+
+pub type ResponseFuture = Box<dyn Future<Item = Response, Error = failure::Error> + Send>;
+
+/**
+ * Sends requests on behalf of a `Client`.
+ *
+ * Implementors may talk to the network, serve canned responses or wrap another transport.
+ * Failures to reach the server should be reported as `ConnectionError`
+ */
+pub trait Transport: Send + Sync + 'static {
+    type Future: Future<Item = Response, Error = failure::Error> + Send + 'static;
+
+    fn send(&self, req: Request) -> Self::Future;
+}
+
+pub struct ReqwestTransport {
+    client: req::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport::with(req::Client::new())
+    }
+
+    pub fn with(client: req::Client) -> ReqwestTransport {
+        ReqwestTransport { client }
+    }
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for ReqwestTransport {
+    type Future = ResponseFuture;
+
+    fn send(&self, req: Request) -> Self::Future {
+        let method = match req.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+        };
+
+        let mut builder = self.client.request(method, &req.url);
+        for (key, value) in req.headers.iter() {
+            builder = builder.header(key.as_str(), value.as_str());
+        }
+        if !req.body.is_empty() {
+            builder = builder.body(req.body);
+        }
+
+        let fut = builder
+            .send()
+            .and_then(|resp| {
+                let status = resp.status().as_u16();
+                resp.into_body().concat2().map(move |body| Response {
+                    status,
+                    body: body.to_vec(),
+                })
+            })
+            .map_err(|e| ConnectionError::new(e).into());
+
+        Box::new(fut)
+    }
+}
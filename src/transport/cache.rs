@@ -0,0 +1,80 @@
+use super::{Method, Request, Response, ResponseFuture, Transport};
+use futures::future;
+use futures::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/**
+ * Serves repeated GET requests from memory for `ttl`.
+ *
+ * Only successful responses are kept. Any POST may change what bgm.tv
+ * returns, so it drops everything cached so far
+ */
+pub struct CachingTransport<T> {
+    inner: T,
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, (Instant, Response)>>>,
+}
+
+impl<T: Transport> CachingTransport<T> {
+    pub fn new(inner: T, ttl: Duration) -> CachingTransport<T> {
+        CachingTransport {
+            inner,
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    // Responses depend on who is asking
+    fn key(req: &Request) -> String {
+        let auth = req
+            .headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Authorization"))
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("");
+        format!("{} {}", auth, req.url)
+    }
+}
+
+impl<T: Transport> Transport for CachingTransport<T> {
+    type Future = ResponseFuture;
+
+    fn send(&self, req: Request) -> Self::Future {
+        if req.method != Method::Get {
+            self.clear();
+            return Box::new(self.inner.send(req));
+        }
+
+        let key = Self::key(&req);
+        {
+            let mut entries = self.entries.lock().unwrap();
+            match entries.get(&key) {
+                Some((at, resp)) if at.elapsed() < self.ttl => {
+                    debug!(target: "bgmtv::cache", "hit endpoint={}", req.endpoint());
+                    return Box::new(future::ok(resp.clone()));
+                }
+                Some(_) => {
+                    entries.remove(&key);
+                }
+                None => {}
+            }
+        }
+
+        let entries = self.entries.clone();
+        Box::new(self.inner.send(req).map(move |resp| {
+            if resp.status >= 200 && resp.status < 300 {
+                entries
+                    .lock()
+                    .unwrap()
+                    .insert(key, (Instant::now(), resp.clone()));
+            }
+            resp
+        }))
+    }
+}
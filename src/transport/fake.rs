@@ -0,0 +1,134 @@
+use super::{ConnectionError, Method, Request, Response, Transport};
+use failure::format_err;
+use futures::future;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct FakeRoutes {
+    /**
+     * Responses by method and endpoint, see `Request::endpoint`
+     */
+    responses: HashMap<(Method, String), Response>,
+
+    /**
+     * Answers for the next requests to an endpoint, in order, before falling back to `responses`.
+     * Errors are transport failures
+     */
+    queued: HashMap<(Method, String), Vec<Result<Response, String>>>,
+
+    /**
+     * Every request sent so far, in order
+     */
+    requests: Vec<Request>,
+}
+
+/**
+ * Answers requests with canned responses, for using a `Client` without network access.
+ *
+ * Responses are matched by method and endpoint, ignoring the query string.
+ * Requests without a scripted response fail like a dropped connection.
+ * Clones share the same routes, so a test can keep one to inspect the requests
+ * made through a `Client`
+ */
+#[derive(Clone, Default)]
+pub struct FakeTransport {
+    routes: Arc<Mutex<FakeRoutes>>,
+}
+
+impl FakeTransport {
+    pub fn new() -> FakeTransport {
+        FakeTransport::default()
+    }
+
+    pub fn respond<B: Into<Vec<u8>>>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        status: u16,
+        body: B,
+    ) -> &Self {
+        let resp = Response {
+            status,
+            body: body.into(),
+        };
+        self.routes
+            .lock()
+            .unwrap()
+            .responses
+            .insert((method, endpoint.to_string()), resp);
+        self
+    }
+
+    pub fn respond_json<T: Serialize>(&self, method: Method, endpoint: &str, body: &T) -> &Self {
+        self.respond(method, endpoint, 200, serde_json::to_vec(body).unwrap())
+    }
+
+    /**
+     * Answers the next request to the endpoint once, before the scripted response
+     */
+    pub fn respond_next<B: Into<Vec<u8>>>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        status: u16,
+        body: B,
+    ) -> &Self {
+        let resp = Response {
+            status,
+            body: body.into(),
+        };
+        self.queue(method, endpoint, Ok(resp))
+    }
+
+    /**
+     * Fails the next request to the endpoint before it reaches the scripted response
+     */
+    pub fn fail_next(&self, method: Method, endpoint: &str, message: &str) -> &Self {
+        self.queue(method, endpoint, Err(message.to_string()))
+    }
+
+    fn queue(&self, method: Method, endpoint: &str, result: Result<Response, String>) -> &Self {
+        self.routes
+            .lock()
+            .unwrap()
+            .queued
+            .entry((method, endpoint.to_string()))
+            .or_default()
+            .push(result);
+        self
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.routes.lock().unwrap().requests.clone()
+    }
+}
+
+impl Transport for FakeTransport {
+    type Future = future::FutureResult<Response, failure::Error>;
+
+    fn send(&self, req: Request) -> Self::Future {
+        let mut routes = self.routes.lock().unwrap();
+        let key = (req.method, req.endpoint().to_string());
+        routes.requests.push(req);
+
+        if let Some(queued) = routes.queued.get_mut(&key) {
+            if !queued.is_empty() {
+                return future::result(
+                    queued
+                        .remove(0)
+                        .map_err(|e| ConnectionError::new(format_err!("{}", e)).into()),
+                );
+            }
+        }
+
+        match routes.responses.get(&key) {
+            Some(resp) => future::ok(resp.clone()),
+            None => future::err(
+                ConnectionError::new(format_err!("No scripted response for {} {}", key.0, key.1))
+                    .into(),
+            ),
+        }
+    }
+}
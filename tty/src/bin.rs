@@ -1,98 +1,73 @@
-#![feature(const_slice_len)]
-#![feature(const_fn)]
-
+mod cli;
+#[cfg(test)]
+mod fixtures;
+mod fuzzy;
 mod help;
+mod i18n;
+mod import;
+mod journal;
+mod keys;
+mod logging;
+mod session;
+mod sort;
 mod state;
+mod sync;
+mod theme;
 mod widgets;
-use crate::help::*;
+mod yaml;
+use crate::i18n::{fill, msgs};
+use crate::keys::{Action, Keymap};
 use crate::state::*;
+use crate::theme::Theme;
 use crate::widgets::*;
 
-use bgmtv::auth::{request_code, request_token, AppCred, AuthResp};
-use bgmtv::client::{Client, CollectionStatus, SubjectType};
-use bgmtv::settings::Settings;
+use bgmtv::auth::{request_code, request_token, token_status, AppCred, AuthResp, TokenState};
+use bgmtv::client::{Client, CollectionStatus, EpisodeStatus, SubjectType};
+use bgmtv::clock::SystemClock;
+use bgmtv::settings::{FileStore, Settings, Store};
 use clap;
 use colored::*;
 use crossbeam_channel::{unbounded, Select, Sender};
-use dirs;
-use failure::Error;
 use futures::future::Future;
-use serde_yaml;
 use std::convert::AsRef;
-use std::fs::File;
-use std::io::{BufRead, Read, Write};
-use std::path::{Path, PathBuf};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use termion;
 use termion::raw::IntoRawMode;
 use tokio;
 use tui;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::terminal::Frame;
+use tui::widgets::{Block, Borders, Widget};
+
+/**
+ * GET requests failing with network or server errors are retried this many times
+ */
+const REQUEST_RETRIES: u32 = 2;
 
 #[derive(Clone)]
 pub struct Args {
     editor: String,
     config: PathBuf,
+    no_session: bool,
 }
 
-trait SettingsExt: Sized {
-    fn load_from<P: AsRef<Path>>(file: P) -> Result<Self, Error>;
-    fn save_to<P: AsRef<Path>>(&self, file: P) -> Result<(), Error>;
-}
-
-impl SettingsExt for Settings {
-    fn load_from<P: AsRef<Path>>(file: P) -> Result<Settings, Error> {
-        let mut buf = String::new();
-        File::open(file)?.read_to_string(&mut buf)?;
-
-        let settings: Settings = serde_yaml::from_str(&buf)?;
-
-        Ok(settings)
-    }
-
-    fn save_to<P: AsRef<Path>>(&self, file: P) -> Result<(), Error> {
-        let serialized = serde_yaml::to_vec(self)?;
-        let mut f = File::create(file)?;
-        f.write_all(&serialized)?;
-
-        Ok(())
-    }
-}
-
-fn default_path() -> PathBuf {
-    let mut buf = dirs::config_dir().unwrap_or(PathBuf::from("."));
-    buf.push("bgmtty.yml");
-    match buf.canonicalize() {
-        Ok(can) => can,
-        Err(_) => buf,
-    }
-}
-
-fn load_settings(config: &Path) -> Result<Settings, Error> {
-    Settings::load_from(config)
-}
-
-fn init_credentials(config: &Path) {
-    println!(
-        "{}",
-        "bgmTTY 通过 OAuth 协议向 bgm.tv 申请验证，所以我们需要有效的 OAuth 应用凭证。"
-            .blue()
-    );
-    println!(
-        "{}",
-        "您可以前往 https://bgm.tv/dev/app 进行申请, 或者使用既有的凭证。"
-            .blue()
-    );
+fn init_credentials(store: &FileStore) {
+    println!("{}", msgs().init_intro.blue());
+    println!("{}", msgs().init_apply.blue());
 
     let stdin = std::io::stdin();
     let lock = stdin.lock();
 
     let mut lines = lock.lines();
-    print!("请输入您的 Client ID: ");
+    print!("{}", msgs().init_client_id);
     std::io::stdout()
         .flush()
         .expect("Could not flush stdout???");
     let id = lines.next();
-    print!("请输入您的 Client secret: ");
+    print!("{}", msgs().init_client_secret);
     std::io::stdout()
         .flush()
         .expect("Could not flush stdout???");
@@ -108,31 +83,41 @@ fn init_credentials(config: &Path) {
 
     let cred = AppCred::new(id, secret);
     let settings = Settings::new(cred, None);
-    let parent = config.parent();
-
-    if let Some(parent) = parent {
-        std::fs::create_dir_all(parent).expect(&"Permission denied!".red());
-    }
 
-    settings
-        .save_to(config)
+    store
+        .save(&settings)
         .expect(&"Failed to save config!".red());
 
-    print!(
-        "{}",
-        "完成了！现在您可以去掉 --init 参数重新启动 bgmTTY，进行 OAuth 认证。"
-            .green()
-    )
+    print!("{}", msgs().init_done.green())
 }
 
-fn new_auth(settings: Settings, config: &Path) -> Result<Settings, ()> {
-    let config: PathBuf = config.into();
+/**
+ * Prints a message about authentication. Subcommands keep stdout for their own output,
+ * so they are told on stderr
+ */
+fn auth_message<T: std::fmt::Display>(interactive: bool, message: T) {
+    if interactive {
+        println!("{}", message);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+/**
+ * Authenticates through the browser. Fails right away when not interactive,
+ * as nobody is there to open the page
+ */
+fn new_auth(settings: Settings, store: &FileStore, interactive: bool) -> Result<Settings, ()> {
+    if !interactive {
+        eprintln!("{}", msgs().auth_interactive.yellow());
+        return Err(());
+    }
 
     let set = settings.clone();
     let cred = set.cred().clone();
     let (uri, fut) = request_code(cred.get_client_id());
 
-    println!("请在本机使用浏览器前往 {} 完成验证", uri);
+    println!("{}", fill(msgs().auth_visit, &[&uri]));
 
     let fut = fut
         .map_err(|e| println!("{:#?}", e))
@@ -142,28 +127,19 @@ fn new_auth(settings: Settings, config: &Path) -> Result<Settings, ()> {
                 .map(|resp| (resp, redirect))
         })
         .and_then(|(resp, redirect)| match resp {
-            AuthResp::Success(info) => {
-                let newset = set.update_auth(info, redirect);
-                newset
-                    .save_to(config)
-                    .expect(&"Failed to save config!".red());
-                futures::future::ok(newset)
-            }
+            AuthResp::Success(info) => futures::future::ok(set.update_auth(info, redirect)),
             _ => {
-                println!(
-                    "{}",
-                    &"获取 Token 失败！请检查您的 Client ID/secret 并重试。".red()
-                );
+                println!("{}", msgs().auth_token_failed.red());
                 futures::future::err(())
             }
         });
     let mut runtime = tokio::runtime::Runtime::new().expect("Unable to create tokio runtime!");
-    runtime.block_on(fut)
+    let newset = runtime.block_on(fut)?;
+    store.save(&newset).expect(&"Failed to save config!".red());
+    Ok(newset)
 }
 
-fn refresh_auth(settings: Settings, config: &Path) -> Result<Settings, ()> {
-    let config: PathBuf = config.into();
-
+fn refresh_auth(settings: Settings, store: &FileStore, interactive: bool) -> Result<Settings, ()> {
     let set = settings.clone();
     let cred = set.cred().clone();
 
@@ -172,28 +148,83 @@ fn refresh_auth(settings: Settings, config: &Path) -> Result<Settings, ()> {
         .clone()
         .unwrap()
         .refresh(cred)
-        .map_err(|e| println!("{}", e))
-        .and_then(|resp| match resp {
-            Ok(handle) => {
-                let newset = set.update_handle(handle);
-                newset
-                    .save_to(config)
-                    .expect(&"Failed to save config!".red());
-                futures::future::ok(newset)
-            }
+        .map_err(move |e| auth_message(interactive, e))
+        .and_then(move |resp| match resp {
+            Ok(handle) => futures::future::ok(set.update_handle(handle)),
             _ => {
-                println!(
-                    "{}",
-                    &"刷新 Token 失败！请检查您的 Client ID/secret 并重试。".red()
-                );
+                auth_message(interactive, msgs().auth_refresh_failed.red());
                 futures::future::err(())
             }
         });
     let mut runtime = tokio::runtime::Runtime::new().expect("Unable to create tokio runtime!");
-    runtime.block_on(fut)
+    let newset = runtime.block_on(fut)?;
+    store.save(&newset).expect(&"Failed to save config!".red());
+    Ok(newset)
+}
+
+/**
+ * Asks bgm.tv whether the stored token is still usable,
+ * refreshing or re-authenticating if it is not.
+ * Network failures are ignored, so bgmTTY still starts offline
+ */
+fn validate_auth(settings: Settings, store: &FileStore, interactive: bool) -> Result<Settings, ()> {
+    let handle = match settings.auth() {
+        Some(handle) => handle.clone(),
+        None => return new_auth(settings, store, interactive),
+    };
+
+    let mut runtime = tokio::runtime::Runtime::new().expect("Unable to create tokio runtime!");
+    match runtime.block_on(token_status(&handle)) {
+        Ok(TokenState::Valid(ref status)) if status.user_id == handle.user_id() => Ok(settings),
+        Ok(TokenState::Valid(_)) => {
+            auth_message(interactive, msgs().auth_other_account.yellow());
+            new_auth(settings, store, interactive)
+        }
+        Ok(TokenState::Rejected { status, error }) => {
+            log::warn!("token rejected: {} {}", status, error.description());
+
+            if settings.refresh_policy().refresh_on(status) {
+                auth_message(interactive, msgs().auth_expired_refreshing.yellow());
+                refresh_auth(settings.clone(), store, interactive)
+                    .or_else(|_| new_auth(settings, store, interactive))
+            } else {
+                auth_message(interactive, msgs().auth_expired.yellow());
+                new_auth(settings, store, interactive)
+            }
+        }
+        Err(e) => {
+            log::warn!("unable to validate token: {}", e);
+            auth_message(interactive, msgs().auth_unverified.yellow());
+            Ok(settings)
+        }
+    }
+}
+
+/**
+ * `--lang` has to be known before clap prints any help or error
+ */
+fn lang_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == "--lang" {
+            return args.next();
+        } else if arg.starts_with("--lang=") {
+            return Some(arg["--lang=".len()..].to_string());
+        }
+    }
+
+    None
 }
 
 fn main() {
+    if let Err(e) = i18n::select(lang_arg().as_ref().map(String::as_str)) {
+        println!("{}", fill(msgs().lang_invalid, &[&e]).red());
+        std::process::exit(1);
+    }
+
     let matches = clap::App::new("bgmTTY")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
@@ -201,22 +232,22 @@ fn main() {
         .arg(
             clap::Arg::with_name("init")
                 .long("init")
-                .help("(重新)初始化 OAuth 应用凭证"),
+                .help(msgs().arg_init),
         )
         .arg(
             clap::Arg::with_name("refresh")
                 .long("refresh")
-                .help("强制刷新 OAuth Token"),
+                .help(msgs().arg_refresh),
         )
         .arg(
             clap::Arg::with_name("logout")
                 .long("logout")
-                .help("登出账户并立即退出"),
+                .help(msgs().arg_logout),
         )
         .arg(
             clap::Arg::with_name("auth-only")
                 .long("auth-only")
-                .help("仅进行认证或刷新 Token"),
+                .help(msgs().arg_auth_only),
         )
         .arg(
             clap::Arg::with_name("config")
@@ -224,7 +255,7 @@ fn main() {
                 .short("c")
                 .value_name("FILE")
                 .takes_value(true)
-                .help("指定默认位置外的配置文件"),
+                .help(msgs().arg_config),
         )
         .arg(
             clap::Arg::with_name("editor")
@@ -232,8 +263,99 @@ fn main() {
                 .short("e")
                 .value_name("COMMAND")
                 .takes_value(true)
-                .help("指定默认 Vim 以外的编辑器"),
+                .help(msgs().arg_editor),
         )
+        .arg(
+            clap::Arg::with_name("log")
+                .long("log")
+                .value_name("FILE")
+                .takes_value(true)
+                .help(msgs().arg_log),
+        )
+        .arg(
+            clap::Arg::with_name("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .takes_value(true)
+                .possible_values(&["error", "warn", "info", "debug", "trace"])
+                .default_value("info")
+                .help(msgs().arg_log_level),
+        )
+        .arg(
+            clap::Arg::with_name("lang")
+                .long("lang")
+                .value_name("LANG")
+                .takes_value(true)
+                .help(msgs().arg_lang),
+        )
+        .arg(
+            clap::Arg::with_name("no-session")
+                .long("no-session")
+                .help(msgs().arg_no_session),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("import")
+                .about(msgs().cmd_import)
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    clap::SubCommand::with_name("mal")
+                        .about(msgs().cmd_import_mal)
+                        .arg(
+                            clap::Arg::with_name("file")
+                                .value_name("FILE")
+                                .required(true)
+                                .help(msgs().arg_mal_file),
+                        )
+                        .arg(
+                            clap::Arg::with_name("dry-run")
+                                .long("dry-run")
+                                .help(msgs().arg_dry_run),
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("sync")
+                .about(msgs().cmd_sync)
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    clap::SubCommand::with_name("anilist")
+                        .about(msgs().cmd_sync_anilist)
+                        .arg(
+                            clap::Arg::with_name("token")
+                                .long("token")
+                                .value_name("TOKEN")
+                                .takes_value(true)
+                                .required(true)
+                                .env("ANILIST_TOKEN")
+                                .help(msgs().arg_anilist_token),
+                        )
+                        .arg(
+                            clap::Arg::with_name("endpoint")
+                                .long("endpoint")
+                                .value_name("URL")
+                                .takes_value(true)
+                                .help(msgs().arg_anilist_endpoint),
+                        )
+                        .arg(
+                            clap::Arg::with_name("mapping")
+                                .long("mapping")
+                                .value_name("FILE")
+                                .takes_value(true)
+                                .help(msgs().arg_anilist_mapping),
+                        )
+                        .arg(
+                            clap::Arg::with_name("accept-new")
+                                .long("accept-new")
+                                .help(msgs().arg_anilist_accept_new),
+                        )
+                        .arg(
+                            clap::Arg::with_name("dry-run")
+                                .long("dry-run")
+                                .help(msgs().arg_dry_run),
+                        ),
+                ),
+        )
+        .subcommands(cli::subcommands())
         .get_matches();
 
     let args = Args {
@@ -244,58 +366,84 @@ fn main() {
         config: matches
             .value_of("config")
             .map(Into::into)
-            .unwrap_or_else(|| default_path()),
+            .unwrap_or_else(|| FileStore::discover("bgmtty").path().to_path_buf()),
+        no_session: matches.is_present("no-session"),
     };
+    let store = FileStore::new(&args.config);
+
+    if let Some(file) = matches.value_of("log") {
+        let level = logging::parse_level(matches.value_of("log-level").unwrap())
+            .and_then(|level| logging::init(file, level));
+
+        if let Err(e) = level {
+            println!("{}", fill(msgs().log_invalid, &[&e]).red());
+            std::process::exit(1);
+        }
+    }
 
     if matches.is_present("init") {
-        init_credentials(&args.config);
+        init_credentials(&store);
         std::process::exit(0);
     }
 
-    let settings = match load_settings(&args.config) {
+    let settings = match store.load() {
         Ok(set) => set,
         Err(e) => {
             println!("{}", e);
+            println!("{}", msgs().config_missing.yellow());
+            println!("{}", msgs().config_missing_cause.yellow());
+
+            println!("{}", msgs().config_missing_init);
             println!(
                 "{}",
-                "看上去这是您第一次使用 bgmTTY，或者".yellow()
+                fill(msgs().config_missing_path, &[&args.config.display()])
             );
-            println!("{}", "bgmTTY 没法打开配置文件。\n".yellow());
+            std::process::exit(1);
+        }
+    };
+
+    let keymap = match Keymap::from_settings(&settings) {
+        Ok(keymap) => keymap,
+        Err(e) => {
+            println!("{}", fill(msgs().keys_invalid, &[&e]).red());
+            std::process::exit(1);
+        }
+    };
 
-            println!("您可以带参数 --init 启动 bgmTTY 来创建一个新的配置文件，或者");
-            println!("将已有的配置文件放到 {}", args.config.display());
+    let theme = match Theme::from_settings(&settings) {
+        Ok(theme) => theme,
+        Err(e) => {
+            println!("{}", fill(msgs().theme_invalid, &[&e]).red());
             std::process::exit(1);
         }
     };
 
     if matches.is_present("logout") {
-        settings
-            .logout()
-            .save_to(&args.config)
+        store
+            .save(&settings.logout())
             .expect(&"Failed to save config!".red());
 
         return;
     }
 
+    // Scripts running the subcommands can not open a browser
+    let interactive = !cli::handles(&matches);
     let settings = if let Some(auth) = settings.auth() {
         if auth.outdated() {
-            new_auth(settings, &args.config)
-        } else if auth.requires_refresh() || matches.is_present("refresh") {
-            refresh_auth(settings, &args.config)
+            new_auth(settings, &store, interactive)
+        } else if settings.requires_refresh(&SystemClock) || matches.is_present("refresh") {
+            refresh_auth(settings, &store, interactive)
         } else {
-            Ok(settings)
+            validate_auth(settings, &store, interactive)
         }
     } else {
-        new_auth(settings, &args.config)
+        new_auth(settings, &store, interactive)
     };
 
     let settings = if let Ok(s) = settings {
         s
     } else {
-        println!(
-            "{}",
-            "验证失败！可能是风把网线刮断了？".red()
-        );
+        auth_message(interactive, msgs().auth_failed.red());
         std::process::exit(1);
     };
 
@@ -303,8 +451,68 @@ fn main() {
         return;
     }
 
-    let client = Client::new(settings);
-    bootstrap(client, args).expect("Terminal failed");
+    let client = Client::new(settings).retries(REQUEST_RETRIES);
+
+    // Keep the client in sync with whatever gets saved from now on
+    let shared = client.clone();
+    store.on_change(Box::new(move |settings| {
+        shared.update_settings(settings.clone())
+    }));
+
+    // Tokens refreshed after a 401 mid-session have to survive a restart
+    let saver = FileStore::new(&args.config);
+    client.on_refresh(Box::new(move |settings| {
+        if let Err(e) = saver.save(settings) {
+            log::warn!("unable to save refreshed token: {}", e);
+        }
+    }));
+
+    if let ("import", Some(sub)) = matches.subcommand() {
+        if let ("mal", Some(mal)) = sub.subcommand() {
+            let file = mal.value_of("file").unwrap();
+            if let Err(e) = import::import_mal(&client, file, mal.is_present("dry-run")) {
+                println!("{}", fill(msgs().import_failed, &[&e]).red());
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if let ("sync", Some(sub)) = matches.subcommand() {
+        if let ("anilist", Some(anilist)) = sub.subcommand() {
+            let remote = sync::AniList::new(
+                anilist
+                    .value_of("endpoint")
+                    .unwrap_or(sync::ANILIST_ENDPOINT)
+                    .to_string(),
+                anilist.value_of("token").unwrap().to_string(),
+            );
+            let mapping = anilist
+                .value_of("mapping")
+                .map(Into::into)
+                .unwrap_or_else(|| sync::default_mapping_path(&args.config));
+
+            if let Err(e) = sync::sync_anilist(
+                &client,
+                &remote,
+                &mapping,
+                anilist.is_present("dry-run"),
+                anilist.is_present("accept-new"),
+            ) {
+                println!("{}", fill(msgs().sync_failed, &[&e]).red());
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if let Some(code) = cli::run(&client, &matches) {
+        std::process::exit(code);
+    }
+
+    bootstrap(client, args, keymap, theme, store).expect("Terminal failed");
 }
 
 trait RectExt {
@@ -363,11 +571,12 @@ impl CollectionStatusExt for CollectionStatus {
     fn disp(&self) -> &'static str {
         use bgmtv::client::CollectionStatus::*;
         match self {
-            Wished => "打算做",
-            Doing => "在做了",
-            Done => "完成！",
-            OnHold => "摸了",
-            Dropped => "没得了",
+            Wished => msgs().status_wished,
+            Doing => msgs().status_doing,
+            Done => msgs().status_done,
+            OnHold => msgs().status_on_hold,
+            Dropped => msgs().status_dropped,
+            Unknown(_) => msgs().status_unknown,
         }
     }
 
@@ -379,6 +588,7 @@ impl CollectionStatusExt for CollectionStatus {
             Done => OnHold,
             OnHold => Dropped,
             Dropped => Wished,
+            Unknown(_) => Wished,
         }
     }
 }
@@ -390,16 +600,33 @@ trait SubjectTypeExt: Sized {
 impl SubjectTypeExt for SubjectType {
     fn disp(&self) -> &'static str {
         match self {
-            SubjectType::Anime => "动画骗",
-            SubjectType::Book => "书籍",
-            SubjectType::Real => "三次元",
-            SubjectType::Game => "游戏",
-            SubjectType::Music => "音乐",
+            SubjectType::Anime => msgs().type_anime,
+            SubjectType::Book => msgs().type_book,
+            SubjectType::Real => msgs().type_real,
+            SubjectType::Game => msgs().type_game,
+            SubjectType::Music => msgs().type_music,
+            SubjectType::Unknown(_) => msgs().type_unknown,
         }
     }
 }
 
-fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
+trait WeekdayExt {
+    fn disp(&self) -> &'static str;
+}
+
+impl WeekdayExt for chrono::Weekday {
+    fn disp(&self) -> &'static str {
+        msgs().weekdays[self.num_days_from_monday() as usize]
+    }
+}
+
+fn bootstrap(
+    client: Client,
+    args: Args,
+    keymap: Keymap,
+    theme: Theme,
+    store: FileStore,
+) -> Result<(), failure::Error> {
     let stdout = std::io::stdout().into_raw_mode()?;
     let stdout = termion::input::MouseTerminal::from(stdout);
     let stdout = termion::screen::AlternateScreen::from(stdout);
@@ -417,8 +644,21 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
 
     kickoff_listener(evtx, stdin_lock.clone());
 
-    let mut app = AppState::create(apptx, client);
-    let mut ui = UIState::with(&args, stdin_lock);
+    let journal = journal::default_journal_path(&args.config);
+    let mut app = AppState::create(apptx, client, journal);
+    let mut ui = UIState::with(&args, &keymap, &store, stdin_lock);
+
+    let session_path = session::default_session_path(&args.config);
+    if !args.no_session {
+        match session::Session::load_from(&session_path) {
+            Ok(Some(session)) => ui.restore(session),
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("{}", e);
+                app.publish_message(fill(msgs().session_load_failed, &[&e]));
+            }
+        }
+    }
 
     loop {
         // Process Splits
@@ -453,10 +693,10 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                 let mut help_block = Block::default().borders(Borders::LEFT);
                 help_block.render(&mut f, primary_split[1]);
                 let help_inner = help_block.inner(primary_split[1]);
-                let mut help_texts = HELP_DATABASE
+                let help_entries = help::entries(&ui, ui.keymap());
+                let mut help_texts = help_entries
                     .iter()
-                    .filter(|e| e.pred()(&ui))
-                    .map(Into::into)
+                    .map(|e| e.text(&theme))
                     .collect::<Vec<CJKText>>();
 
                 let mut help_scroll = Scroll::with(&mut ui.help_scroll);
@@ -504,13 +744,17 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                 )
                 .split(primary_chunk);
 
-            let mut tab_block = Block::default().borders(Borders::ALL).title("bgmTTY");
+            let title = match app.pending().len() {
+                0 => "bgmTTY".to_string(),
+                count => fill(msgs().title_pending, &[&count]),
+            };
+            let mut tab_block = Block::default().borders(Borders::ALL).title(&title);
             tab_block.render(&mut f, chunks[0]);
             let tab_inner = tab_block.inner(chunks[0]);
             let tab_names = ui.tabs.iter().map(|e| e.disp(&app)).collect::<Vec<_>>();
             let tab_name_borrows = tab_names.iter().map(|e| e.as_str()).collect::<Vec<_>>();
-            let mut tabber =
-                Tabber::with(tab_name_borrows.as_slice(), &mut ui.tab_scroll).select(ui.tab);
+            let mut tabber = Tabber::with(tab_name_borrows.as_slice(), &mut ui.tab_scroll, &theme)
+                .select(ui.tab);
             tabber.set_bound(tab_inner);
             tabber.cap_bound();
             tabber.render(&mut f, tab_inner);
@@ -534,16 +778,21 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
             let needs_help = ui.needs_help();
             let status = ui.command.prompt().unwrap_or_else(|| {
                 if needs_help {
-                    "按 h 可以打开帮助哦".to_string()
+                    let key = ui.keymap().describe(Action::HelpToggle).remove(0);
+                    fill(msgs().help_hint, &[&key])
                 } else {
                     app.last_message()
                 }
             });
             let mut status_line = CJKText::new(&status);
+            if status.starts_with(msgs().request_failed) {
+                status_line.set_style(theme.error);
+            }
             let status_inner = chunks[2].padding_hoz(1);
             status_line.render(&mut f, status_inner);
 
             let is_double_click = ui.is_double_click();
+            let page_links = ui.active_tab().links(&mut app);
             match ui.active_tab_mut() {
                 Tab::Collection => {
                     // Render collections
@@ -580,7 +829,10 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                         .iter()
                         .map(SubjectTypeExt::disp)
                         .collect::<Vec<&'static str>>();
-                    let mut filters = FilterList::with(&filter_names, &ui.filters);
+                    let sort = ui.sort.map(|sort| sort.disp());
+                    let mut filters = FilterList::with(&filter_names, &ui.filters, &theme)
+                        .sorting(sort.as_ref().map(String::as_str))
+                        .searching(&ui.query);
 
                     let collection = app.fetch_collection();
 
@@ -630,17 +882,24 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                     );
 
                     if let FetchResult::Direct(collection) = collection {
-                        // Sync app state into ui state
-                        ui.focus.set_limit(collection.len());
-
                         let inner = outer.inner(subchunks[1]);
 
                         let collection = Some(collection);
-                        let mut ents = ui
-                            .do_filter(&collection)
-                            .map(ViewingEntry::with_coll)
+                        let visible = ui.do_filter(&collection).collect::<Vec<_>>();
+                        let query = ui.query.clone();
+                        let mut ents = visible
+                            .iter()
+                            .map(|e| {
+                                let mut ent = ViewingEntry::with_coll(e, &theme);
+                                ent.highlight(&query);
+                                ent.mark_pending(app.is_pending(e.subject.id));
+                                ent
+                            })
                             .collect::<Vec<_>>();
 
+                        // Sync app state into ui state
+                        let moved = ui.sync_collection(&visible);
+
                         if let Some(i) = ui.focus.get() {
                             ents[i].select(true);
                         }
@@ -659,6 +918,8 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
 
                         if let Some(PendingUIEvent::ScrollIntoView(index)) = pending {
                             scroll.scroll_into_view(index);
+                        } else if let Some(index) = moved {
+                            scroll.scroll_into_view(index);
                         }
 
                         if let Some(PendingUIEvent::Click(x, y, btn)) = pending {
@@ -677,7 +938,7 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                                     {
                                         Some(ViewingEntryEvent::Click) => {
                                             if ui.focus.get() == Some(i) && is_double_click {
-                                                ui.goto_detail(collection.unwrap()[i].subject.id);
+                                                ui.goto_detail(visible[i].subject.id);
                                             } else {
                                                 ui.focus.set(Some(i));
                                             }
@@ -716,11 +977,10 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
 
                     let mut text_comp = if text != "" {
                         let mut text_comp = CJKText::new(text);
-                        text_comp
-                            .set_style(tui::style::Style::default().fg(tui::style::Color::White));
+                        text_comp.set_style(theme.text);
                         text_comp
                     } else {
-                        CJKText::new("按 e 或 Enter 开始输入，然后双击 Enter 搜索")
+                        CJKText::new(msgs().search_placeholder)
                     };
                     text_comp.render(&mut f, input_inner);
                 }
@@ -728,112 +988,213 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                 Tab::Subject {
                     id,
                     scroll: ref mut scroll_val,
+                    ref mut focus,
+                    ref mut episode,
                 } => {
-                    let mut block = Block::default().borders(Borders::ALL ^ Borders::TOP);
-                    block.render(&mut f, chunks[1]);
-                    SingleCell::new(tui::symbols::line::VERTICAL_RIGHT)
-                        .render(&mut f, Rect::new(chunks[1].x, chunks[1].y - 1, 1, 1));
-                    SingleCell::new(tui::symbols::line::VERTICAL_LEFT).render(
-                        &mut f,
-                        Rect::new(chunks[1].x + chunks[1].width - 1, chunks[1].y - 1, 1, 1),
-                    );
-                    let inner = block.inner(chunks[1]).padding_left(1);
+                    let id = *id;
+                    let inner = render_page_block(&mut f, chunks[1]);
 
                     use tui::style::*;
 
-                    let detail = app.fetch_collection_detail(*id);
-                    let subject = app.fetch_subject(*id);
+                    let detail = app.fetch_collection_detail(id);
+                    let subject = app.fetch_subject(id);
+                    let episodes = app.fetch_episodes(id) + app.fetch_episode_progress(id);
 
                     match detail + subject {
                         FetchResult::Deferred => {
-                            let text = format!("猫咪检索中... ID: {}", id);
+                            let text = fill(msgs().fetching_id, &[&id]);
                             CJKText::new(&text).render(&mut f, inner);
                         }
                         FetchResult::Direct((detail, subject)) => {
-                            let mut scroll = Scroll::with(scroll_val);
+                            let mut header = Vec::new();
 
-                            let mut subject_text = CJKText::raw(
+                            header.push(CJKText::raw(
                                 [
-                                    (subject.name.as_str(), Style::default().fg(Color::Yellow)),
+                                    (subject.name.as_str(), theme.title),
                                     ("\n", Style::default()),
-                                    (subject.name_cn.as_str(), Style::default().fg(Color::White)),
+                                    (subject.name_cn.as_str(), theme.text),
                                     ("\n\n", Style::default()),
                                     (subject.summary.as_str(), Style::default()),
                                     ("\n\n", Style::default()),
                                 ]
                                 .to_vec(),
-                            );
-
-                            scroll.push(&mut subject_text);
+                            ));
 
                             let status;
                             let score;
                             let tag;
-                            let mut detail_cont;
-                            let mut detail_text;
-                            let mut comment;
+                            let detail_cont;
 
                             if let Some(detail) = detail {
                                 detail_cont = detail;
                                 status = detail_cont.status.disp();
                                 score = if detail_cont.rating == 0 {
-                                    "未评分".to_string()
+                                    msgs().unrated.to_string()
                                 } else {
                                     format!("{} / 10", detail_cont.rating)
                                 };
                                 tag = detail_cont.tag.join(", ");
 
-                                detail_text = CJKText::raw(
+                                header.push(CJKText::raw(
                                     [
-                                        ("状态: ", Style::default().fg(Color::Blue)),
+                                        (msgs().label_status, theme.label),
                                         (status, Style::default()),
                                         ("\n", Style::default()),
-                                        ("评分: ", Style::default().fg(Color::Blue)),
+                                        (msgs().label_rating, theme.label),
                                         (&score, Style::default()),
                                         ("\n", Style::default()),
-                                        ("标签: ", Style::default().fg(Color::Blue)),
+                                        (msgs().label_tags, theme.label),
                                         (&tag, Style::default()),
                                         ("\n\n", Style::default()),
-                                        ("评论: ", Style::default().fg(Color::Blue)),
+                                        (msgs().label_comment, theme.label),
                                     ]
                                     .to_vec(),
-                                );
+                                ));
 
-                                comment = CJKText::new(&detail_cont.comment);
-
-                                scroll.push(&mut detail_text);
-                                scroll.push(&mut comment);
+                                header.push(CJKText::new(&detail_cont.comment));
                             } else {
-                                detail_text = CJKText::raw(
+                                header.push(CJKText::raw(
                                     [
-                                        ("状态: ", Style::default().fg(Color::Blue)),
-                                        ("没打算", Style::default()),
+                                        (msgs().label_status, theme.label),
+                                        (msgs().not_collected, Style::default()),
                                     ]
                                     .to_vec(),
-                                );
+                                ));
+                            }
+
+                            header.push(CJKText::raw(
+                                [
+                                    ("\n\n", Style::default()),
+                                    (msgs().section_episodes, theme.label),
+                                ]
+                                .to_vec(),
+                            ));
 
-                                scroll.push(&mut detail_text);
+                            let mut grid = None;
+                            match episodes {
+                                FetchResult::Deferred => header.push(CJKText::new(msgs().loading)),
+                                FetchResult::Direct((ref list, _)) if list.len() == 0 => {
+                                    header.push(CJKText::new(msgs().empty))
+                                }
+                                FetchResult::Direct((ref list, ref progress)) => {
+                                    grid = Some(EpisodeGrid::with(list, progress, episode, &theme));
+                                }
                             }
 
-                            scroll.set_bound(inner);
-                            scroll.cap_bound();
-                            scroll.render(&mut f, inner);
+                            let clicked = render_page(
+                                &mut f,
+                                inner,
+                                scroll_val,
+                                focus,
+                                header,
+                                grid,
+                                &page_links,
+                                &pending,
+                                is_double_click,
+                                &theme,
+                            );
 
-                            if let Some(PendingUIEvent::Click(x, y, btn)) = pending {
-                                if inner.contains(x, y) {
-                                    match scroll.intercept(x, y, btn) {
-                                        Some(ScrollEvent::ScrollTo(pos)) => {
-                                            scroll_val.set(pos);
-                                        }
-                                        Some(ScrollEvent::ScrollUp) => {
-                                            scroll_val.delta(-1);
-                                        }
-                                        Some(ScrollEvent::ScrollDown) => {
-                                            scroll_val.delta(1);
-                                        }
-                                        _ => {}
-                                    }
+                            match clicked {
+                                Some(PageClick::Link(link)) => ui.open_link(link),
+                                Some(PageClick::Episode(index)) => {
+                                    let toggle = |current| match current {
+                                        Some(EpisodeStatus::Watched) => None,
+                                        _ => Some(EpisodeStatus::Watched),
+                                    };
+                                    ui.mark_episode(id, index, toggle, &mut app);
                                 }
+                                None => {}
+                            }
+                        }
+                    }
+                }
+
+                Tab::Character {
+                    id,
+                    scroll: ref mut scroll_val,
+                    ref mut focus,
+                } => {
+                    let inner = render_page_block(&mut f, chunks[1]);
+
+                    use tui::style::*;
+
+                    match app.fetch_character(*id) {
+                        FetchResult::Deferred => {
+                            let text = fill(msgs().fetching_id, &[id]);
+                            CJKText::new(&text).render(&mut f, inner);
+                        }
+                        FetchResult::Direct(character) => {
+                            let header = vec![CJKText::raw(
+                                [
+                                    (character.name.as_str(), theme.title),
+                                    ("\n\n", Style::default()),
+                                    (character.summary.as_str(), Style::default()),
+                                ]
+                                .to_vec(),
+                            )];
+
+                            let clicked = render_page(
+                                &mut f,
+                                inner,
+                                scroll_val,
+                                focus,
+                                header,
+                                None,
+                                &page_links,
+                                &pending,
+                                is_double_click,
+                                &theme,
+                            );
+
+                            if let Some(PageClick::Link(link)) = clicked {
+                                ui.open_link(link);
+                            }
+                        }
+                    }
+                }
+
+                Tab::Person {
+                    id,
+                    scroll: ref mut scroll_val,
+                    ref mut focus,
+                } => {
+                    let inner = render_page_block(&mut f, chunks[1]);
+
+                    use tui::style::*;
+
+                    match app.fetch_person(*id) {
+                        FetchResult::Deferred => {
+                            let text = fill(msgs().fetching_id, &[id]);
+                            CJKText::new(&text).render(&mut f, inner);
+                        }
+                        FetchResult::Direct(person) => {
+                            let career = person.career.join(", ");
+                            let header = vec![CJKText::raw(
+                                [
+                                    (person.name.as_str(), theme.title),
+                                    ("\n", Style::default()),
+                                    (career.as_str(), theme.text),
+                                    ("\n\n", Style::default()),
+                                    (person.summary.as_str(), Style::default()),
+                                ]
+                                .to_vec(),
+                            )];
+
+                            let clicked = render_page(
+                                &mut f,
+                                inner,
+                                scroll_val,
+                                focus,
+                                header,
+                                None,
+                                &page_links,
+                                &pending,
+                                is_double_click,
+                                &theme,
+                            );
+
+                            if let Some(PageClick::Link(link)) = clicked {
+                                ui.open_link(link);
                             }
                         }
                     }
@@ -878,27 +1239,27 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                             let mut heading = if result.count == 0 {
                                 CJKText::raw(
                                     [
-                                        (search.as_str(), Style::default().fg(Color::Green)),
+                                        (search.as_str(), theme.accent),
                                         ("\n", Style::default()),
-                                        ("这里是", Style::default()),
-                                        ("没有猫咪", Style::default().fg(Color::Yellow)),
-                                        ("的荒原\n\n是不是越界了?", Style::default()),
+                                        (msgs().no_results_before, Style::default()),
+                                        (msgs().no_results, theme.title),
+                                        (msgs().no_results_after, Style::default()),
                                     ]
                                     .to_vec(),
                                 )
                             } else {
                                 CJKText::raw(
                                     [
-                                        (search.as_str(), Style::default().fg(Color::Green)),
+                                        (search.as_str(), theme.accent),
                                         ("\n", Style::default()),
-                                        (count.as_str(), Style::default().fg(Color::Yellow)),
-                                        (" 结果，", Style::default()),
-                                        (lower.as_str(), Style::default().fg(Color::Yellow)),
+                                        (count.as_str(), theme.number),
+                                        (msgs().results_count, Style::default()),
+                                        (lower.as_str(), theme.number),
                                         (" - ", Style::default()),
-                                        (upper.as_str(), Style::default().fg(Color::Yellow)),
-                                        ("，", Style::default()),
-                                        (visible.as_str(), Style::default().fg(Color::Yellow)),
-                                        (" 可见", Style::default()),
+                                        (upper.as_str(), theme.number),
+                                        (msgs().results_range, Style::default()),
+                                        (visible.as_str(), theme.number),
+                                        (msgs().results_visible, Style::default()),
                                     ]
                                     .to_vec(),
                                 )
@@ -910,7 +1271,7 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                             let mut ents = result
                                 .list
                                 .iter()
-                                .map(ViewingEntry::with_subject)
+                                .map(|s| ViewingEntry::with_subject(s, &theme))
                                 .collect::<Vec<_>>();
 
                             if let Some(focus) = focus.get().and_then(|focus| ents.get_mut(focus)) {
@@ -962,6 +1323,97 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
                         }
                     }
                 }
+
+                Tab::Pending {
+                    scroll: ref mut scroll_val,
+                    ref mut focus,
+                } => {
+                    let inner = render_page_block(&mut f, chunks[1]);
+
+                    use tui::style::*;
+
+                    let writes = app.pending();
+                    focus.set_limit(writes.len());
+
+                    if writes.len() == 0 {
+                        CJKText::new(msgs().no_pending).render(&mut f, inner);
+                    } else {
+                        let lines = writes
+                            .iter()
+                            .map(|w| {
+                                let time = w.time.with_timezone(&chrono::Local);
+                                (time.format("%m-%d %H:%M").to_string(), w.mutation.disp())
+                            })
+                            .collect::<Vec<_>>();
+
+                        let mut texts = writes
+                            .iter()
+                            .zip(lines.iter())
+                            .enumerate()
+                            .map(|(i, (write, (time, desc)))| {
+                                let mut content = [
+                                    (time.as_str(), theme.info),
+                                    ("  ", Style::default()),
+                                    (desc.as_str(), Style::default()),
+                                ]
+                                .to_vec();
+                                if write.conflict {
+                                    content.push(("  ", Style::default()));
+                                    content.push((msgs().conflict_mark, theme.error));
+                                }
+                                if let Some(ref reason) = write.rejected {
+                                    content.push(("  ", Style::default()));
+                                    content.push((msgs().rejected_mark, theme.error));
+                                    content.push((": ", theme.error));
+                                    content.push((reason.as_str(), theme.error));
+                                }
+
+                                let mut text = CJKText::raw(content);
+                                if focus.get() == Some(i) {
+                                    text.set_style(theme.selection);
+                                }
+                                text
+                            })
+                            .collect::<Vec<_>>();
+
+                        let mut scroll = Scroll::with(scroll_val);
+                        for text in texts.iter_mut() {
+                            scroll.push(text);
+                        }
+
+                        scroll.set_bound(inner);
+                        scroll.cap_bound();
+                        scroll.render(&mut f, inner);
+
+                        if let Some(PendingUIEvent::ScrollIntoView(index)) = pending {
+                            scroll.scroll_into_view(index);
+                        }
+
+                        if let Some(PendingUIEvent::Click(x, y, btn)) = pending {
+                            if inner.contains(x, y) {
+                                match scroll.intercept(x, y, btn) {
+                                    Some(ScrollEvent::ScrollTo(pos)) => {
+                                        scroll_val.set(pos);
+                                    }
+                                    Some(ScrollEvent::ScrollUp) => {
+                                        scroll_val.delta(-1);
+                                    }
+                                    Some(ScrollEvent::ScrollDown) => {
+                                        scroll_val.delta(1);
+                                    }
+                                    Some(ScrollEvent::Sub(i)) => {
+                                        if focus.get() == Some(i) && is_double_click {
+                                            ui.goto_detail(writes[i].mutation.subject());
+                                        } else {
+                                            focus.set(Some(i));
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                }
             }
         })?;
 
@@ -970,6 +1422,8 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
         }
 
         loop {
+            app.replay_journal();
+
             let mut select = Select::new();
 
             select.recv(&evrx);
@@ -1001,9 +1455,178 @@ fn bootstrap(client: Client, args: Args) -> Result<(), failure::Error> {
         }
     }
 
+    if !args.no_session {
+        if let Err(e) = ui.session().save_to(&session_path) {
+            log::error!("{}", e);
+        }
+    }
+
     Ok(())
 }
 
+/**
+ * Draws the borders shared by all page tabs, returning the content area
+ */
+fn render_page_block<B: Backend>(f: &mut Frame<B>, area: Rect) -> Rect {
+    let mut block = Block::default().borders(Borders::ALL ^ Borders::TOP);
+    block.render(f, area);
+    SingleCell::new(tui::symbols::line::VERTICAL_RIGHT)
+        .render(f, Rect::new(area.x, area.y - 1, 1, 1));
+    SingleCell::new(tui::symbols::line::VERTICAL_LEFT)
+        .render(f, Rect::new(area.x + area.width - 1, area.y - 1, 1, 1));
+    block.inner(area).padding_left(1)
+}
+
+/**
+ * What the user double-clicked in a page tab
+ */
+enum PageClick {
+    Link(Link),
+    Episode(usize),
+}
+
+/**
+ * Renders the header texts, the episode grid if any, then the link sections of a page tab
+ */
+fn render_page<B: Backend>(
+    f: &mut Frame<B>,
+    inner: Rect,
+    scroll_val: &mut ScrollState,
+    focus: &mut FocusState,
+    mut header: Vec<CJKText>,
+    mut grid: Option<EpisodeGrid>,
+    sections: &[LinkSection],
+    pending: &Option<PendingUIEvent>,
+    is_double_click: bool,
+    theme: &Theme,
+) -> Option<PageClick> {
+    use tui::style::*;
+
+    let links = flatten_links(sections);
+    focus.set_limit(links.len());
+
+    let tags = links
+        .iter()
+        .map(|e| {
+            if e.tag == "" {
+                String::new()
+            } else {
+                format!("[{}] ", e.tag)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut texts = Vec::new();
+    // Maps scroll children to link indexes
+    let mut index = Vec::new();
+    let mut current = 0;
+
+    for section in sections.iter() {
+        texts.push(CJKText::raw(
+            [("\n\n", Style::default()), (section.title, theme.label)].to_vec(),
+        ));
+        index.push(None);
+
+        match section.entries {
+            FetchResult::Deferred => {
+                texts.push(CJKText::new(msgs().loading));
+                index.push(None);
+            }
+            FetchResult::Direct(ref entries) if entries.len() == 0 => {
+                texts.push(CJKText::new(msgs().empty));
+                index.push(None);
+            }
+            FetchResult::Direct(ref entries) => {
+                for _ in entries.iter() {
+                    let entry = links[current];
+                    let mut text = CJKText::raw(
+                        [
+                            (tags[current].as_str(), theme.label),
+                            (entry.name.as_str(), theme.title),
+                            (" ", Style::default()),
+                            (entry.extra.as_str(), Style::default()),
+                        ]
+                        .to_vec(),
+                    );
+
+                    if focus.get() == Some(current) {
+                        text.set_style(theme.selection);
+                    }
+
+                    texts.push(text);
+                    index.push(Some(current));
+                    current += 1;
+                }
+            }
+        }
+    }
+
+    let grid_index = grid.as_ref().map(|_| header.len());
+    let base = header.len() + grid_index.iter().count();
+    let mut scroll = Scroll::with(scroll_val);
+    for text in header.iter_mut() {
+        scroll.push(text);
+    }
+    if let Some(ref mut grid) = grid {
+        scroll.push(grid);
+    }
+    for text in texts.iter_mut() {
+        scroll.push(text);
+    }
+
+    scroll.set_bound(inner);
+    scroll.cap_bound();
+    scroll.render(f, inner);
+
+    if let Some(PendingUIEvent::ScrollIntoView(target)) = *pending {
+        if let Some(pos) = index.iter().position(|i| *i == Some(target)) {
+            scroll.scroll_into_view(base + pos);
+        }
+    }
+
+    if let Some(PendingUIEvent::Click(x, y, btn)) = *pending {
+        if inner.contains(x, y) {
+            match scroll.intercept(x, y, btn) {
+                Some(ScrollEvent::ScrollTo(pos)) => {
+                    scroll_val.set(pos);
+                }
+                Some(ScrollEvent::ScrollUp) => {
+                    scroll_val.delta(-1);
+                }
+                Some(ScrollEvent::ScrollDown) => {
+                    scroll_val.delta(1);
+                }
+                Some(ScrollEvent::Sub(i)) if Some(i) == grid_index => {
+                    let (gx, gy) = scroll.locate(i, x, y);
+                    if let Some(ref mut grid) = grid {
+                        let width = inner.width - 1;
+                        grid.set_bound(Rect::new(0, 0, width, grid.height(width)));
+                        if let Some(EpisodeGridEvent::Select(i)) = grid.intercept(gx, gy, btn) {
+                            if grid.selected() == Some(i) && is_double_click {
+                                return Some(PageClick::Episode(i));
+                            } else {
+                                grid.select(i);
+                            }
+                        }
+                    }
+                }
+                Some(ScrollEvent::Sub(i)) if i >= base => {
+                    if let Some(Some(i)) = index.get(i - base) {
+                        if focus.get() == Some(*i) && is_double_click {
+                            return Some(PageClick::Link(links[*i].link));
+                        } else {
+                            focus.set(Some(*i));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
 fn kickoff_listener(tx: Sender<UIEvent>, stdin_lock: Arc<Mutex<()>>) {
     use std::io;
     use std::thread;
@@ -0,0 +1,648 @@
+use crate::i18n::{fill, msgs};
+use crate::{CollectionStatusExt, SubjectTypeExt};
+use bgmtv::api::BangumiApi;
+use bgmtv::client::{
+    CollectionDetail, CollectionEntry, CollectionStatus, SubjectSmall, SubjectType,
+};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use colored::*;
+use serde_json::json;
+use tokio::runtime::Runtime;
+
+/**
+ * Exit codes of the subcommands below. Everything else exits with 0 on success
+ */
+pub const EXIT_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+
+const SEARCH_LIMIT: usize = 20;
+
+pub struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn usage(message: String) -> Failure {
+        Failure {
+            code: EXIT_USAGE,
+            message,
+        }
+    }
+
+    fn not_found(message: String) -> Failure {
+        Failure {
+            code: EXIT_NOT_FOUND,
+            message,
+        }
+    }
+}
+
+impl From<failure::Error> for Failure {
+    fn from(e: failure::Error) -> Failure {
+        Failure {
+            code: EXIT_FAILED,
+            message: format!("{}{}", msgs().request_failed, e),
+        }
+    }
+}
+
+fn json_arg() -> Arg<'static, 'static> {
+    Arg::with_name("json").long("json").help(msgs().arg_json)
+}
+
+fn id_arg() -> Arg<'static, 'static> {
+    Arg::with_name("id")
+        .value_name("ID")
+        .required(true)
+        .help(msgs().arg_subject_id)
+}
+
+pub fn subcommands() -> Vec<App<'static, 'static>> {
+    vec![
+        SubCommand::with_name("list")
+            .about(msgs().cmd_list)
+            .arg(
+                Arg::with_name("status")
+                    .long("status")
+                    .value_name("STATUS")
+                    .takes_value(true)
+                    .help(msgs().arg_status_filter),
+            )
+            .arg(
+                Arg::with_name("type")
+                    .long("type")
+                    .value_name("TYPE")
+                    .takes_value(true)
+                    .help(msgs().arg_type_filter),
+            )
+            .arg(json_arg()),
+        SubCommand::with_name("show")
+            .about(msgs().cmd_show)
+            .arg(id_arg())
+            .arg(json_arg()),
+        SubCommand::with_name("progress")
+            .about(msgs().cmd_progress)
+            // Otherwise clap takes `-1` for a flag
+            .setting(AppSettings::AllowNegativeNumbers)
+            .arg(id_arg())
+            .arg(
+                Arg::with_name("progress")
+                    .value_name("+N|-N|=N")
+                    .required(true)
+                    .allow_hyphen_values(true)
+                    .help(msgs().arg_progress_spec),
+            )
+            .arg(json_arg()),
+        SubCommand::with_name("status")
+            .about(msgs().cmd_status)
+            .arg(id_arg())
+            .arg(
+                Arg::with_name("status")
+                    .value_name("STATUS")
+                    .required(true)
+                    .help(msgs().arg_status_value),
+            )
+            .arg(json_arg()),
+        SubCommand::with_name("rate")
+            .about(msgs().cmd_rate)
+            .arg(id_arg())
+            .arg(
+                Arg::with_name("rating")
+                    .value_name("0-10")
+                    .required(true)
+                    .help(msgs().arg_rating_value),
+            )
+            .arg(json_arg()),
+        SubCommand::with_name("search")
+            .about(msgs().cmd_search)
+            .arg(
+                Arg::with_name("keywords")
+                    .value_name("KEYWORDS")
+                    .required(true)
+                    .multiple(true)
+                    .help(msgs().arg_keywords),
+            )
+            .arg(json_arg()),
+    ]
+}
+
+/**
+ * Whether the subcommand is one of the above
+ */
+pub fn handles(matches: &ArgMatches) -> bool {
+    match matches.subcommand_name() {
+        Some(name) => subcommands().iter().any(|cmd| cmd.get_name() == name),
+        None => false,
+    }
+}
+
+/**
+ * Runs the subcommand if it's one of the above, returning the exit code
+ */
+pub fn run<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Option<i32> {
+    let result = match matches.subcommand() {
+        ("list", Some(sub)) => list(client, sub),
+        ("show", Some(sub)) => show(client, sub),
+        ("progress", Some(sub)) => progress(client, sub),
+        ("status", Some(sub)) => status(client, sub),
+        ("rate", Some(sub)) => rate(client, sub),
+        ("search", Some(sub)) => search(client, sub),
+        _ => return None,
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        Err(failure) => {
+            eprintln!("{}", failure.message.red());
+            Some(failure.code)
+        }
+    }
+}
+
+const STATUS_NAMES: &str = "wish|do|collect|on_hold|dropped";
+
+fn parse_status(name: &str) -> Result<CollectionStatus, Failure> {
+    match name {
+        "wish" => Ok(CollectionStatus::Wished),
+        "do" | "doing" => Ok(CollectionStatus::Doing),
+        "collect" | "done" => Ok(CollectionStatus::Done),
+        "on_hold" | "hold" => Ok(CollectionStatus::OnHold),
+        "dropped" | "drop" => Ok(CollectionStatus::Dropped),
+        _ => Err(Failure::usage(fill(
+            msgs().cli_invalid_status,
+            &[&name, &STATUS_NAMES],
+        ))),
+    }
+}
+
+const TYPE_NAMES: &str = "anime|book|music|game|real";
+
+fn parse_type(name: &str) -> Result<SubjectType, Failure> {
+    match name {
+        "anime" => Ok(SubjectType::Anime),
+        "book" => Ok(SubjectType::Book),
+        "music" => Ok(SubjectType::Music),
+        "game" => Ok(SubjectType::Game),
+        "real" => Ok(SubjectType::Real),
+        _ => Err(Failure::usage(fill(
+            msgs().cli_invalid_type,
+            &[&name, &TYPE_NAMES],
+        ))),
+    }
+}
+
+fn parse_id(matches: &ArgMatches) -> Result<u64, Failure> {
+    let id = matches.value_of("id").unwrap();
+    id.parse()
+        .map_err(|_| Failure::usage(fill(msgs().cli_invalid_id, &[&id])))
+}
+
+fn display_name(subject: &SubjectSmall) -> &str {
+    if subject.name_cn != "" {
+        &subject.name_cn
+    } else {
+        &subject.name
+    }
+}
+
+/**
+ * Episodes for most subjects, volumes for books, like the progress bar in the UI
+ */
+fn progress_of(entry: &CollectionEntry) -> (u64, Option<u64>) {
+    match entry.subject.subject_type {
+        SubjectType::Book => (entry.vol_status, entry.subject.vols_count),
+        _ => (entry.ep_status, entry.subject.eps_count),
+    }
+}
+
+fn disp_progress(entry: &CollectionEntry) -> String {
+    match progress_of(entry) {
+        (watched, Some(total)) if total > 0 => format!("{}/{}", watched, total),
+        (watched, _) => format!("{}/?", watched),
+    }
+}
+
+fn print_json(value: &serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/**
+ * bgm.tv only lists the subjects in progress, so other statuses are refused
+ * rather than listing nothing
+ */
+fn list<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Result<(), Failure> {
+    if let Some(name) = matches.value_of("status") {
+        if parse_status(name)? != CollectionStatus::Doing {
+            return Err(Failure::usage(fill(msgs().cli_unlisted_status, &[&name])));
+        }
+    }
+    let subject_type = matches.value_of("type").map(parse_type).transpose()?;
+
+    let mut runtime = Runtime::new().map_err(failure::Error::from)?;
+    let mut collection = runtime.block_on(client.collection(None))?;
+
+    if let Some(ref subject_type) = subject_type {
+        collection.retain(|e| &e.subject.subject_type == subject_type);
+    }
+
+    if matches.is_present("json") {
+        print_json(&json!(collection));
+        return Ok(());
+    }
+
+    for entry in collection.iter() {
+        println!(
+            "{}\t{}\t{}\t{}",
+            entry.subject.id,
+            entry.subject.subject_type.disp(),
+            disp_progress(entry),
+            display_name(&entry.subject),
+        );
+    }
+
+    Ok(())
+}
+
+fn show<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Result<(), Failure> {
+    let id = parse_id(matches)?;
+
+    let mut runtime = Runtime::new().map_err(failure::Error::from)?;
+    let subject = runtime.block_on(client.subject(id))?;
+    let detail = runtime.block_on(client.collection_detail(id))?;
+    let entry = runtime
+        .block_on(client.collection(None))?
+        .into_iter()
+        .find(|e| e.subject.id == id);
+
+    if matches.is_present("json") {
+        print_json(&json!({
+            "subject": subject,
+            "collection": detail,
+            "progress": entry,
+        }));
+        return Ok(());
+    }
+
+    let m = msgs();
+    println!("{}", subject.name.bold());
+    if subject.name_cn != "" {
+        println!("{}", subject.name_cn);
+    }
+    println!("{} {}", subject.subject_type.disp(), subject.id);
+
+    println!();
+    match detail {
+        Some(detail) => {
+            println!("{}{}", m.label_status, detail.status.disp());
+            if detail.rating == 0 {
+                println!("{}{}", m.label_rating, m.unrated);
+            } else {
+                println!("{}{} / 10", m.label_rating, detail.rating);
+            }
+            if let Some(ref entry) = entry {
+                println!("{}{}", m.label_progress, disp_progress(entry));
+            }
+            if detail.tag.len() > 0 {
+                println!("{}{}", m.label_tags, detail.tag.join(", "));
+            }
+            if detail.comment != "" {
+                println!("{}{}", m.label_comment, detail.comment);
+            }
+        }
+        None => println!("{}{}", m.label_status, m.not_collected),
+    }
+
+    if subject.summary != "" {
+        println!();
+        println!("{}", subject.summary);
+    }
+
+    Ok(())
+}
+
+/**
+ * `+N` and `-N` step from the current progress, `=N` or `N` sets it
+ */
+fn parse_progress(spec: &str, entry: &CollectionEntry) -> Option<i64> {
+    let current = progress_of(entry).0 as i64;
+    let (sign, number) = match spec.chars().next()? {
+        c @ '+' | c @ '-' | c @ '=' => (Some(c), &spec[1..]),
+        _ => (None, spec),
+    };
+    let number = number.parse::<i64>().ok()?;
+
+    match sign {
+        Some('+') => Some(number),
+        Some('-') => Some(-number),
+        _ => Some(number - current),
+    }
+}
+
+fn progress<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Result<(), Failure> {
+    let id = parse_id(matches)?;
+    let spec = matches.value_of("progress").unwrap();
+
+    let mut runtime = Runtime::new().map_err(failure::Error::from)?;
+    let entry = runtime
+        .block_on(client.collection(None))?
+        .into_iter()
+        .find(|e| e.subject.id == id)
+        .ok_or_else(|| Failure::not_found(fill(msgs().cli_not_watching, &[&id])))?;
+
+    let dist = parse_progress(spec, &entry)
+        .ok_or_else(|| Failure::usage(fill(msgs().cli_invalid_progress, &[&spec])))?;
+
+    let mut after = entry.clone();
+    let (ep, vol) = match entry.subject.subject_type {
+        SubjectType::Book => {
+            after.vol_status = entry.step_vol(dist);
+            (None, Some(after.vol_status))
+        }
+        _ => {
+            after.ep_status = entry.step_ep(dist);
+            (Some(after.ep_status), None)
+        }
+    };
+
+    if progress_of(&after).0 != progress_of(&entry).0 {
+        runtime.block_on(client.progress(&entry, ep, vol))?;
+    }
+
+    if matches.is_present("json") {
+        print_json(&json!(after));
+        return Ok(());
+    }
+
+    let change = fill(
+        msgs().history_progress,
+        &[
+            &display_name(&entry.subject),
+            &progress_of(&entry).0,
+            &disp_progress(&after),
+        ],
+    );
+    println!("{}", fill(msgs().cli_updated, &[&change]));
+
+    Ok(())
+}
+
+/**
+ * Prints the collection after a change, along with what it was
+ */
+fn print_change<A: BangumiApi>(
+    runtime: &mut Runtime,
+    client: &A,
+    id: u64,
+    before: Option<CollectionDetail>,
+    after: CollectionDetail,
+    json: bool,
+) -> Result<(), Failure> {
+    if json {
+        print_json(&json!(after));
+        return Ok(());
+    }
+
+    let subject = runtime.block_on(client.subject(id))?;
+    let name = display_name(&subject);
+    let m = msgs();
+
+    let line = match before {
+        None => fill(m.history_collect, &[&name, &after.status.disp()]),
+        Some(ref before) if before.status != after.status => fill(
+            m.history_status,
+            &[&name, &before.status.disp(), &after.status.disp()],
+        ),
+        Some(ref before) => fill(m.history_rating, &[&name, &before.rating, &after.rating]),
+    };
+    println!("{}", fill(m.cli_updated, &[&line]));
+
+    Ok(())
+}
+
+fn status<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Result<(), Failure> {
+    let id = parse_id(matches)?;
+    let status = parse_status(matches.value_of("status").unwrap())?;
+
+    let mut runtime = Runtime::new().map_err(failure::Error::from)?;
+    let before = runtime.block_on(client.collection_detail(id))?;
+    // Keeps the rating, tags and comment
+    let after = runtime.block_on(client.update_collection_detail(id, status, before.clone()))?;
+
+    print_change(
+        &mut runtime,
+        client,
+        id,
+        before,
+        after,
+        matches.is_present("json"),
+    )
+}
+
+fn rate<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Result<(), Failure> {
+    let id = parse_id(matches)?;
+    let rating = matches.value_of("rating").unwrap();
+    let rating = match rating.parse::<u8>() {
+        Ok(rating) if rating <= 10 => rating,
+        _ => return Err(Failure::usage(fill(msgs().cli_invalid_rating, &[&rating]))),
+    };
+
+    let mut runtime = Runtime::new().map_err(failure::Error::from)?;
+    let before = runtime
+        .block_on(client.collection_detail(id))?
+        .ok_or_else(|| Failure::not_found(fill(msgs().cli_not_collected, &[&id])))?;
+
+    let mut detail = before.clone();
+    detail.rating = rating;
+    let after = runtime.block_on(client.update_collection_detail(
+        id,
+        detail.status.clone(),
+        Some(detail),
+    ))?;
+
+    print_change(
+        &mut runtime,
+        client,
+        id,
+        Some(before),
+        after,
+        matches.is_present("json"),
+    )
+}
+
+fn search<A: BangumiApi>(client: &A, matches: &ArgMatches) -> Result<(), Failure> {
+    let keywords = matches
+        .values_of("keywords")
+        .unwrap()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut runtime = Runtime::new().map_err(failure::Error::from)?;
+    let result = runtime.block_on(client.search(&keywords, SEARCH_LIMIT, 0))?;
+
+    if matches.is_present("json") {
+        print_json(&json!({
+            "count": result.count,
+            "list": result.list,
+        }));
+    } else {
+        for subject in result.list.iter() {
+            println!(
+                "{}\t{}\t{}\t{}",
+                subject.id,
+                subject.subject_type.disp(),
+                subject.name,
+                subject.name_cn,
+            );
+        }
+    }
+
+    if result.list.len() == 0 {
+        return Err(Failure::not_found(fill(
+            msgs().cli_no_results,
+            &[&keywords],
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::entry;
+    use bgmtv::api::{FakeApi, FakeData};
+
+    fn exit_code(api: &FakeApi, args: &[&str]) -> Option<i32> {
+        let matches = App::new("bgmtty")
+            .subcommands(subcommands())
+            .get_matches_from(args);
+        run(api, &matches)
+    }
+
+    fn watching() -> FakeApi {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        data.subject.insert(1, entry(1).subject());
+        data.collection_detail.insert(
+            1,
+            CollectionDetail {
+                status: CollectionStatus::Doing,
+                rating: 7,
+                comment: String::new(),
+                tag: Vec::new(),
+            },
+        );
+        FakeApi::with_data(data)
+    }
+
+    fn ep_status(api: &FakeApi) -> u64 {
+        api.script(|data| data.collection[0].ep_status)
+    }
+
+    fn detail(api: &FakeApi) -> CollectionDetail {
+        api.script(|data| data.collection_detail[&1].clone())
+    }
+
+    #[test]
+    fn refuses_statuses_bgmtv_can_not_list() {
+        let api = FakeApi::new();
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "list", "--status", "collect"]),
+            Some(EXIT_USAGE)
+        );
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "list", "--status", "nope"]),
+            Some(EXIT_USAGE)
+        );
+        assert_eq!(exit_code(&api, &["bgmtty"]), None);
+        assert!(api.calls().is_empty());
+    }
+
+    #[test]
+    fn tells_its_subcommands_apart() {
+        let app = || {
+            App::new("bgmtty")
+                .subcommand(SubCommand::with_name("import"))
+                .subcommands(subcommands())
+        };
+        assert!(handles(&app().get_matches_from(&["bgmtty", "show", "1"])));
+        assert!(!handles(&app().get_matches_from(&["bgmtty", "import"])));
+        assert!(!handles(&app().get_matches_from(&["bgmtty"])));
+    }
+
+    #[test]
+    fn updates_progress() {
+        let api = watching();
+        assert_eq!(exit_code(&api, &["bgmtty", "progress", "1", "+2"]), Some(0));
+        assert_eq!(ep_status(&api), 5);
+        assert_eq!(exit_code(&api, &["bgmtty", "progress", "1", "-1"]), Some(0));
+        assert_eq!(ep_status(&api), 4);
+        assert_eq!(exit_code(&api, &["bgmtty", "progress", "1", "12"]), Some(0));
+        assert_eq!(ep_status(&api), 12);
+
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "progress", "1", "+x"]),
+            Some(EXIT_USAGE)
+        );
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "progress", "2", "+1"]),
+            Some(EXIT_NOT_FOUND)
+        );
+        assert_eq!(ep_status(&api), 12);
+    }
+
+    #[test]
+    fn fails_when_bgmtv_rejects_progress() {
+        let api = watching();
+        api.fail_next("progress", "Rejected");
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "progress", "1", "+1"]),
+            Some(EXIT_FAILED)
+        );
+        assert_eq!(ep_status(&api), 3);
+    }
+
+    #[test]
+    fn updates_status_keeping_the_rating() {
+        let api = watching();
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "status", "1", "collect", "--json"]),
+            Some(0)
+        );
+        assert_eq!(detail(&api).status, CollectionStatus::Done);
+        assert_eq!(detail(&api).rating, 7);
+
+        api.fail_next("update_collection_detail", "Rejected");
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "status", "1", "dropped"]),
+            Some(EXIT_FAILED)
+        );
+        assert_eq!(detail(&api).status, CollectionStatus::Done);
+    }
+
+    #[test]
+    fn rates_collected_subjects() {
+        let api = watching();
+        assert_eq!(exit_code(&api, &["bgmtty", "rate", "1", "8"]), Some(0));
+        assert_eq!(detail(&api).rating, 8);
+        assert_eq!(detail(&api).status, CollectionStatus::Doing);
+
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "rate", "1", "11"]),
+            Some(EXIT_USAGE)
+        );
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "rate", "2", "8"]),
+            Some(EXIT_NOT_FOUND)
+        );
+
+        api.fail_next("update_collection_detail", "Rejected");
+        assert_eq!(
+            exit_code(&api, &["bgmtty", "rate", "1", "3"]),
+            Some(EXIT_FAILED)
+        );
+        assert_eq!(detail(&api).rating, 8);
+    }
+}
@@ -0,0 +1,72 @@
+use bgmtv::client::{CollectionEntry, SubjectSmall};
+use serde_json::json;
+
+/**
+ * A collected anime with 12 eps, tweaked field by field for each test
+ */
+pub struct EntryBuilder {
+    id: u64,
+    subject_type: u64,
+    air_date: String,
+    eps_count: u64,
+    ep_status: u64,
+    lasttouch: i64,
+}
+
+pub fn entry(id: u64) -> EntryBuilder {
+    EntryBuilder {
+        id,
+        subject_type: 2,
+        air_date: String::new(),
+        eps_count: 12,
+        ep_status: 0,
+        lasttouch: 1546300800 + id as i64,
+    }
+}
+
+impl EntryBuilder {
+    pub fn subject_type(mut self, subject_type: u64) -> EntryBuilder {
+        self.subject_type = subject_type;
+        self
+    }
+
+    pub fn air_date(mut self, air_date: &str) -> EntryBuilder {
+        self.air_date = air_date.to_string();
+        self
+    }
+
+    pub fn eps_count(mut self, eps_count: u64) -> EntryBuilder {
+        self.eps_count = eps_count;
+        self
+    }
+
+    pub fn ep_status(mut self, ep_status: u64) -> EntryBuilder {
+        self.ep_status = ep_status;
+        self
+    }
+
+    pub fn lasttouch(mut self, lasttouch: i64) -> EntryBuilder {
+        self.lasttouch = lasttouch;
+        self
+    }
+
+    pub fn subject(&self) -> SubjectSmall {
+        serde_json::from_value(json!({
+            "id": self.id,
+            "name": format!("Subject {}", self.id),
+            "type": self.subject_type,
+            "air_date": self.air_date,
+            "eps_count": self.eps_count,
+        }))
+        .unwrap()
+    }
+
+    pub fn build(&self) -> CollectionEntry {
+        serde_json::from_value(json!({
+            "ep_status": self.ep_status,
+            "lasttouch": self.lasttouch,
+            "subject": self.subject(),
+        }))
+        .unwrap()
+    }
+}
@@ -0,0 +1,171 @@
+use std::ops::Range;
+
+/**
+ * GB2312 codes where the pinyin of level-1 hanzi, which are sorted by pinyin,
+ * starts with each letter. No syllable starts with i, u or v
+ */
+const INITIALS: [(u16, char); 23] = [
+    (0xB0A1, 'a'),
+    (0xB0C5, 'b'),
+    (0xB2C1, 'c'),
+    (0xB4EE, 'd'),
+    (0xB6EA, 'e'),
+    (0xB7A2, 'f'),
+    (0xB8C1, 'g'),
+    (0xB9FE, 'h'),
+    (0xBBF7, 'j'),
+    (0xBFA6, 'k'),
+    (0xC0AC, 'l'),
+    (0xC2E8, 'm'),
+    (0xC4C3, 'n'),
+    (0xC5B6, 'o'),
+    (0xC5BE, 'p'),
+    (0xC6DA, 'q'),
+    (0xC8BB, 'r'),
+    (0xC8F6, 's'),
+    (0xCBFA, 't'),
+    (0xCDDA, 'w'),
+    (0xCEF4, 'x'),
+    (0xD1B9, 'y'),
+    (0xD4D1, 'z'),
+];
+
+const LEVEL1_END: u16 = 0xD7F9;
+
+/**
+ * The first letter of the pinyin of common hanzi, e.g. `j` for 进
+ */
+pub fn initial(c: char) -> Option<char> {
+    if c.is_ascii() {
+        return None;
+    }
+
+    let mut buf = [0; 4];
+    let (bytes, _, unmappable) = encoding_rs::GBK.encode(c.encode_utf8(&mut buf));
+    if unmappable || bytes.len() != 2 {
+        return None;
+    }
+
+    let code = u16::from(bytes[0]) << 8 | u16::from(bytes[1]);
+    if code < INITIALS[0].0 || code > LEVEL1_END {
+        return None;
+    }
+
+    INITIALS
+        .iter()
+        .rev()
+        .find(|(start, _)| code >= *start)
+        .map(|(_, letter)| *letter)
+}
+
+fn matches(c: char, q: char) -> bool {
+    c.to_lowercase().eq(std::iter::once(q)) || initial(c) == Some(q)
+}
+
+/**
+ * Matches the query as a subsequence of the text, ignoring case and whitespace in the query.
+ * Hanzi also match the initial of their pinyin.
+ *
+ * Returns the byte ranges of the matched parts, which are empty for an empty query
+ */
+pub fn find(text: &str, query: &str) -> Option<Vec<Range<usize>>> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for (pos, c) in text.char_indices() {
+        let q = match query.peek() {
+            Some(q) => *q,
+            None => break,
+        };
+
+        if !matches(c, q) {
+            continue;
+        }
+
+        query.next();
+        let end = pos + c.len_utf8();
+        match ranges.last_mut() {
+            Some(last) if last.end == pos => last.end = end,
+            _ => ranges.push(pos..end),
+        }
+    }
+
+    if query.peek().is_some() {
+        None
+    } else {
+        Some(ranges)
+    }
+}
+
+/**
+ * Splits the text into the matched parts and the rest, in order
+ */
+pub fn split<'a>(text: &'a str, ranges: &[Range<usize>]) -> Vec<(&'a str, bool)> {
+    let mut result = Vec::new();
+    let mut last = 0;
+
+    for range in ranges.iter() {
+        if range.start > last {
+            result.push((&text[last..range.start], false));
+        }
+        result.push((&text[range.clone()], true));
+        last = range.end;
+    }
+
+    if last < text.len() {
+        result.push((&text[last..], false));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_ascii_case() {
+        assert_eq!(find("Shingeki no Kyojin", "SHIN"), Some(vec![0..4]));
+        assert_eq!(
+            find("Shingeki no Kyojin", "snk"),
+            Some(vec![0..1, 3..4, 6..7])
+        );
+        assert_eq!(find("Shingeki", "x"), None);
+    }
+
+    #[test]
+    fn matches_pinyin_initials() {
+        assert_eq!(initial('进'), Some('j'));
+        assert_eq!(initial('人'), Some('r'));
+        assert_eq!(find("进击的巨人", "jjdjr"), Some(vec![0..15]));
+        assert_eq!(find("进击的巨人", "jr"), Some(vec![0..3, 12..15]));
+        assert_eq!(find("进击的巨人", "jjdjrx"), None);
+    }
+
+    #[test]
+    fn skips_characters_outside_level1() {
+        assert_eq!(initial('a'), None);
+        // GB2312 level 2, GBK extension and outside GBK
+        assert_eq!(initial('亍'), None);
+        assert_eq!(initial('龘'), None);
+        assert_eq!(initial('㐀'), None);
+        assert_eq!(initial('é'), None);
+    }
+
+    #[test]
+    fn returns_byte_ranges() {
+        let text = "Re:从零开始";
+        let ranges = find(text, "r c l").unwrap();
+        assert_eq!(ranges, vec![0..1, 3..9]);
+        assert_eq!(
+            split(text, &ranges),
+            vec![("R", true), ("e:", false), ("从零", true), ("开始", false)]
+        );
+        assert_eq!(find(text, ""), Some(vec![]));
+        assert_eq!(split(text, &[]), vec![(text, false)]);
+    }
+}
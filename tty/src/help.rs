@@ -1,131 +1,78 @@
-use crate::state::{Tab, UIState};
+use crate::i18n::{msgs, Messages};
+use crate::keys::{Keymap, ACTIONS};
+use crate::state::UIState;
+use crate::theme::Theme;
 use crate::widgets::CJKText;
-use tui::style::{Color, Modifier, Style};
+use tui::style::Style;
 
-pub struct HelpEntry(
-    &'static [&'static str],
-    &'static str,
-    &'static Fn(&UIState) -> bool,
-);
-
-impl HelpEntry {
-    pub fn pred(&self) -> &'static Fn(&UIState) -> bool {
-        self.2
-    }
+pub struct HelpEntry {
+    keys: Vec<String>,
+    desc: &'static str,
 }
 
-impl<'a> Into<CJKText<'static>> for &'a HelpEntry {
-    fn into(self) -> CJKText<'static> {
+impl HelpEntry {
+    pub fn text(&self, theme: &Theme) -> CJKText<'_> {
         let mut result = Vec::new();
 
-        for i in 0..self.0.len() {
+        for (i, key) in self.keys.iter().enumerate() {
             if i != 0 {
                 result.push((" / ", Style::default()));
             }
-            result.push((
-                self.0[i],
-                Style::default().modifier(Modifier::Bold).fg(Color::Red),
-            ));
+            result.push((key.as_str(), theme.key));
         }
 
         result.push((": ", Style::default()));
-        result.push((self.1, Style::default()));
+        result.push((self.desc, Style::default()));
 
         CJKText::raw(result)
     }
 }
 
-fn is_subject(ui: &UIState) -> bool {
-    ui.active_tab().is_subject()
-}
+/**
+ * Keys, description in the given catalog and when to show it
+ */
+type CommandHelp = (
+    &'static str,
+    fn(&Messages) -> &'static str,
+    &'static dyn Fn(&UIState) -> bool,
+);
 
-fn is_collection(ui: &UIState) -> bool {
-    ui.active_tab().is_collection()
-}
+/**
+ * Long commands, which are not bound to keys
+ */
+const COMMAND_HELP: [CommandHelp; 8] = [
+    ("Esc", |m| m.command_cancel, &|ui| ui.command.present()),
+    (":sort [<key> [asc|desc]]", |m| m.command_sort, &|ui| {
+        ui.active_tab().is_collection()
+    }),
+    (":tabe <coll|search>", |m| m.command_tabe, &|_| true),
+    (":pending", |m| m.command_pending, &|_| true),
+    (":mksession [name]", |m| m.command_mksession, &|_| true),
+    (":session [name]", |m| m.command_session, &|_| true),
+    (":tabm <n>", |m| m.command_tabm, &|_| true),
+    (":q", |m| m.command_q, &|_| true),
+];
 
-fn is_search(ui: &UIState) -> bool {
-    ui.active_tab().is_search()
-}
+/**
+ * Everything usable in the current state, with the keys currently bound
+ */
+pub fn entries(ui: &UIState, keymap: &Keymap) -> Vec<HelpEntry> {
+    let bound = ACTIONS
+        .iter()
+        .filter(|action| action.available(ui))
+        .map(|action| HelpEntry {
+            keys: keymap.describe(*action),
+            desc: action.desc(ui),
+        })
+        .filter(|entry| !entry.keys.is_empty());
 
-fn is_search_result(ui: &UIState) -> bool {
-    ui.active_tab().is_search_result()
-}
+    let commands = COMMAND_HELP
+        .iter()
+        .filter(|(_, _, pred)| pred(ui))
+        .map(|(keys, desc, _)| HelpEntry {
+            keys: vec![keys.to_string()],
+            desc: desc(msgs()),
+        });
 
-pub const HELP_DATABASE: [HelpEntry; 34] = [
-    // General
-    HelpEntry(&["?", "h", ":help"], "康帮助", &|_| true),
-    HelpEntry(&["K"], "向上滚动帮助", &|ui| ui.help),
-    HelpEntry(&["J"], "向下滚动帮助", &|ui| ui.help),
-    HelpEntry(&[":qa", "C-q"], "Rage quit", &|_| true),
-    HelpEntry(&["R"], "刷新", &|ui| !is_search(ui)),
-    // On primary tab
-    HelpEntry(&["k", "Up"], "选择上一个", &|ui| is_collection(ui)),
-    HelpEntry(&["j", "Down"], "选择下一个", &|ui| is_collection(ui)),
-    HelpEntry(&["t<i>"], "切换第 i 个过滤选项", &|ui| {
-        is_collection(ui)
-    }),
-    // When have focus
-    HelpEntry(&["+"], "增加进度", &|ui| {
-        is_collection(ui) && ui.focus.get().is_some()
-    }),
-    HelpEntry(&["-"], "减少进度", &|ui| {
-        is_collection(ui) && ui.focus.get().is_some()
-    }),
-    HelpEntry(&["Enter"], "详情/编辑", &|ui| {
-        is_collection(ui) && ui.focus.get().is_some()
-    }),
-    HelpEntry(&["Esc"], "取消选择", &|ui| {
-        is_collection(ui) && ui.focus.get().is_some() && !ui.command.present()
-    }),
-    // When in subject page
-    HelpEntry(&["k", "Up"], "向上滚动", &is_subject),
-    HelpEntry(&["j", "Down"], "向下滚动", &is_subject),
-    HelpEntry(&["s"], "修改收藏状态", &is_subject),
-    HelpEntry(&["r"], "修改评分", &is_subject),
-    HelpEntry(&["t"], "修改标签", &is_subject),
-    HelpEntry(&["c"], "修改评论", &is_subject),
-    HelpEntry(&["Esc"], "也可以关闭标签", &|ui| {
-        is_subject(ui) && !ui.command.present()
-    }),
-    // When in search page
-    HelpEntry(&["e", "Enter"], "修改搜索文字", &|ui| {
-        if let Tab::Search { text } = ui.active_tab() {
-            text == ""
-        } else {
-            false
-        }
-    }),
-    HelpEntry(&["e"], "修改搜索文字", &|ui| {
-        if let Tab::Search { text } = ui.active_tab() {
-            text != ""
-        } else {
-            false
-        }
-    }),
-    HelpEntry(&["Enter"], "搜索", &|ui| {
-        if let Tab::Search { text } = ui.active_tab() {
-            text != ""
-        } else {
-            false
-        }
-    }),
-    // In search result
-    HelpEntry(&["n"], "下一页", &|ui| is_search_result(ui)),
-    HelpEntry(&["N"], "上一页", &|ui| is_search_result(ui)),
-    HelpEntry(&["k", "Up"], "选择上一个", &|ui| is_search_result(ui)),
-    HelpEntry(&["j", "Down"], "选择下一个", &|ui| {
-        is_search_result(ui)
-    }),
-    // Long command
-    HelpEntry(&["Esc"], "取消命令", &|ui| ui.command.present()),
-    // Tabs
-    HelpEntry(&["gt", "Tab"], "下一个 Tab", &|_| true),
-    HelpEntry(&["gT"], "上一个 Tab", &|_| true),
-    HelpEntry(&["gg"], "滚动至顶", &|ui| !is_search(ui)),
-    HelpEntry(&["G"], "滚动至底", &|ui| !is_search(ui)),
-    HelpEntry(&[":tabe <coll|search>"], "打开格子/搜索 Tab", &|_| {
-        true
-    }),
-    HelpEntry(&[":tabm <n>"], "移动 Tab", &|_| true),
-    HelpEntry(&[":q"], "关闭 Tab", &|_| true),
-];
+    bound.chain(commands).collect()
+}
@@ -0,0 +1,403 @@
+use failure::{format_err, Error};
+use std::fmt::{Display, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod en;
+mod zh_cn;
+
+/**
+ * Every user-facing string of bgmTTY.
+ *
+ * `{}` in a message is replaced by the arguments in order, see `fill`
+ */
+pub struct Messages {
+    // Command line
+    pub arg_init: &'static str,
+    pub arg_refresh: &'static str,
+    pub arg_logout: &'static str,
+    pub arg_auth_only: &'static str,
+    pub arg_config: &'static str,
+    pub arg_editor: &'static str,
+    pub arg_log: &'static str,
+    pub arg_log_level: &'static str,
+    pub arg_lang: &'static str,
+    pub arg_no_session: &'static str,
+    pub arg_dry_run: &'static str,
+    pub cmd_import: &'static str,
+    pub cmd_import_mal: &'static str,
+    pub arg_mal_file: &'static str,
+    pub cmd_sync: &'static str,
+    pub cmd_sync_anilist: &'static str,
+    pub arg_anilist_token: &'static str,
+    pub arg_anilist_endpoint: &'static str,
+    pub arg_anilist_mapping: &'static str,
+    pub arg_anilist_accept_new: &'static str,
+    pub cmd_list: &'static str,
+    pub cmd_show: &'static str,
+    pub cmd_progress: &'static str,
+    pub cmd_status: &'static str,
+    pub cmd_rate: &'static str,
+    pub cmd_search: &'static str,
+    pub arg_json: &'static str,
+    pub arg_status_filter: &'static str,
+    pub arg_type_filter: &'static str,
+    pub arg_subject_id: &'static str,
+    pub arg_progress_spec: &'static str,
+    pub arg_status_value: &'static str,
+    pub arg_rating_value: &'static str,
+    pub arg_keywords: &'static str,
+
+    // Startup
+    pub init_intro: &'static str,
+    pub init_apply: &'static str,
+    pub init_client_id: &'static str,
+    pub init_client_secret: &'static str,
+    pub init_done: &'static str,
+    pub auth_visit: &'static str,
+    pub auth_interactive: &'static str,
+    pub auth_token_failed: &'static str,
+    pub auth_refresh_failed: &'static str,
+    pub auth_other_account: &'static str,
+    pub auth_expired_refreshing: &'static str,
+    pub auth_expired: &'static str,
+    pub auth_unverified: &'static str,
+    pub auth_failed: &'static str,
+    pub config_missing: &'static str,
+    pub config_missing_cause: &'static str,
+    pub config_missing_init: &'static str,
+    pub config_missing_path: &'static str,
+    pub log_invalid: &'static str,
+    pub keys_invalid: &'static str,
+    pub theme_invalid: &'static str,
+    pub lang_invalid: &'static str,
+    pub import_failed: &'static str,
+    pub sync_failed: &'static str,
+    pub cli_updated: &'static str,
+    pub cli_invalid_id: &'static str,
+    pub cli_invalid_progress: &'static str,
+    pub cli_invalid_status: &'static str,
+    pub cli_unlisted_status: &'static str,
+    pub cli_invalid_type: &'static str,
+    pub cli_invalid_rating: &'static str,
+    pub cli_not_watching: &'static str,
+    pub cli_not_collected: &'static str,
+    pub cli_no_results: &'static str,
+
+    // Configuration errors
+    pub unknown_lang: &'static str,
+    pub unknown_log_level: &'static str,
+    pub unknown_key: &'static str,
+    pub empty_key: &'static str,
+    pub unknown_action: &'static str,
+    pub unknown_theme: &'static str,
+    pub unknown_style: &'static str,
+    pub unknown_color: &'static str,
+    pub unknown_modifier: &'static str,
+    pub unknown_sort: &'static str,
+    pub unknown_sort_order: &'static str,
+    pub theme_cycle: &'static str,
+
+    // Collections, subjects and dates
+    pub status_wished: &'static str,
+    pub status_doing: &'static str,
+    pub status_done: &'static str,
+    pub status_on_hold: &'static str,
+    pub status_dropped: &'static str,
+    pub status_unknown: &'static str,
+    pub type_anime: &'static str,
+    pub type_book: &'static str,
+    pub type_real: &'static str,
+    pub type_game: &'static str,
+    pub type_music: &'static str,
+    pub type_unknown: &'static str,
+
+    /**
+     * Monday first
+     */
+    pub weekdays: [&'static str; 7],
+    pub airing: &'static str,
+    pub premiere: &'static str,
+    pub sort_lasttouch: &'static str,
+    pub sort_name: &'static str,
+    pub sort_progress: &'static str,
+    pub sort_remaining: &'static str,
+    pub sort_airday: &'static str,
+    pub sort_type: &'static str,
+
+    // Tabs and pages
+    pub tab_collection: &'static str,
+    pub tab_search: &'static str,
+    pub tab_subject: &'static str,
+    pub tab_character: &'static str,
+    pub tab_person: &'static str,
+    pub tab_search_result: &'static str,
+    pub tab_pending: &'static str,
+    pub title_pending: &'static str,
+    pub section_relations: &'static str,
+    pub section_characters: &'static str,
+    pub section_staff: &'static str,
+    pub section_appearances: &'static str,
+    pub section_voice_actors: &'static str,
+    pub section_works: &'static str,
+    pub section_episodes: &'static str,
+    pub label_status: &'static str,
+    pub label_rating: &'static str,
+    pub label_tags: &'static str,
+    pub label_comment: &'static str,
+    pub label_progress: &'static str,
+    pub label_sort: &'static str,
+    pub unrated: &'static str,
+    pub not_collected: &'static str,
+    pub fetching_id: &'static str,
+    pub loading: &'static str,
+    pub empty: &'static str,
+    pub search_placeholder: &'static str,
+
+    /**
+     * Followed by the emphasized `no_results`, then by `no_results_after`
+     */
+    pub no_results_before: &'static str,
+    pub no_results: &'static str,
+    pub no_results_after: &'static str,
+
+    /**
+     * Following the result count, the range and the visible count respectively
+     */
+    pub results_count: &'static str,
+    pub results_range: &'static str,
+    pub results_visible: &'static str,
+    pub help_hint: &'static str,
+
+    // Prompts and status messages
+    pub prompt_rating: &'static str,
+    pub prompt_status: &'static str,
+    pub prompt_search: &'static str,
+    pub unknown_number: &'static str,
+    pub unknown_command: &'static str,
+    pub editor_missing: &'static str,
+    pub sort_save_failed: &'static str,
+    pub request_failed: &'static str,
+    pub refreshing_collection: &'static str,
+    pub collection_loaded: &'static str,
+    pub updating_progress: &'static str,
+    pub fetching_collection_detail: &'static str,
+    pub updating_collection: &'static str,
+    pub collection_updated: &'static str,
+    pub nothing_to_undo: &'static str,
+    pub nothing_to_redo: &'static str,
+    pub undone: &'static str,
+    pub redone: &'static str,
+    pub undo_uncollect: &'static str,
+    pub history_progress: &'static str,
+    pub history_collect: &'static str,
+    pub history_status: &'static str,
+    pub history_rating: &'static str,
+    pub history_detail: &'static str,
+    pub write_queued: &'static str,
+    pub replay_conflict: &'static str,
+    pub replay_rejected: &'static str,
+    pub pending_discarded: &'static str,
+    pub journal_load_failed: &'static str,
+    pub journal_save_failed: &'static str,
+    pub session_saved: &'static str,
+    pub session_loaded: &'static str,
+    pub session_missing: &'static str,
+    pub session_save_failed: &'static str,
+    pub session_load_failed: &'static str,
+    pub session_name_invalid: &'static str,
+    pub pending_mark: &'static str,
+    pub conflict_mark: &'static str,
+    pub rejected_mark: &'static str,
+    pub no_pending: &'static str,
+    pub fetching_subject: &'static str,
+    pub subject_loaded: &'static str,
+    pub fetching_relations: &'static str,
+    pub relations_loaded: &'static str,
+    pub fetching_characters: &'static str,
+    pub characters_loaded: &'static str,
+    pub fetching_staff: &'static str,
+    pub staff_loaded: &'static str,
+    pub fetching_character: &'static str,
+    pub character_loaded: &'static str,
+    pub fetching_appearances: &'static str,
+    pub appearances_loaded: &'static str,
+    pub fetching_voice_actors: &'static str,
+    pub voice_actors_loaded: &'static str,
+    pub fetching_person: &'static str,
+    pub person_loaded: &'static str,
+    pub fetching_works: &'static str,
+    pub works_loaded: &'static str,
+    pub fetching_episodes: &'static str,
+    pub episodes_loaded: &'static str,
+    pub fetching_episode_progress: &'static str,
+    pub episode_progress_loaded: &'static str,
+    pub updating_episode: &'static str,
+    pub episode_updated: &'static str,
+    pub searching: &'static str,
+    pub search_done: &'static str,
+
+    // Help
+    pub action_quit: &'static str,
+    pub action_command: &'static str,
+    pub action_help_toggle: &'static str,
+    pub action_help_up: &'static str,
+    pub action_help_down: &'static str,
+    pub action_refresh: &'static str,
+    pub action_undo: &'static str,
+    pub action_redo: &'static str,
+    pub action_scroll_up: &'static str,
+    pub action_select_prev: &'static str,
+    pub action_scroll_down: &'static str,
+    pub action_select_next: &'static str,
+    pub action_open_link: &'static str,
+    pub action_details: &'static str,
+    pub action_details_edit: &'static str,
+    pub action_clear: &'static str,
+    pub action_filter_toggle: &'static str,
+    pub action_filter_input: &'static str,
+    pub action_match_next: &'static str,
+    pub action_match_prev: &'static str,
+    pub action_filter_clear: &'static str,
+    pub action_progress_inc: &'static str,
+    pub action_progress_dec: &'static str,
+    pub action_edit_status: &'static str,
+    pub action_edit_rating: &'static str,
+    pub action_edit_tags: &'static str,
+    pub action_edit_comment: &'static str,
+    pub action_episode_left: &'static str,
+    pub action_episode_right: &'static str,
+    pub action_episode_up: &'static str,
+    pub action_episode_down: &'static str,
+    pub action_episode_watched: &'static str,
+    pub action_episode_queued: &'static str,
+    pub action_episode_dropped: &'static str,
+    pub action_episode_remove: &'static str,
+    pub action_link_next: &'static str,
+    pub action_link_prev: &'static str,
+    pub action_search_edit: &'static str,
+    pub action_search_submit: &'static str,
+    pub action_page_next: &'static str,
+    pub action_page_prev: &'static str,
+    pub action_pending_discard: &'static str,
+    pub action_pending_retry: &'static str,
+    pub action_tab_next: &'static str,
+    pub action_tab_prev: &'static str,
+    pub action_tab_close: &'static str,
+    pub action_scroll_top: &'static str,
+    pub action_scroll_bottom: &'static str,
+    pub command_cancel: &'static str,
+    pub command_sort: &'static str,
+    pub command_tabe: &'static str,
+    pub command_pending: &'static str,
+    pub command_mksession: &'static str,
+    pub command_session: &'static str,
+    pub command_tabm: &'static str,
+    pub command_q: &'static str,
+
+    // Import and sync
+    pub xml_error: &'static str,
+    pub rating_unchanged: &'static str,
+    pub import_plan: &'static str,
+    pub import_progress_book: &'static str,
+    pub import_not_found: &'static str,
+    pub import_candidates: &'static str,
+    pub import_choose: &'static str,
+    pub import_read: &'static str,
+    pub import_unknown_status: &'static str,
+    pub import_imported: &'static str,
+    pub import_partial: &'static str,
+    pub import_done: &'static str,
+    pub dry_run: &'static str,
+    pub anilist_empty: &'static str,
+    pub anilist_user: &'static str,
+    pub anilist_not_found: &'static str,
+    pub anilist_new_match: &'static str,
+    pub anilist_unconfirmed: &'static str,
+    pub sync_state: &'static str,
+    pub sync_absent: &'static str,
+    pub sync_up_to_date: &'static str,
+    pub sync_partial: &'static str,
+    pub sync_done: &'static str,
+}
+
+/**
+ * Shipped catalogs, by language tag. The first one is the fallback
+ */
+pub static LOCALES: [(&str, &Messages); 2] = [("zh-CN", &zh_cn::MESSAGES), ("en", &en::MESSAGES)];
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/**
+ * The catalog of the selected locale
+ */
+pub fn msgs() -> &'static Messages {
+    LOCALES[CURRENT.load(Ordering::Relaxed)].1
+}
+
+/**
+ * Matches tags like `en`, `zh_CN.UTF-8` or `en-US` against the shipped catalogs,
+ * first exactly, then by the language alone
+ */
+pub fn find(tag: &str) -> Option<usize> {
+    let tag = tag
+        .split(['.', '@'])
+        .next()
+        .unwrap_or("")
+        .replace('_', "-")
+        .to_lowercase();
+    let language = tag.split('-').next().unwrap_or("");
+
+    if language == "" {
+        return None;
+    }
+
+    let primary = |(name, _): &(&str, &Messages)| name.split('-').next().unwrap().to_lowercase();
+
+    LOCALES
+        .iter()
+        .position(|(name, _)| name.to_lowercase() == tag)
+        .or_else(|| LOCALES.iter().position(|l| primary(l) == language))
+}
+
+/**
+ * Selects the locale given explicitly, or else the one in `LC_ALL`, `LC_MESSAGES` or `LANG`.
+ * Unsupported locales from the environment are ignored
+ */
+pub fn select(lang: Option<&str>) -> Result<(), Error> {
+    let index = match lang {
+        Some(lang) => find(lang).ok_or_else(|| {
+            let names = LOCALES.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            format_err!("{}", fill(msgs().unknown_lang, &[&lang, &names.join(", ")]))
+        })?,
+        None => ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| value != "")
+            .and_then(|value| find(&value))
+            .unwrap_or(0),
+    };
+
+    CURRENT.store(index, Ordering::Relaxed);
+    Ok(())
+}
+
+/**
+ * Replaces each `{}` in the message with the next argument
+ */
+pub fn fill(message: &str, args: &[&dyn Display]) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut args = args.iter();
+    let mut rest = message;
+
+    while let Some(pos) = rest.find("{}") {
+        result.push_str(&rest[..pos]);
+        if let Some(arg) = args.next() {
+            // Writing into a String never fails
+            let _ = write!(result, "{}", arg);
+        }
+        rest = &rest[pos + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
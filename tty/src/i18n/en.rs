@@ -0,0 +1,303 @@
+use super::Messages;
+
+pub static MESSAGES: Messages = Messages {
+    arg_init: "(Re)initializes the OAuth app credentials",
+    arg_refresh: "Forces refreshing the OAuth token",
+    arg_logout: "Logs out and exits",
+    arg_auth_only: "Only authenticates or refreshes the token",
+    arg_config: "Uses a config file other than the default one",
+    arg_editor: "Uses an editor other than Vim",
+    arg_log: "Writes logs into the file",
+    arg_log_level: "Log level",
+    arg_lang: "Language of the interface, taken from LANG by default",
+    arg_no_session: "Neither restore the tabs of the last run, nor save them on quit",
+    arg_dry_run: "Only lists the changes to be made",
+    cmd_import: "Imports collections from other sites",
+    cmd_import_mal: "Imports an XML export of MyAnimeList",
+    arg_mal_file: "The XML file exported from MyAnimeList",
+    cmd_sync: "Syncs collections with other sites both ways",
+    cmd_sync_anilist: "Syncs statuses, ratings and progress with AniList",
+    arg_anilist_token: "OAuth access token of AniList",
+    arg_anilist_endpoint: "Uses another address for the AniList GraphQL API",
+    arg_anilist_mapping: "Uses an ID mapping file other than the default one",
+    arg_anilist_accept_new: "Saves and syncs the titles newly matched on AniList",
+    cmd_list: "Lists the subjects in progress",
+    cmd_show: "Shows a subject and its collection",
+    cmd_progress: "Updates the progress of a subject in progress",
+    cmd_status: "Updates the collection status of a subject",
+    cmd_rate: "Rates a collected subject",
+    cmd_search: "Searches subjects by keywords",
+    arg_json: "Prints JSON instead",
+    arg_status_filter:
+        "Only lists subjects of the status. bgm.tv only lists those in progress, so this must be do",
+    arg_type_filter: "Only lists subjects of the type: anime, book, music, game or real",
+    arg_subject_id: "ID of the subject on bgm.tv",
+    arg_progress_spec: "+N or -N to step, =N or N to set. Volumes for books, episodes otherwise",
+    arg_status_value: "wish, do, collect, on_hold or dropped",
+    arg_rating_value: "Rating from 1 to 10, 0 to clear",
+    arg_keywords: "Keywords to search",
+
+    init_intro:
+        "bgmTTY authenticates with bgm.tv through OAuth, so valid OAuth app credentials are needed.",
+    init_apply: "You can apply for them at https://bgm.tv/dev/app, or use existing ones.",
+    init_client_id: "Client ID: ",
+    init_client_secret: "Client secret: ",
+    init_done: "Done! Now restart bgmTTY without --init to authenticate with OAuth.",
+    auth_visit: "Please visit {} with a browser on this machine to authenticate",
+    auth_interactive:
+        "Not logged in. Please run bgmTTY without a subcommand to authenticate first.",
+    auth_token_failed: "Failed to obtain a token! Please check your Client ID/secret and retry.",
+    auth_refresh_failed:
+        "Failed to refresh the token! Please check your Client ID/secret and retry.",
+    auth_other_account: "The token belongs to another account, re-authenticating.",
+    auth_expired_refreshing: "The token has expired, refreshing...",
+    auth_expired: "The token has expired, re-authenticating.",
+    auth_unverified: "Unable to verify the token, using it anyway.",
+    auth_failed: "Authentication failed! Did the wind blow the cable away?",
+    config_missing: "Looks like this is your first time using bgmTTY, or",
+    config_missing_cause: "bgmTTY is unable to open the config file.\n",
+    config_missing_init: "You can start bgmTTY with --init to create a new config file, or",
+    config_missing_path: "put an existing config file at {}",
+    log_invalid: "Unable to open the log file! {}",
+    keys_invalid: "Invalid key bindings! {}",
+    theme_invalid: "Invalid theme! {}",
+    lang_invalid: "Invalid language! {}",
+    import_failed: "Import failed! {}",
+    sync_failed: "Sync failed! {}",
+    cli_updated: "Updated {}",
+    cli_invalid_id: "Invalid subject ID: {}",
+    cli_invalid_progress: "Invalid progress: {}. Use +N, -N, =N or N",
+    cli_invalid_status: "Unknown status: {}. Use one of {}",
+    cli_unlisted_status: "Only subjects in progress can be listed, not {}",
+    cli_invalid_type: "Unknown type: {}. Use one of {}",
+    cli_invalid_rating: "Invalid rating: {}. Use 0 to 10",
+    cli_not_watching: "Subject {} is not in progress",
+    cli_not_collected: "Subject {} is not collected",
+    cli_no_results: "Nothing found for {}",
+
+    unknown_lang: "Unknown language: {}, supported languages are {}",
+    unknown_log_level: "Unknown log level: {}",
+    unknown_key: "Unknown key: {}",
+    empty_key: "Keys can not be empty",
+    unknown_action: "Unknown action: {}",
+    unknown_theme: "Theme not found: {}, built-in themes are {}",
+    unknown_style: "Unknown style in theme {}: {}",
+    unknown_color: "Unknown color: {}",
+    unknown_modifier: "Unknown font style: {}",
+    unknown_sort: "Unknown sort key: {}, available ones are {}",
+    unknown_sort_order: "Unknown sort order: {}, only asc and desc are supported",
+    theme_cycle: "The base of theme {} goes round in circles",
+
+    status_wished: "Wished",
+    status_doing: "Doing",
+    status_done: "Done!",
+    status_on_hold: "On hold",
+    status_dropped: "Dropped",
+    status_unknown: "Unknown",
+    type_anime: "Anime",
+    type_book: "Book",
+    type_real: "Real",
+    type_game: "Game",
+    type_music: "Music",
+    type_unknown: "Unknown type",
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    airing: "Airing, updated every {}, {} episodes aired",
+    premiere: "Premieres on {} ({})",
+    sort_lasttouch: "Last modified",
+    sort_name: "Name",
+    sort_progress: "Progress",
+    sort_remaining: "Remaining",
+    sort_airday: "Air day",
+    sort_type: "Type",
+
+    tab_collection: "Collection",
+    tab_search: "Search",
+    tab_subject: "Subject: {}",
+    tab_character: "Character: {}",
+    tab_person: "Person: {}",
+    tab_search_result: "Search: {} / {}",
+    tab_pending: "Pending",
+    title_pending: "bgmTTY [{} pending]",
+    section_relations: "Related subjects",
+    section_characters: "Characters",
+    section_staff: "Staff",
+    section_appearances: "Appearances",
+    section_voice_actors: "Voice actors",
+    section_works: "Works",
+    section_episodes: "Episodes",
+    label_status: "Status: ",
+    label_rating: "Rating: ",
+    label_tags: "Tags: ",
+    label_comment: "Comment: ",
+    label_progress: "Progress: ",
+    label_sort: "Sort: ",
+    unrated: "Not rated",
+    not_collected: "Not collected",
+    fetching_id: "Fetching... ID: {}",
+    loading: "Loading...",
+    empty: "Nothing here",
+    search_placeholder: "Press e or Enter to type, then Enter twice to search",
+    no_results_before: "Nothing but ",
+    no_results: "tumbleweeds",
+    no_results_after: " here\n\nOut of range, maybe?",
+    results_count: " results, ",
+    results_range: ", ",
+    results_visible: " visible",
+    help_hint: "Press {} for help",
+
+    prompt_rating: "Rating (1-10, 0=clear): {}",
+    prompt_status: "Status: {} [Tab]",
+    prompt_search: "Search: {}",
+    unknown_number: "{} is not a valid number!",
+    unknown_command: "Unknown command!",
+    editor_missing: "Editor not found! Specify one with -e, or give Vim a try?",
+    sort_save_failed: "Failed to save the sort order! {}",
+    request_failed: "Request failed! ",
+    refreshing_collection: "Refreshing collection...",
+    collection_loaded: "Collection loaded!",
+    updating_progress: "Updating progress: {}...",
+    fetching_collection_detail: "Fetching collection status...",
+    updating_collection: "Updating collection...",
+    collection_updated: "Collection updated!",
+    nothing_to_undo: "Nothing to undo",
+    nothing_to_redo: "Nothing to redo",
+    undone: "Undone: {}",
+    redone: "Redone: {}",
+    undo_uncollect: "bgm.tv can't remove collections, so collecting {} can't be undone",
+    history_progress: "progress of {} {} → {}",
+    history_collect: "collecting {} as {}",
+    history_status: "status of {} {} → {}",
+    history_rating: "rating of {} {} → {}",
+    history_detail: "tags and comment of {}",
+    write_queued: "Saved locally, will sync once online. {} pending",
+    replay_conflict: "Syncing paused: {} conflicts with a change made elsewhere. See :pending",
+    replay_rejected: "bgm.tv rejected {}, syncing the rest. See :pending",
+    pending_discarded: "Discarded pending change: {}",
+    journal_load_failed: "Failed to load pending changes! {}",
+    journal_save_failed: "Failed to save pending changes! {}",
+    session_saved: "Session saved to {}",
+    session_loaded: "Session restored from {}",
+    session_missing: "Session not found: {}",
+    session_save_failed: "Failed to save the session! {}",
+    session_load_failed: "Failed to load the session! {}",
+    session_name_invalid: "Session names may only contain letters, digits, - and _: {}",
+    pending_mark: "pending",
+    conflict_mark: "conflict",
+    rejected_mark: "rejected",
+    no_pending: "No pending changes",
+    fetching_subject: "Fetching subject: {}...",
+    subject_loaded: "Subject loaded!",
+    fetching_relations: "Fetching related subjects: {}...",
+    relations_loaded: "Related subjects loaded!",
+    fetching_characters: "Fetching characters: {}...",
+    characters_loaded: "Characters loaded!",
+    fetching_staff: "Fetching staff: {}...",
+    staff_loaded: "Staff loaded!",
+    fetching_character: "Fetching character: {}...",
+    character_loaded: "Character loaded!",
+    fetching_appearances: "Fetching appearances: {}...",
+    appearances_loaded: "Appearances loaded!",
+    fetching_voice_actors: "Fetching voice actors: {}...",
+    voice_actors_loaded: "Voice actors loaded!",
+    fetching_person: "Fetching person: {}...",
+    person_loaded: "Person loaded!",
+    fetching_works: "Fetching works: {}...",
+    works_loaded: "Works loaded!",
+    fetching_episodes: "Fetching episodes: {}...",
+    episodes_loaded: "Episodes loaded!",
+    fetching_episode_progress: "Fetching watch records: {}...",
+    episode_progress_loaded: "Watch records loaded!",
+    updating_episode: "Updating episode: {}...",
+    episode_updated: "Episode updated!",
+    searching: "Searching: {}...",
+    search_done: "Search done!",
+
+    action_quit: "Rage quit",
+    action_command: "Enter a command",
+    action_help_toggle: "Toggle help",
+    action_help_up: "Scroll help up",
+    action_help_down: "Scroll help down",
+    action_refresh: "Refresh",
+    action_undo: "Undo the last change",
+    action_redo: "Redo the undone change",
+    action_scroll_up: "Scroll up",
+    action_select_prev: "Select previous",
+    action_scroll_down: "Scroll down",
+    action_select_next: "Select next",
+    action_open_link: "Open link",
+    action_details: "Details",
+    action_details_edit: "Details/edit",
+    action_clear: "Clear selection",
+    action_filter_toggle: "Toggle the i-th filter",
+    action_filter_input: "Filter by name, pinyin initials work too",
+    action_match_next: "Next match",
+    action_match_prev: "Previous match",
+    action_filter_clear: "Clear the filter",
+    action_progress_inc: "Increase progress",
+    action_progress_dec: "Decrease progress",
+    action_edit_status: "Edit status",
+    action_edit_rating: "Edit rating",
+    action_edit_tags: "Edit tags",
+    action_edit_comment: "Edit comment",
+    action_episode_left: "Select previous episode",
+    action_episode_right: "Select next episode",
+    action_episode_up: "Select episode in the row above",
+    action_episode_down: "Select episode in the row below",
+    action_episode_watched: "Mark as watched",
+    action_episode_queued: "Mark as queued",
+    action_episode_dropped: "Mark as dropped",
+    action_episode_remove: "Remove mark",
+    action_link_next: "Select next link",
+    action_link_prev: "Select previous link",
+    action_search_edit: "Edit search text",
+    action_search_submit: "Search",
+    action_page_next: "Next page",
+    action_page_prev: "Previous page",
+    action_pending_discard: "Discard the change",
+    action_pending_retry: "Retry now, overwriting on conflicts",
+    action_tab_next: "Next tab",
+    action_tab_prev: "Previous tab",
+    action_tab_close: "Also closes the tab",
+    action_scroll_top: "Scroll to top",
+    action_scroll_bottom: "Scroll to bottom",
+    command_cancel: "Cancel command",
+    command_sort: "Sort the collection, the default order without arguments",
+    command_tabe: "Open collection/search tab",
+    command_pending: "Show pending changes",
+    command_mksession: "Save the open tabs, the one restored on launch without a name",
+    command_session: "Restore saved tabs",
+    command_tabm: "Move tab",
+    command_q: "Close tab",
+
+    xml_error: "Failed to parse XML: {}",
+    rating_unchanged: "unchanged",
+    import_plan: "{} {} -> status: {}, rating: {}, progress: {}",
+    import_progress_book: "{} chapters, {} volumes",
+    import_not_found: "Subject not found:",
+    import_candidates: "Multiple candidates for:",
+    import_choose: "Choose (1-{}, empty to skip): ",
+    import_read: "Read {} entries",
+    import_unknown_status: "Unknown status, skipped:",
+    import_imported: "Imported: {}",
+    import_partial: "{} entries failed to import",
+    import_done: "Import done!",
+    dry_run: "Dry run, nothing was changed.",
+    anilist_empty: "AniList: empty response",
+    anilist_user: "AniList user: {}",
+    anilist_not_found: "Not found on AniList:",
+    anilist_new_match: "Matched on AniList: {} -> {}",
+    anilist_unconfirmed: "Skipped {} new matches. Check them and run again with --accept-new",
+    sync_state: "{} / {} points / {} episodes",
+    sync_absent: "No record on {}",
+    sync_up_to_date: "Both sides are in sync already!",
+    sync_partial: "{} entries failed to sync",
+    sync_done: "Sync done!",
+};
@@ -0,0 +1,291 @@
+use super::Messages;
+
+pub static MESSAGES: Messages = Messages {
+    arg_init: "(重新)初始化 OAuth 应用凭证",
+    arg_refresh: "强制刷新 OAuth Token",
+    arg_logout: "登出账户并立即退出",
+    arg_auth_only: "仅进行认证或刷新 Token",
+    arg_config: "指定默认位置外的配置文件",
+    arg_editor: "指定默认 Vim 以外的编辑器",
+    arg_log: "将日志写入指定文件",
+    arg_log_level: "日志等级",
+    arg_lang: "界面语言，默认取自 LANG",
+    arg_no_session: "不恢复上次打开的 Tab，退出时也不保存",
+    arg_dry_run: "仅列出将要做出的修改",
+    cmd_import: "从其他网站导入收藏",
+    cmd_import_mal: "导入 MyAnimeList 的 XML 导出文件",
+    arg_mal_file: "MyAnimeList 导出的 XML 文件",
+    cmd_sync: "与其他网站双向同步收藏",
+    cmd_sync_anilist: "与 AniList 同步状态、评分与进度",
+    arg_anilist_token: "AniList 的 OAuth Access Token",
+    arg_anilist_endpoint: "指定 AniList GraphQL API 的地址",
+    arg_anilist_mapping: "指定默认位置外的 ID 对应表",
+    arg_anilist_accept_new: "保存并同步在 AniList 上新匹配到的条目",
+    cmd_list: "列出在看的条目",
+    cmd_show: "查看条目及其收藏",
+    cmd_progress: "更新在看条目的进度",
+    cmd_status: "更新条目的收藏状态",
+    cmd_rate: "为收藏的条目评分",
+    cmd_search: "按关键词搜索条目",
+    arg_json: "以 JSON 格式输出",
+    arg_status_filter: "只列出该状态的条目。bgm.tv 只提供在看的列表，所以只能是 do",
+    arg_type_filter: "只列出该类型的条目: anime, book, music, game 或 real",
+    arg_subject_id: "条目在 bgm.tv 上的 ID",
+    arg_progress_spec: "+N 或 -N 前进后退，=N 或 N 直接设置。书籍为卷数，其余为集数",
+    arg_status_value: "wish, do, collect, on_hold 或 dropped",
+    arg_rating_value: "1 到 10 分，0 为取消评分",
+    arg_keywords: "搜索的关键词",
+
+    init_intro: "bgmTTY 通过 OAuth 协议向 bgm.tv 申请验证，所以我们需要有效的 OAuth 应用凭证。",
+    init_apply: "您可以前往 https://bgm.tv/dev/app 进行申请, 或者使用既有的凭证。",
+    init_client_id: "请输入您的 Client ID: ",
+    init_client_secret: "请输入您的 Client secret: ",
+    init_done: "完成了！现在您可以去掉 --init 参数重新启动 bgmTTY，进行 OAuth 认证。",
+    auth_visit: "请在本机使用浏览器前往 {} 完成验证",
+    auth_interactive: "尚未登录，请先不带子命令运行 bgmTTY 完成验证。",
+    auth_token_failed: "获取 Token 失败！请检查您的 Client ID/secret 并重试。",
+    auth_refresh_failed: "刷新 Token 失败！请检查您的 Client ID/secret 并重试。",
+    auth_other_account: "Token 属于另一个账户，需要重新认证。",
+    auth_expired_refreshing: "Token 已失效，尝试刷新...",
+    auth_expired: "Token 已失效，需要重新认证。",
+    auth_unverified: "无法验证 Token，将直接使用。",
+    auth_failed: "验证失败！可能是风把网线刮断了？",
+    config_missing: "看上去这是您第一次使用 bgmTTY，或者",
+    config_missing_cause: "bgmTTY 没法打开配置文件。\n",
+    config_missing_init: "您可以带参数 --init 启动 bgmTTY 来创建一个新的配置文件，或者",
+    config_missing_path: "将已有的配置文件放到 {}",
+    log_invalid: "无法打开日志文件！{}",
+    keys_invalid: "按键配置有误！{}",
+    theme_invalid: "主题配置有误！{}",
+    lang_invalid: "语言设置有误！{}",
+    import_failed: "导入失败！{}",
+    sync_failed: "同步失败！{}",
+    cli_updated: "已更新 {}",
+    cli_invalid_id: "无效的条目 ID: {}",
+    cli_invalid_progress: "无效的进度: {}，请使用 +N、-N、=N 或 N",
+    cli_invalid_status: "未知的状态: {}，可选 {}",
+    cli_unlisted_status: "只能列出在看的条目，无法列出 {}",
+    cli_invalid_type: "未知的类型: {}，可选 {}",
+    cli_invalid_rating: "无效的评分: {}，请使用 0 到 10",
+    cli_not_watching: "条目 {} 不在在看列表中",
+    cli_not_collected: "条目 {} 还没有收藏",
+    cli_no_results: "没有找到 {} 相关的条目",
+
+    unknown_lang: "不认识的语言: {}，支持的语言有 {}",
+    unknown_log_level: "不认识的日志等级: {}",
+    unknown_key: "不认识的按键: {}",
+    empty_key: "按键不能为空",
+    unknown_action: "不认识的操作: {}",
+    unknown_theme: "找不到主题: {}，内置的主题有 {}",
+    unknown_style: "主题 {} 里有不认识的样式: {}",
+    unknown_color: "不认识的颜色: {}",
+    unknown_modifier: "不认识的字体样式: {}",
+    unknown_sort: "不认识的排序方式: {}，可用的有 {}",
+    unknown_sort_order: "不认识的排序方向: {}，只有 asc 和 desc",
+    theme_cycle: "主题 {} 的 base 绕了个圈",
+
+    status_wished: "打算做",
+    status_doing: "在做了",
+    status_done: "完成！",
+    status_on_hold: "摸了",
+    status_dropped: "没得了",
+    status_unknown: "不知道",
+    type_anime: "动画骗",
+    type_book: "书籍",
+    type_real: "三次元",
+    type_game: "游戏",
+    type_music: "音乐",
+    type_unknown: "未知类型",
+    weekdays: ["周一", "周二", "周三", "周四", "周五", "周六", "周日"],
+    airing: "放送中，每{}更新，已放送 {} 话",
+    premiere: "{} ({}) 开播",
+    sort_lasttouch: "最近修改",
+    sort_name: "名称",
+    sort_progress: "进度",
+    sort_remaining: "剩余",
+    sort_airday: "放送日",
+    sort_type: "类型",
+
+    tab_collection: "格子",
+    tab_search: "搜索",
+    tab_subject: "条目: {}",
+    tab_character: "角色: {}",
+    tab_person: "人物: {}",
+    tab_search_result: "搜索: {} / {}",
+    tab_pending: "待同步",
+    title_pending: "bgmTTY [{} 条待同步]",
+    section_relations: "关联条目",
+    section_characters: "角色",
+    section_staff: "制作人员",
+    section_appearances: "出演",
+    section_voice_actors: "声优",
+    section_works: "参与作品",
+    section_episodes: "章节",
+    label_status: "状态: ",
+    label_rating: "评分: ",
+    label_tags: "标签: ",
+    label_comment: "评论: ",
+    label_progress: "进度: ",
+    label_sort: "排序: ",
+    unrated: "未评分",
+    not_collected: "没打算",
+    fetching_id: "猫咪检索中... ID: {}",
+    loading: "加载中...",
+    empty: "没有内容",
+    search_placeholder: "按 e 或 Enter 开始输入，然后双击 Enter 搜索",
+    no_results_before: "这里是",
+    no_results: "没有猫咪",
+    no_results_after: "的荒原\n\n是不是越界了?",
+    results_count: " 结果，",
+    results_range: "，",
+    results_visible: " 可见",
+    help_hint: "按 {} 可以打开帮助哦",
+
+    prompt_rating: "评分 (1-10, 0=取消): {}",
+    prompt_status: "状态: {} [Tab]",
+    prompt_search: "搜索: {}",
+    unknown_number: "{} 是不认识的数字!",
+    unknown_command: "是不认识的命令!",
+    editor_missing: "找不到编辑器啦！参数 -e 指定编辑器，或者试试 Vim 嘛？",
+    sort_save_failed: "排序方式保存失败！{}",
+    request_failed: "请求失败！",
+    refreshing_collection: "刷新收藏中...",
+    collection_loaded: "收藏加载完成！",
+    updating_progress: "更新进度: {}...",
+    fetching_collection_detail: "获取收藏状态...",
+    updating_collection: "更新更新...",
+    collection_updated: "收藏更新完成！",
+    nothing_to_undo: "没有可以撤销的修改",
+    nothing_to_redo: "没有可以重做的修改",
+    undone: "已撤销: {}",
+    redone: "已重做: {}",
+    undo_uncollect: "bgm.tv 不能取消收藏，撤销不了 {} 的收藏",
+    history_progress: "{} 的进度 {} → {}",
+    history_collect: "收藏 {} 为{}",
+    history_status: "{} 的状态 {} → {}",
+    history_rating: "{} 的评分 {} → {}",
+    history_detail: "{} 的标签和评论",
+    write_queued: "修改先存在本地啦，联网后会自动同步，共 {} 条待同步",
+    replay_conflict: "同步暂停了: {} 和别处的修改冲突，用 :pending 看看吧",
+    replay_rejected: "bgm.tv 拒绝了 {}，其余的继续同步，用 :pending 看看吧",
+    pending_discarded: "丢弃了待同步的修改: {}",
+    journal_load_failed: "待同步的修改读取失败！{}",
+    journal_save_failed: "待同步的修改保存失败！{}",
+    session_saved: "会话保存到了 {}",
+    session_loaded: "从 {} 恢复了会话",
+    session_missing: "找不到会话 {}",
+    session_save_failed: "会话保存失败！{}",
+    session_load_failed: "会话读取失败！{}",
+    session_name_invalid: "会话名只能有字母、数字、- 和 _: {}",
+    pending_mark: "待同步",
+    conflict_mark: "冲突",
+    rejected_mark: "被拒绝",
+    no_pending: "没有待同步的修改",
+    fetching_subject: "获取条目中: {}...",
+    subject_loaded: "条目加载完成！",
+    fetching_relations: "获取关联条目中: {}...",
+    relations_loaded: "关联条目加载完成！",
+    fetching_characters: "获取角色中: {}...",
+    characters_loaded: "角色加载完成！",
+    fetching_staff: "获取制作人员中: {}...",
+    staff_loaded: "制作人员加载完成！",
+    fetching_character: "获取角色中: {}...",
+    character_loaded: "角色加载完成！",
+    fetching_appearances: "获取出演条目中: {}...",
+    appearances_loaded: "出演条目加载完成！",
+    fetching_voice_actors: "获取声优中: {}...",
+    voice_actors_loaded: "声优加载完成！",
+    fetching_person: "获取人物中: {}...",
+    person_loaded: "人物加载完成！",
+    fetching_works: "获取参与作品中: {}...",
+    works_loaded: "参与作品加载完成！",
+    fetching_episodes: "获取章节中: {}...",
+    episodes_loaded: "章节加载完成！",
+    fetching_episode_progress: "获取观看记录中: {}...",
+    episode_progress_loaded: "观看记录加载完成！",
+    updating_episode: "更新章节: {}...",
+    episode_updated: "章节更新完成！",
+    searching: "搜索中: {}...",
+    search_done: "搜索完成！",
+
+    action_quit: "Rage quit",
+    action_command: "输入命令",
+    action_help_toggle: "康帮助",
+    action_help_up: "向上滚动帮助",
+    action_help_down: "向下滚动帮助",
+    action_refresh: "刷新",
+    action_undo: "撤销上一次修改",
+    action_redo: "重做撤销的修改",
+    action_scroll_up: "向上滚动",
+    action_select_prev: "选择上一个",
+    action_scroll_down: "向下滚动",
+    action_select_next: "选择下一个",
+    action_open_link: "打开链接",
+    action_details: "详情",
+    action_details_edit: "详情/编辑",
+    action_clear: "取消选择",
+    action_filter_toggle: "切换第 i 个过滤选项",
+    action_filter_input: "按名称筛选，支持拼音首字母",
+    action_match_next: "下一个匹配",
+    action_match_prev: "上一个匹配",
+    action_filter_clear: "清除筛选",
+    action_progress_inc: "增加进度",
+    action_progress_dec: "减少进度",
+    action_edit_status: "修改收藏状态",
+    action_edit_rating: "修改评分",
+    action_edit_tags: "修改标签",
+    action_edit_comment: "修改评论",
+    action_episode_left: "选择前一话",
+    action_episode_right: "选择后一话",
+    action_episode_up: "选择上一行的章节",
+    action_episode_down: "选择下一行的章节",
+    action_episode_watched: "标记为看过",
+    action_episode_queued: "标记为想看",
+    action_episode_dropped: "标记为抛弃",
+    action_episode_remove: "撤销标记",
+    action_link_next: "选择下一个链接",
+    action_link_prev: "选择上一个链接",
+    action_search_edit: "修改搜索文字",
+    action_search_submit: "搜索",
+    action_page_next: "下一页",
+    action_page_prev: "上一页",
+    action_pending_discard: "丢弃这条修改",
+    action_pending_retry: "马上重试，冲突时强制覆盖",
+    action_tab_next: "下一个 Tab",
+    action_tab_prev: "上一个 Tab",
+    action_tab_close: "也可以关闭标签",
+    action_scroll_top: "滚动至顶",
+    action_scroll_bottom: "滚动至底",
+    command_cancel: "取消命令",
+    command_sort: "排序格子，不带参数时恢复默认顺序",
+    command_tabe: "打开格子/搜索 Tab",
+    command_pending: "查看待同步的修改",
+    command_mksession: "保存打开的 Tab，不带名字时是启动时恢复的那个",
+    command_session: "恢复保存的 Tab",
+    command_tabm: "移动 Tab",
+    command_q: "关闭 Tab",
+
+    xml_error: "XML 解析失败: {}",
+    rating_unchanged: "不变",
+    import_plan: "{} {} -> 状态: {}, 评分: {}, 进度: {}",
+    import_progress_book: "{} 话, {} 卷",
+    import_not_found: "找不到条目:",
+    import_candidates: "有多个候选条目:",
+    import_choose: "请选择 (1-{}，留空跳过): ",
+    import_read: "读取到 {} 个条目",
+    import_unknown_status: "无法识别的状态，跳过:",
+    import_imported: "已导入: {}",
+    import_partial: "{} 个条目导入失败",
+    import_done: "导入完成！",
+    dry_run: "Dry run，没有做出任何修改。",
+    anilist_empty: "AniList: 空响应",
+    anilist_user: "AniList 用户: {}",
+    anilist_not_found: "AniList 上找不到:",
+    anilist_new_match: "AniList 上匹配到: {} -> {}",
+    anilist_unconfirmed: "跳过了 {} 个新匹配，确认无误后加上 --accept-new 再运行一次吧",
+    sync_state: "{} / {} 分 / {} 话",
+    sync_absent: "{} 上没有记录",
+    sync_up_to_date: "两边已经同步了！",
+    sync_partial: "{} 个条目同步失败",
+    sync_done: "同步完成！",
+};
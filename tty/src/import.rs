@@ -0,0 +1,460 @@
+use crate::i18n::{fill, msgs};
+use crate::CollectionStatusExt;
+use bgmtv::client::{
+    Client, CollectionDetail, CollectionEntry, CollectionStatus, SubjectSmall, SubjectType,
+};
+use colored::*;
+use failure::{format_err, Error};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const SEARCH_CANDIDATES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MALKind {
+    Anime,
+    Manga,
+}
+
+impl Default for MALKind {
+    fn default() -> Self {
+        MALKind::Anime
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MALEntry {
+    pub kind: MALKind,
+    pub title: String,
+    /**
+     * Chapters for manga
+     */
+    pub watched_eps: u64,
+    pub read_vols: u64,
+    pub score: u8,
+    pub status: String,
+}
+
+impl MALEntry {
+    /**
+     * Manga are books on bgm.tv
+     */
+    pub fn subject_type(&self) -> SubjectType {
+        match self.kind {
+            MALKind::Anime => SubjectType::Anime,
+            MALKind::Manga => SubjectType::Book,
+        }
+    }
+
+    /**
+     * MAL exports use either the textual status ("Plan to Watch")
+     * or its numeric form (6) depending on the exporter
+     */
+    pub fn collection_status(&self) -> Option<CollectionStatus> {
+        match self.status.trim() {
+            "Watching" | "Reading" | "1" => Some(CollectionStatus::Doing),
+            "Completed" | "2" => Some(CollectionStatus::Done),
+            "On-Hold" | "3" => Some(CollectionStatus::OnHold),
+            "Dropped" | "4" => Some(CollectionStatus::Dropped),
+            "Plan to Watch" | "Plan to Read" | "6" => Some(CollectionStatus::Wished),
+            _ => None,
+        }
+    }
+}
+
+fn xml_error(e: quick_xml::Error) -> Error {
+    format_err!("{}", fill(msgs().xml_error, &[&format!("{:?}", e)]))
+}
+
+pub fn parse_mal<P: AsRef<Path>>(file: P) -> Result<Vec<MALEntry>, Error> {
+    let reader = Reader::from_file(file).map_err(xml_error)?;
+    parse_mal_from(reader)
+}
+
+fn parse_mal_from<R: BufRead>(mut reader: Reader<R>) -> Result<Vec<MALEntry>, Error> {
+    reader.trim_text(true);
+
+    let mut result = Vec::new();
+    let mut current: Option<MALEntry> = None;
+    let mut field: Option<String> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event(&mut buf).map_err(xml_error)? {
+            Event::Start(ref e) => {
+                let name = String::from_utf8_lossy(e.name()).to_string();
+                if name == "anime" {
+                    current = Some(MALEntry::default());
+                } else if name == "manga" {
+                    current = Some(MALEntry {
+                        kind: MALKind::Manga,
+                        ..MALEntry::default()
+                    });
+                } else {
+                    field = Some(name);
+                }
+            }
+            Event::End(ref e) => {
+                if e.name() == b"anime" || e.name() == b"manga" {
+                    if let Some(entry) = current.take() {
+                        result.push(entry);
+                    }
+                }
+                field = None;
+            }
+            Event::Text(ref e) => {
+                let text = e.unescape_and_decode(&reader).map_err(xml_error)?;
+                apply_field(&mut current, &field, text);
+            }
+            Event::CData(ref e) => {
+                let text = String::from_utf8_lossy(e.escaped()).to_string();
+                apply_field(&mut current, &field, text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+
+        buf.clear();
+    }
+
+    Ok(result)
+}
+
+fn apply_field(current: &mut Option<MALEntry>, field: &Option<String>, text: String) {
+    let (entry, field) = match (current, field) {
+        (Some(entry), Some(field)) => (entry, field),
+        _ => return,
+    };
+
+    match field.as_str() {
+        "series_title" | "manga_title" => entry.title = text,
+        "my_watched_episodes" | "my_read_chapters" => {
+            entry.watched_eps = text.trim().parse().unwrap_or(0)
+        }
+        "my_read_volumes" => entry.read_vols = text.trim().parse().unwrap_or(0),
+        "my_score" => entry.score = text.trim().parse().unwrap_or(0),
+        "my_status" => entry.status = text,
+        _ => {}
+    }
+}
+
+pub struct ImportPlan {
+    pub subject: SubjectSmall,
+    pub status: CollectionStatus,
+    pub rating: u8,
+    pub eps: u64,
+    pub vols: u64,
+}
+
+impl ImportPlan {
+    /**
+     * Progress is capped at the subject's length, unless bgm.tv doesn't know it
+     * and reports 0
+     */
+    fn new(entry: &MALEntry, subject: SubjectSmall, status: CollectionStatus) -> ImportPlan {
+        let eps = match subject.eps_count {
+            Some(total) if total > 0 && total < entry.watched_eps => total,
+            _ => entry.watched_eps,
+        };
+        let vols = match subject.vols_count {
+            Some(total) if total > 0 && total < entry.read_vols => total,
+            _ => entry.read_vols,
+        };
+
+        ImportPlan {
+            subject,
+            status,
+            rating: entry.score,
+            eps,
+            vols,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let rating = if self.rating == 0 {
+            msgs().rating_unchanged.to_string()
+        } else {
+            format!("{} / 10", self.rating)
+        };
+        let progress = if self.subject.subject_type == SubjectType::Book {
+            fill(msgs().import_progress_book, &[&self.eps, &self.vols])
+        } else {
+            self.eps.to_string()
+        };
+
+        fill(
+            msgs().import_plan,
+            &[
+                &self.subject.id,
+                &self.subject.name,
+                &self.status.disp(),
+                &rating,
+                &progress,
+            ],
+        )
+    }
+}
+
+/**
+ * Exact matches on the original name are taken directly.
+ * Everything else is left to the user
+ */
+fn resolve(entry: &MALEntry, candidates: Vec<SubjectSmall>) -> Result<Option<SubjectSmall>, Error> {
+    let target = entry.title.trim().to_lowercase();
+    let mut exact = candidates
+        .iter()
+        .filter(|s| s.name.trim().to_lowercase() == target)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if exact.len() == 1 {
+        return Ok(exact.pop());
+    }
+
+    let choices = if exact.len() > 1 { exact } else { candidates };
+
+    if choices.len() == 0 {
+        println!("{} {}", msgs().import_not_found.yellow(), entry.title);
+        return Ok(None);
+    }
+
+    choose(&entry.title, choices)
+}
+
+fn choose(title: &str, mut choices: Vec<SubjectSmall>) -> Result<Option<SubjectSmall>, Error> {
+    println!("{} {}", msgs().import_candidates.yellow(), title);
+    for (i, subject) in choices.iter().enumerate() {
+        println!(
+            "  {}. [{}] {} / {}",
+            i + 1,
+            subject.id,
+            subject.name,
+            subject.name_cn
+        );
+    }
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("{}", fill(msgs().import_choose, &[&choices.len()]));
+        std::io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(None),
+        };
+
+        let line = line.trim();
+        if line == "" {
+            return Ok(None);
+        }
+
+        match line.parse::<usize>() {
+            Ok(i) if i >= 1 && i <= choices.len() => return Ok(Some(choices.swap_remove(i - 1))),
+            _ => println!("{}", fill(msgs().unknown_number, &[&line])),
+        }
+    }
+}
+
+pub fn import_mal<P: AsRef<Path>>(client: &Client, file: P, dry_run: bool) -> Result<(), Error> {
+    let entries = parse_mal(file)?;
+    println!("{}", fill(msgs().import_read, &[&entries.len()]));
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let mut plans = Vec::with_capacity(entries.len());
+
+    for entry in entries.iter() {
+        let status = match entry.collection_status() {
+            Some(status) => status,
+            None => {
+                println!(
+                    "{} {} ({})",
+                    msgs().import_unknown_status.yellow(),
+                    entry.title,
+                    entry.status
+                );
+                continue;
+            }
+        };
+
+        let result = runtime.block_on(client.search(&entry.title, SEARCH_CANDIDATES, 0))?;
+        let subject_type = entry.subject_type();
+        let candidates = result
+            .list
+            .into_iter()
+            .filter(|s| s.subject_type == subject_type)
+            .collect();
+
+        if let Some(subject) = resolve(entry, candidates)? {
+            plans.push(ImportPlan::new(entry, subject, status));
+        }
+    }
+
+    println!();
+    for plan in plans.iter() {
+        println!("{}", plan.describe());
+    }
+
+    if dry_run {
+        println!("{}", msgs().dry_run.green());
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for plan in plans.into_iter() {
+        if let Err(e) = apply(&mut runtime, client, plan) {
+            println!("{}{}", msgs().request_failed.red(), e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        Err(format_err!("{}", fill(msgs().import_partial, &[&failed])))
+    } else {
+        println!("{}", msgs().import_done.green());
+        Ok(())
+    }
+}
+
+fn apply(
+    runtime: &mut tokio::runtime::Runtime,
+    client: &Client,
+    plan: ImportPlan,
+) -> Result<(), Error> {
+    let id = plan.subject.id;
+
+    // Keep existing comments and tags
+    let existing = runtime.block_on(client.collection_detail(id))?;
+    let mut detail = existing.unwrap_or_else(|| CollectionDetail {
+        status: plan.status.clone(),
+        rating: 0,
+        comment: String::new(),
+        tag: Vec::new(),
+    });
+    detail.status = plan.status.clone();
+    if plan.rating != 0 {
+        detail.rating = plan.rating;
+    }
+
+    runtime.block_on(client.update_collection_detail(id, plan.status, Some(detail)))?;
+
+    if plan.eps > 0 || plan.vols > 0 {
+        let coll = CollectionEntry {
+            ep_status: 0,
+            vol_status: 0,
+            lasttouch: chrono::Utc::now(),
+            subject: plan.subject,
+        };
+        runtime.block_on(client.progress(&coll, Some(plan.eps), Some(plan.vols)))?;
+    }
+
+    println!("{}", fill(msgs().import_imported, &[&id]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::entry;
+
+    fn parse(xml: &str) -> Vec<MALEntry> {
+        parse_mal_from(Reader::from_str(xml)).unwrap()
+    }
+
+    #[test]
+    fn parses_anime_export() {
+        let entries = parse(
+            r#"<?xml version="1.0" encoding="UTF-8" ?>
+            <myanimelist>
+                <myinfo><user_export_type>1</user_export_type></myinfo>
+                <anime>
+                    <series_animedb_id>16498</series_animedb_id>
+                    <series_title><![CDATA[Shingeki no Kyojin]]></series_title>
+                    <my_watched_episodes>25</my_watched_episodes>
+                    <my_score>9</my_score>
+                    <my_status>Completed</my_status>
+                </anime>
+                <anime>
+                    <series_title>Steins;Gate &amp; more</series_title>
+                    <my_watched_episodes>3</my_watched_episodes>
+                    <my_score>0</my_score>
+                    <my_status>1</my_status>
+                </anime>
+            </myanimelist>"#,
+        );
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, MALKind::Anime);
+        assert_eq!(entries[0].subject_type(), SubjectType::Anime);
+        assert_eq!(entries[0].title, "Shingeki no Kyojin");
+        assert_eq!(entries[0].watched_eps, 25);
+        assert_eq!(entries[0].read_vols, 0);
+        assert_eq!(entries[0].score, 9);
+        assert_eq!(entries[0].collection_status(), Some(CollectionStatus::Done));
+        assert_eq!(entries[1].title, "Steins;Gate & more");
+        assert_eq!(
+            entries[1].collection_status(),
+            Some(CollectionStatus::Doing)
+        );
+    }
+
+    #[test]
+    fn parses_manga_export() {
+        let entries = parse(
+            r#"<myanimelist>
+                <myinfo><user_export_type>2</user_export_type></myinfo>
+                <manga>
+                    <manga_mangadb_id>23390</manga_mangadb_id>
+                    <manga_title><![CDATA[Shingeki no Kyojin]]></manga_title>
+                    <my_read_volumes>12</my_read_volumes>
+                    <my_read_chapters>50</my_read_chapters>
+                    <my_score>8</my_score>
+                    <my_status>Plan to Read</my_status>
+                </manga>
+            </myanimelist>"#,
+        );
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, MALKind::Manga);
+        assert_eq!(entries[0].subject_type(), SubjectType::Book);
+        assert_eq!(entries[0].title, "Shingeki no Kyojin");
+        assert_eq!(entries[0].watched_eps, 50);
+        assert_eq!(entries[0].read_vols, 12);
+        assert_eq!(entries[0].score, 8);
+        assert_eq!(
+            entries[0].collection_status(),
+            Some(CollectionStatus::Wished)
+        );
+    }
+
+    #[test]
+    fn ignores_fields_outside_entries() {
+        let entries = parse(
+            r#"<myanimelist>
+                <myinfo><user_name>someone</user_name><my_score>5</my_score></myinfo>
+            </myanimelist>"#,
+        );
+
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn caps_progress_at_known_lengths() {
+        let watched = MALEntry {
+            title: "Subject 1".to_string(),
+            watched_eps: 25,
+            ..MALEntry::default()
+        };
+
+        let plan = ImportPlan::new(&watched, entry(1).subject(), CollectionStatus::Done);
+        assert_eq!(plan.eps, 12);
+
+        // bgm.tv reports 0 eps when it doesn't know
+        let unknown = entry(1).eps_count(0).subject();
+        let plan = ImportPlan::new(&watched, unknown, CollectionStatus::Done);
+        assert_eq!(plan.eps, 25);
+    }
+}
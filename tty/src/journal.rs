@@ -0,0 +1,146 @@
+use crate::i18n::{fill, msgs};
+use crate::yaml;
+use crate::CollectionStatusExt;
+use bgmtv::client::{CollectionDetail, CollectionEntry, CollectionStatus, SubjectType};
+use chrono::{DateTime, Utc};
+use failure::Error;
+use serde_derive::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/**
+ * A change to the collection, holding what it was before and after
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Mutation {
+    Progress(CollectionEntry, CollectionEntry),
+    Detail {
+        id: u64,
+        name: String,
+        before: Option<CollectionDetail>,
+        after: (CollectionStatus, Option<CollectionDetail>),
+    },
+}
+
+impl Mutation {
+    pub fn subject(&self) -> u64 {
+        match self {
+            Mutation::Progress(_, after) => after.subject.id,
+            Mutation::Detail { id, .. } => *id,
+        }
+    }
+
+    pub fn disp(&self) -> String {
+        let m = msgs();
+        match self {
+            Mutation::Progress(before, after) => {
+                let (from, to) = match after.subject.subject_type {
+                    SubjectType::Book => (before.vol_status, after.vol_status),
+                    _ => (before.ep_status, after.ep_status),
+                };
+                fill(m.history_progress, &[&after.subject.name, &from, &to])
+            }
+            Mutation::Detail {
+                name,
+                before: None,
+                after: (status, _),
+                ..
+            } => fill(m.history_collect, &[name, &status.disp()]),
+            Mutation::Detail {
+                name,
+                before: Some(before),
+                after: (status, after),
+                ..
+            } => {
+                if &before.status != status {
+                    fill(
+                        m.history_status,
+                        &[name, &before.status.disp(), &status.disp()],
+                    )
+                } else {
+                    match after {
+                        Some(after) if after.rating != before.rating => {
+                            fill(m.history_rating, &[name, &before.rating, &after.rating])
+                        }
+                        _ => fill(m.history_detail, &[name]),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+ * A change which couldn't be sent to bgm.tv yet
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingWrite {
+    pub mutation: Mutation,
+    pub time: DateTime<Utc>,
+
+    /**
+     * When the subject was last touched in the collection as the change was made.
+     * None if it wasn't collected, or the change follows another replayed one
+     */
+    pub lasttouch: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub conflict: bool,
+
+    /**
+     * Why bgm.tv refused the change when it was replayed. Skipped by the replay until retried
+     */
+    #[serde(default)]
+    pub rejected: Option<String>,
+}
+
+/**
+ * Changes made while bgm.tv was unreachable, persisted between runs and
+ * replayed in order once it's back
+ */
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    pub entries: Vec<PendingWrite>,
+}
+
+impl Journal {
+    pub fn load_from<P: AsRef<Path>>(file: P) -> Result<Journal, Error> {
+        Ok(yaml::load(file)?.unwrap_or_default())
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, file: P) -> Result<(), Error> {
+        yaml::save(file, self)
+    }
+
+    pub fn is_pending(&self, subject: u64) -> bool {
+        self.entries.iter().any(|e| e.mutation.subject() == subject)
+    }
+
+    /**
+     * Index of the entry to replay next, skipping the ones bgm.tv rejected
+     */
+    pub fn next(&self) -> Option<usize> {
+        self.entries.iter().position(|e| e.rejected.is_none())
+    }
+
+    /**
+     * Drops the entry after it's replayed. Later changes to the same subject
+     * were made on top of it, so they are no longer checked against the old lasttouch
+     */
+    pub fn remove_replayed(&mut self, index: usize) {
+        if index >= self.entries.len() {
+            return;
+        }
+
+        let replayed = self.entries.remove(index);
+        let subject = replayed.mutation.subject();
+        for entry in self.entries.iter_mut() {
+            if entry.mutation.subject() == subject {
+                entry.lasttouch = None;
+            }
+        }
+    }
+}
+
+pub fn default_journal_path(config: &Path) -> PathBuf {
+    config.with_file_name("bgmtty-journal.yml")
+}
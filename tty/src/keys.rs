@@ -0,0 +1,534 @@
+use crate::i18n::{fill, msgs};
+use crate::state::{Tab, UIState};
+use bgmtv::settings::Settings;
+use failure::{format_err, Error};
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use termion::event::Key;
+
+/**
+ * Everything that can be bound to a key. The names are used in the `keys`
+ * section of the config file
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Command,
+    HelpToggle,
+    HelpUp,
+    HelpDown,
+    Refresh,
+    Undo,
+    Redo,
+
+    CursorUp,
+    CursorDown,
+    CursorOpen,
+    CursorClear,
+
+    FilterToggle,
+    FilterInput,
+    MatchNext,
+    MatchPrev,
+    FilterClear,
+    ProgressInc,
+    ProgressDec,
+
+    EditStatus,
+    EditRating,
+    EditTags,
+    EditComment,
+    EpisodeLeft,
+    EpisodeRight,
+    EpisodeUp,
+    EpisodeDown,
+    EpisodeWatched,
+    EpisodeQueued,
+    EpisodeDropped,
+    EpisodeRemove,
+    LinkNext,
+    LinkPrev,
+
+    SearchEdit,
+    SearchSubmit,
+    PageNext,
+    PagePrev,
+
+    PendingDiscard,
+    PendingRetry,
+
+    TabNext,
+    TabPrev,
+    TabClose,
+    ScrollTop,
+    ScrollBottom,
+}
+
+/**
+ * In the order they are shown in the help panel. When a key is bound to
+ * several actions, the first one available in the current tab wins
+ */
+pub const ACTIONS: [Action; 44] = [
+    Action::HelpToggle,
+    Action::HelpUp,
+    Action::HelpDown,
+    Action::Quit,
+    Action::Command,
+    Action::Refresh,
+    Action::Undo,
+    Action::Redo,
+    Action::CursorUp,
+    Action::CursorDown,
+    Action::CursorOpen,
+    Action::CursorClear,
+    Action::FilterToggle,
+    Action::FilterInput,
+    Action::MatchNext,
+    Action::MatchPrev,
+    Action::FilterClear,
+    Action::ProgressInc,
+    Action::ProgressDec,
+    Action::EditStatus,
+    Action::EditRating,
+    Action::EditTags,
+    Action::EditComment,
+    Action::EpisodeLeft,
+    Action::EpisodeRight,
+    Action::EpisodeUp,
+    Action::EpisodeDown,
+    Action::EpisodeWatched,
+    Action::EpisodeQueued,
+    Action::EpisodeDropped,
+    Action::EpisodeRemove,
+    Action::LinkNext,
+    Action::LinkPrev,
+    Action::TabClose,
+    Action::SearchEdit,
+    Action::SearchSubmit,
+    Action::PageNext,
+    Action::PagePrev,
+    Action::PendingDiscard,
+    Action::PendingRetry,
+    Action::TabNext,
+    Action::TabPrev,
+    Action::ScrollTop,
+    Action::ScrollBottom,
+];
+
+fn has_focus(ui: &UIState) -> bool {
+    match ui.active_tab() {
+        Tab::Collection => ui.focus.get().is_some(),
+        tab => tab.get_focus().is_some(),
+    }
+}
+
+fn is_list(ui: &UIState) -> bool {
+    let tab = ui.active_tab();
+    tab.is_collection() || tab.is_page() || tab.is_search_result() || tab.is_pending()
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "app.quit",
+            Action::Command => "app.command",
+            Action::HelpToggle => "help.toggle",
+            Action::HelpUp => "help.up",
+            Action::HelpDown => "help.down",
+            Action::Refresh => "app.refresh",
+            Action::Undo => "app.undo",
+            Action::Redo => "app.redo",
+            Action::CursorUp => "cursor.up",
+            Action::CursorDown => "cursor.down",
+            Action::CursorOpen => "cursor.open",
+            Action::CursorClear => "cursor.clear",
+            Action::FilterToggle => "filter.toggle",
+            Action::FilterInput => "filter.input",
+            Action::MatchNext => "filter.next_match",
+            Action::MatchPrev => "filter.prev_match",
+            Action::FilterClear => "filter.clear",
+            Action::ProgressInc => "progress.inc",
+            Action::ProgressDec => "progress.dec",
+            Action::EditStatus => "subject.edit_status",
+            Action::EditRating => "subject.edit_rating",
+            Action::EditTags => "subject.edit_tags",
+            Action::EditComment => "subject.edit_comment",
+            Action::EpisodeLeft => "episode.left",
+            Action::EpisodeRight => "episode.right",
+            Action::EpisodeUp => "episode.up",
+            Action::EpisodeDown => "episode.down",
+            Action::EpisodeWatched => "episode.watched",
+            Action::EpisodeQueued => "episode.queue",
+            Action::EpisodeDropped => "episode.drop",
+            Action::EpisodeRemove => "episode.remove",
+            Action::LinkNext => "link.next",
+            Action::LinkPrev => "link.prev",
+            Action::SearchEdit => "search.edit",
+            Action::SearchSubmit => "search.submit",
+            Action::PageNext => "search.next_page",
+            Action::PagePrev => "search.prev_page",
+            Action::PendingDiscard => "pending.discard",
+            Action::PendingRetry => "pending.retry",
+            Action::TabNext => "tab.next",
+            Action::TabPrev => "tab.prev",
+            Action::TabClose => "tab.close",
+            Action::ScrollTop => "scroll.top",
+            Action::ScrollBottom => "scroll.bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|a| a.name() == name).cloned()
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["C-q"],
+            Action::Command => &[":"],
+            Action::HelpToggle => &["?", "h"],
+            Action::HelpUp => &["K"],
+            Action::HelpDown => &["J"],
+            Action::Refresh => &["R"],
+            Action::Undo => &["u"],
+            Action::Redo => &["C-r"],
+            Action::CursorUp => &["k", "Up"],
+            Action::CursorDown => &["j", "Down"],
+            Action::CursorOpen => &["Enter"],
+            Action::CursorClear => &["Esc"],
+            Action::FilterToggle => &["t"],
+            Action::FilterInput => &["/"],
+            Action::MatchNext => &["n"],
+            Action::MatchPrev => &["N"],
+            Action::FilterClear => &["Esc"],
+            Action::ProgressInc => &["+"],
+            Action::ProgressDec => &["-"],
+            Action::EditStatus => &["s"],
+            Action::EditRating => &["r"],
+            Action::EditTags => &["t"],
+            Action::EditComment => &["c"],
+            Action::EpisodeLeft => &["Left", "H"],
+            Action::EpisodeRight => &["Right", "L"],
+            Action::EpisodeUp => &["["],
+            Action::EpisodeDown => &["]"],
+            Action::EpisodeWatched => &["w"],
+            Action::EpisodeQueued => &["q"],
+            Action::EpisodeDropped => &["d"],
+            Action::EpisodeRemove => &["x"],
+            Action::LinkNext => &["n"],
+            Action::LinkPrev => &["N"],
+            Action::SearchEdit => &["e"],
+            Action::SearchSubmit => &["Enter"],
+            Action::PageNext => &["n"],
+            Action::PagePrev => &["N"],
+            Action::PendingDiscard => &["x"],
+            Action::PendingRetry => &["r"],
+            Action::TabNext => &["gt", "Tab"],
+            Action::TabPrev => &["gT"],
+            Action::TabClose => &["Esc"],
+            Action::ScrollTop => &["gg"],
+            Action::ScrollBottom => &["G"],
+        }
+    }
+
+    /**
+     * Long commands doing the same thing, listed next to the keys in the help panel
+     */
+    pub fn commands(&self) -> &'static [&'static str] {
+        match self {
+            Action::HelpToggle => &[":help"],
+            Action::Quit => &[":qa"],
+            _ => &[],
+        }
+    }
+
+    /**
+     * Shown after each key in the help panel, for actions reading an argument
+     */
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Action::FilterToggle => "<i>",
+            _ => "",
+        }
+    }
+
+    pub fn available(&self, ui: &UIState) -> bool {
+        let tab = ui.active_tab();
+        match self {
+            Action::HelpUp | Action::HelpDown => ui.help,
+            Action::Refresh | Action::ScrollTop | Action::ScrollBottom => !tab.is_search(),
+            Action::CursorUp | Action::CursorDown => is_list(ui),
+            Action::CursorOpen | Action::CursorClear => is_list(ui) && has_focus(ui),
+            Action::FilterToggle | Action::FilterInput => tab.is_collection(),
+            Action::MatchNext | Action::MatchPrev | Action::FilterClear => {
+                tab.is_collection() && ui.query != ""
+            }
+            Action::ProgressInc | Action::ProgressDec => tab.is_collection() && has_focus(ui),
+            Action::EditStatus | Action::EditRating | Action::EditTags | Action::EditComment => {
+                tab.is_subject()
+            }
+            Action::EpisodeLeft
+            | Action::EpisodeRight
+            | Action::EpisodeUp
+            | Action::EpisodeDown => tab.is_subject(),
+            Action::EpisodeWatched
+            | Action::EpisodeQueued
+            | Action::EpisodeDropped
+            | Action::EpisodeRemove => tab.get_episode().is_some(),
+            Action::LinkNext | Action::LinkPrev => tab.is_page(),
+            Action::TabClose => tab.is_page() && !has_focus(ui),
+            Action::SearchEdit | Action::SearchSubmit => tab.is_search(),
+            Action::PageNext | Action::PagePrev => tab.is_search_result(),
+            Action::PendingDiscard | Action::PendingRetry => tab.is_pending() && has_focus(ui),
+            _ => true,
+        }
+    }
+
+    pub fn desc(&self, ui: &UIState) -> &'static str {
+        let tab = ui.active_tab();
+        let m = msgs();
+        match self {
+            Action::Quit => m.action_quit,
+            Action::Command => m.action_command,
+            Action::HelpToggle => m.action_help_toggle,
+            Action::HelpUp => m.action_help_up,
+            Action::HelpDown => m.action_help_down,
+            Action::Refresh => m.action_refresh,
+            Action::Undo => m.action_undo,
+            Action::Redo => m.action_redo,
+            Action::CursorUp if tab.is_page() => m.action_scroll_up,
+            Action::CursorUp => m.action_select_prev,
+            Action::CursorDown if tab.is_page() => m.action_scroll_down,
+            Action::CursorDown => m.action_select_next,
+            Action::CursorOpen if tab.is_page() => m.action_open_link,
+            Action::CursorOpen if tab.is_search_result() => m.action_details,
+            Action::CursorOpen => m.action_details_edit,
+            Action::CursorClear => m.action_clear,
+            Action::FilterToggle => m.action_filter_toggle,
+            Action::FilterInput => m.action_filter_input,
+            Action::MatchNext => m.action_match_next,
+            Action::MatchPrev => m.action_match_prev,
+            Action::FilterClear => m.action_filter_clear,
+            Action::ProgressInc => m.action_progress_inc,
+            Action::ProgressDec => m.action_progress_dec,
+            Action::EditStatus => m.action_edit_status,
+            Action::EditRating => m.action_edit_rating,
+            Action::EditTags => m.action_edit_tags,
+            Action::EditComment => m.action_edit_comment,
+            Action::EpisodeLeft => m.action_episode_left,
+            Action::EpisodeRight => m.action_episode_right,
+            Action::EpisodeUp => m.action_episode_up,
+            Action::EpisodeDown => m.action_episode_down,
+            Action::EpisodeWatched => m.action_episode_watched,
+            Action::EpisodeQueued => m.action_episode_queued,
+            Action::EpisodeDropped => m.action_episode_dropped,
+            Action::EpisodeRemove => m.action_episode_remove,
+            Action::LinkNext => m.action_link_next,
+            Action::LinkPrev => m.action_link_prev,
+            Action::SearchEdit => m.action_search_edit,
+            Action::SearchSubmit => match tab {
+                Tab::Search { text } if text != "" => m.action_search_submit,
+                _ => m.action_search_edit,
+            },
+            Action::PageNext => m.action_page_next,
+            Action::PagePrev => m.action_page_prev,
+            Action::PendingDiscard => m.action_pending_discard,
+            Action::PendingRetry => m.action_pending_retry,
+            Action::TabNext => m.action_tab_next,
+            Action::TabPrev => m.action_tab_prev,
+            Action::TabClose => m.action_tab_close,
+            Action::ScrollTop => m.action_scroll_top,
+            Action::ScrollBottom => m.action_scroll_bottom,
+        }
+    }
+}
+
+const NAMED_KEYS: [(&str, Key); 14] = [
+    ("Enter", Key::Char('\n')),
+    ("Tab", Key::Char('\t')),
+    ("Space", Key::Char(' ')),
+    ("Esc", Key::Esc),
+    ("Backspace", Key::Backspace),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Delete", Key::Delete),
+];
+
+fn parse_token(token: &str) -> Option<Vec<Key>> {
+    if let Some((_, key)) = NAMED_KEYS.iter().find(|(name, _)| *name == token) {
+        return Some(vec![*key]);
+    }
+
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if token.starts_with("C-") {
+        return single(&token[2..]).map(|c| vec![Key::Ctrl(c)]);
+    }
+    if token.starts_with("A-") {
+        return single(&token[2..]).map(|c| vec![Key::Alt(c)]);
+    }
+    if token.len() > 1 && token.starts_with('F') {
+        if let Ok(n) = token[1..].parse() {
+            return Some(vec![Key::F(n)]);
+        }
+    }
+
+    Some(token.chars().map(Key::Char).collect())
+}
+
+/**
+ * Parses a key sequence such as `gt`, `C-q` or `g Enter`.
+ *
+ * Whitespace separates named keys from the others, everything else is typed one character at a time
+ */
+pub fn parse_keys(spec: &str) -> Result<Vec<Key>, Error> {
+    let mut result = Vec::new();
+    for token in spec.split_whitespace() {
+        match parse_token(token) {
+            Some(keys) => result.extend(keys),
+            None => return Err(format_err!("{}", fill(msgs().unknown_key, &[&spec]))),
+        }
+    }
+
+    if result.is_empty() {
+        return Err(format_err!("{}", msgs().empty_key));
+    }
+    Ok(result)
+}
+
+pub fn format_key(key: &Key) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, k)| k == key) {
+        return name.to_string();
+    }
+
+    match key {
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("C-{}", c),
+        Key::Alt(c) => format!("A-{}", c),
+        Key::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+pub fn format_keys(keys: &[Key]) -> String {
+    let tokens = keys.iter().map(format_key).collect::<Vec<_>>();
+    if tokens.iter().all(|t| t.chars().count() == 1) {
+        tokens.concat()
+    } else {
+        tokens.join(" ")
+    }
+}
+
+/**
+ * One key sequence or several of them, as written in the config file
+ */
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Keymap {
+    pub fn defaults() -> Keymap {
+        let mut bindings = Vec::new();
+        for action in ACTIONS.iter() {
+            for spec in action.default_keys() {
+                bindings.push((*action, parse_keys(spec).unwrap()));
+            }
+        }
+
+        Keymap { bindings }
+    }
+
+    /**
+     * Every action listed replaces all of its default bindings.
+     * An empty list unbinds the action
+     */
+    pub fn with_overrides(overrides: &BTreeMap<String, KeySpec>) -> Result<Keymap, Error> {
+        let mut keymap = Keymap::defaults();
+
+        for (name, spec) in overrides.iter() {
+            let action = Action::from_name(name)
+                .ok_or_else(|| format_err!("{}", fill(msgs().unknown_action, &[name])))?;
+
+            let specs = match spec {
+                KeySpec::One(s) => vec![s.clone()],
+                KeySpec::Many(s) => s.clone(),
+            };
+
+            keymap.bindings.retain(|(a, _)| *a != action);
+            for spec in specs.iter() {
+                keymap.bindings.push((action, parse_keys(spec)?));
+            }
+        }
+
+        // Keep the resolving order independent from the order in the config file
+        keymap.bindings.sort_by_key(|(action, _)| {
+            ACTIONS
+                .iter()
+                .position(|a| a == action)
+                .unwrap_or(ACTIONS.len())
+        });
+
+        Ok(keymap)
+    }
+
+    pub fn from_settings(settings: &Settings) -> Result<Keymap, Error> {
+        match settings.section::<BTreeMap<String, KeySpec>>("keys")? {
+            Some(overrides) => Keymap::with_overrides(&overrides),
+            None => Ok(Keymap::defaults()),
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = &[Key]> {
+        self.bindings
+            .iter()
+            .filter(move |(a, _)| *a == action)
+            .map(|(_, keys)| keys.as_slice())
+    }
+
+    /**
+     * Actions bound to exactly this sequence
+     */
+    pub fn lookup<'s>(&'s self, seq: &'s [Key]) -> impl Iterator<Item = Action> + 's {
+        self.bindings
+            .iter()
+            .filter(move |(_, keys)| keys.as_slice() == seq)
+            .map(|(action, _)| *action)
+    }
+
+    /**
+     * If some longer sequence starts with this one
+     */
+    pub fn is_prefix(&self, seq: &[Key]) -> bool {
+        self.bindings
+            .iter()
+            .any(|(_, keys)| keys.len() > seq.len() && keys.starts_with(seq))
+    }
+
+    /**
+     * How the action is triggered, as shown to the user
+     */
+    pub fn describe(&self, action: Action) -> Vec<String> {
+        self.keys(action)
+            .map(|keys| format_keys(keys) + action.suffix())
+            .chain(action.commands().iter().map(|c| c.to_string()))
+            .collect()
+    }
+}
@@ -0,0 +1,71 @@
+use crate::i18n::{fill, msgs};
+use failure::{format_err, Error};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/**
+ * Writes log records into a file, so the terminal UI is left untouched
+ */
+pub struct FileLogger {
+    level: LevelFilter,
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut file = self.file.lock().unwrap();
+        // Nowhere else to report a failing logger
+        let _ = writeln!(
+            file,
+            "{} {:5} [{}] {}",
+            chrono::Local::now().to_rfc3339(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().flush();
+    }
+}
+
+pub fn parse_level(level: &str) -> Result<LevelFilter, Error> {
+    match level.to_lowercase().as_str() {
+        "off" => Ok(LevelFilter::Off),
+        "error" => Ok(LevelFilter::Error),
+        "warn" => Ok(LevelFilter::Warn),
+        "info" => Ok(LevelFilter::Info),
+        "debug" => Ok(LevelFilter::Debug),
+        "trace" => Ok(LevelFilter::Trace),
+        _ => Err(format_err!("{}", fill(msgs().unknown_log_level, &[&level]))),
+    }
+}
+
+/**
+ * Appends to the given file. Only one logger can be installed per process
+ */
+pub fn init<P: AsRef<Path>>(file: P, level: LevelFilter) -> Result<(), Error> {
+    let file = OpenOptions::new().create(true).append(true).open(file)?;
+
+    log::set_boxed_logger(Box::new(FileLogger {
+        level,
+        file: Mutex::new(file),
+    }))
+    .map_err(|e| format_err!("{}", e))?;
+    log::set_max_level(level);
+
+    log::info!("bgmTTY {} started", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
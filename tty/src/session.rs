@@ -0,0 +1,60 @@
+use crate::i18n::{fill, msgs};
+use crate::state::{FocusState, ScrollState, Tab, SELECT_COUNT};
+use crate::yaml;
+use failure::{format_err, Error};
+use serde_derive::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/**
+ * Open tabs and the state of the collection tab, saved on quit and restored on launch
+ */
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub tabs: Vec<Tab>,
+    pub tab: usize,
+
+    pub filters: [bool; SELECT_COUNT],
+    #[serde(default)]
+    pub query: String,
+    pub scroll: ScrollState,
+    pub focus: FocusState,
+}
+
+impl Session {
+    /**
+     * None if there is no such session yet
+     */
+    pub fn load_from<P: AsRef<Path>>(file: P) -> Result<Option<Session>, Error> {
+        yaml::load(file)
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, file: P) -> Result<(), Error> {
+        yaml::save(file, self)
+    }
+}
+
+pub fn default_session_path(config: &Path) -> PathBuf {
+    config.with_file_name("bgmtty-session.yml")
+}
+
+/**
+ * Where `:mksession <name>` writes to. An empty name is the session restored on launch
+ */
+pub fn named_session_path(config: &Path, name: &str) -> Result<PathBuf, Error> {
+    if name == "" {
+        return Ok(default_session_path(config));
+    }
+
+    // Ends up in a file name
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format_err!(
+            "{}",
+            fill(msgs().session_name_invalid, &[&name])
+        ));
+    }
+
+    Ok(config.with_file_name(format!("bgmtty-session-{}.yml", name)))
+}
@@ -0,0 +1,266 @@
+use crate::i18n::{fill, msgs};
+use bgmtv::client::{CollectionEntry, SubjectType};
+use bgmtv::clock::{Clock, SystemClock};
+use chrono::NaiveDate;
+use failure::{format_err, Error};
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    LastTouch,
+    Name,
+    Progress,
+    Remaining,
+    AirDay,
+    Type,
+}
+
+pub const SORT_KEYS: [SortKey; 6] = [
+    SortKey::LastTouch,
+    SortKey::Name,
+    SortKey::Progress,
+    SortKey::Remaining,
+    SortKey::AirDay,
+    SortKey::Type,
+];
+
+impl SortKey {
+    /**
+     * As written after `:sort`
+     */
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::LastTouch => "lasttouch",
+            SortKey::Name => "name",
+            SortKey::Progress => "progress",
+            SortKey::Remaining => "remaining",
+            SortKey::AirDay => "airday",
+            SortKey::Type => "type",
+        }
+    }
+
+    pub fn disp(self) -> &'static str {
+        let m = msgs();
+        match self {
+            SortKey::LastTouch => m.sort_lasttouch,
+            SortKey::Name => m.sort_name,
+            SortKey::Progress => m.sort_progress,
+            SortKey::Remaining => m.sort_remaining,
+            SortKey::AirDay => m.sort_airday,
+            SortKey::Type => m.sort_type,
+        }
+    }
+}
+
+/**
+ * How the collection tab is sorted, saved in the `sort` section of the config file
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortMode {
+    pub key: SortKey,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SortMode {
+    /**
+     * Parses the arguments of `:sort`, e.g. `remaining` or `lasttouch desc`.
+     * No arguments at all restores the order of the API
+     */
+    pub fn parse(args: &str) -> Result<Option<SortMode>, Error> {
+        let mut args = args.split_whitespace();
+        let key = match args.next() {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+
+        let key = SORT_KEYS
+            .iter()
+            .find(|k| k.name() == key)
+            .cloned()
+            .ok_or_else(|| {
+                let names = SORT_KEYS.iter().map(|k| k.name()).collect::<Vec<_>>();
+                format_err!("{}", fill(msgs().unknown_sort, &[&key, &names.join("|")]))
+            })?;
+
+        let descending = match (args.next(), args.next()) {
+            (None, None) | (Some("asc"), None) => false,
+            (Some("desc"), None) => true,
+            (Some(other), _) | (None, Some(other)) => {
+                return Err(format_err!(
+                    "{}",
+                    fill(msgs().unknown_sort_order, &[&other])
+                ))
+            }
+        };
+
+        Ok(Some(SortMode { key, descending }))
+    }
+
+    pub fn disp(&self) -> String {
+        let arrow = if self.descending { "↓" } else { "↑" };
+        format!("{} {}", self.key.disp(), arrow)
+    }
+
+    /**
+     * Sorts stably, so entries with equal keys stay in the order of the API.
+     * Entries without a key, e.g. the remaining episodes of unknown length, always come last
+     */
+    pub fn apply(&self, entries: &mut Vec<&CollectionEntry>) {
+        self.apply_at(entries, &SystemClock)
+    }
+
+    /**
+     * Days until airing are counted from today in local time
+     */
+    pub fn apply_at(&self, entries: &mut Vec<&CollectionEntry>, clock: &dyn Clock) {
+        let today = clock
+            .now()
+            .with_timezone(&chrono::Local)
+            .naive_local()
+            .date();
+        entries.sort_by(|a, b| self.compare(a, b, today));
+    }
+
+    fn compare(&self, a: &CollectionEntry, b: &CollectionEntry, today: NaiveDate) -> Ordering {
+        let ordering = match self.key {
+            SortKey::LastTouch => a.lasttouch.cmp(&b.lasttouch),
+            SortKey::Name => a
+                .subject
+                .name
+                .to_lowercase()
+                .cmp(&b.subject.name.to_lowercase()),
+            SortKey::Progress => watched(a).cmp(&watched(b)),
+            SortKey::Remaining => return self.compare_partial(remaining(a), remaining(b)),
+            SortKey::AirDay => {
+                return self.compare_partial(days_to_air(a, today), days_to_air(b, today))
+            }
+            SortKey::Type => a
+                .subject
+                .subject_type
+                .value()
+                .cmp(&b.subject.subject_type.value()),
+        };
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn compare_partial<T: Ord>(&self, a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if self.descending => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+fn watched(entry: &CollectionEntry) -> u64 {
+    match entry.subject.subject_type {
+        SubjectType::Book => entry.vol_status,
+        _ => entry.ep_status,
+    }
+}
+
+fn remaining(entry: &CollectionEntry) -> Option<u64> {
+    let total = match entry.subject.subject_type {
+        SubjectType::Book => entry.subject.vols_count,
+        _ => entry.subject.eps_count,
+    };
+
+    // bgm.tv uses 0 for unknown lengths
+    total
+        .filter(|total| *total > 0)
+        .map(|total| total.saturating_sub(watched(entry)))
+}
+
+/**
+ * 0 if the next episode airs today
+ */
+fn days_to_air(entry: &CollectionEntry, today: NaiveDate) -> Option<i64> {
+    if entry.subject.subject_type == SubjectType::Book {
+        return None;
+    }
+
+    entry
+        .subject
+        .next_airing(today)
+        .map(|date| (date - today).num_days())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::entry;
+    use bgmtv::clock::FixedClock;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn sorted(mode: SortMode, entries: &[CollectionEntry], clock: &dyn Clock) -> Vec<u64> {
+        let mut refs = entries.iter().collect::<Vec<_>>();
+        mode.apply_at(&mut refs, clock);
+        refs.iter().map(|e| e.subject.id).collect()
+    }
+
+    fn noon(y: i32, m: u32, d: u32) -> FixedClock {
+        let local = NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        FixedClock::new(
+            chrono::Local
+                .from_local_datetime(&local)
+                .unwrap()
+                .with_timezone(&chrono::Utc),
+        )
+    }
+
+    #[test]
+    fn parses_modes() {
+        let mode = SortMode::parse("progress desc").unwrap().unwrap();
+        assert_eq!(mode.key, SortKey::Progress);
+        assert!(mode.descending);
+        assert!(SortMode::parse("nonsense").is_err());
+    }
+
+    #[test]
+    fn sorts_by_next_airing() {
+        // Mondays, Tuesdays and Sundays from the same week. Books never air
+        let entries = vec![
+            entry(1).air_date("2019-01-07").build(),
+            entry(2).air_date("2019-01-08").build(),
+            entry(3).subject_type(1).air_date("2019-01-07").build(),
+            entry(4).air_date("2019-01-06").build(),
+        ];
+        let mode = SortMode {
+            key: SortKey::AirDay,
+            descending: false,
+        };
+
+        assert_eq!(sorted(mode, &entries, &noon(2019, 1, 14)), vec![1, 2, 4, 3]);
+        assert_eq!(sorted(mode, &entries, &noon(2019, 1, 15)), vec![2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn unknown_keys_come_last() {
+        let unknown = entry(3).eps_count(0).build();
+        let entries = vec![
+            entry(1).ep_status(10).build(),
+            unknown,
+            entry(2).ep_status(2).build(),
+        ];
+        let mode = SortMode {
+            key: SortKey::Remaining,
+            descending: true,
+        };
+
+        assert_eq!(sorted(mode, &entries, &noon(2019, 1, 1)), vec![2, 1, 3]);
+    }
+}
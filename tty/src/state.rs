@@ -1,13 +1,26 @@
+use crate::fuzzy;
+use crate::i18n::{fill, msgs};
+use crate::journal::{Journal, Mutation, PendingWrite};
+use crate::keys::{format_keys, Action, Keymap};
+use crate::session::{named_session_path, Session};
+use crate::sort::SortMode;
 use crate::{Args, CollectionStatusExt};
+use bgmtv::api::BangumiApi;
 use bgmtv::client::{
-    Client, CollectionDetail, CollectionEntry, CollectionStatus, SubjectSmall, SubjectType,
+    Character, CharacterPerson, Client, CollectionDetail, CollectionEntry, CollectionStatus,
+    CreditedSubject, Episode, EpisodeStatus, Person, RelatedSubject, SubjectCharacter,
+    SubjectPerson, SubjectSmall, SubjectType,
 };
+use bgmtv::settings::{FileStore, Store};
+use bgmtv::transport::is_connection_error;
 use crossbeam_channel::Sender;
 use futures::future::Future;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::hash_map;
 use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -27,6 +40,13 @@ impl<T> FetchResult<T> {
             },
         }
     }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> FetchResult<U> {
+        match self {
+            FetchResult::Direct(t) => FetchResult::Direct(f(t)),
+            FetchResult::Deferred => FetchResult::Deferred,
+        }
+    }
 }
 
 impl<T, U> std::ops::Add<FetchResult<U>> for FetchResult<T> {
@@ -66,19 +86,231 @@ pub struct PopulatedSearchResult {
 
 pub const SEARCH_PAGING: usize = 10;
 
+/**
+ * How many changes can be undone
+ */
+const HISTORY_LIMIT: usize = 100;
+
+/**
+ * How long to wait before replaying the journal again after a failure
+ */
+const REPLAY_RETRY: Duration = Duration::from_secs(30);
+
+/**
+ * Why a mutation is sent, deciding where it goes in the history once done
+ */
+#[derive(Clone, Copy, PartialEq)]
+enum Replay {
+    Do,
+    Undo,
+    Redo,
+    // The first entry of the journal, done once it's sent
+    Journal,
+}
+
+/**
+ * Replaying the journal goes one entry at a time
+ */
+#[derive(Clone, Copy, PartialEq)]
+enum Flush {
+    Idle,
+    // Waiting for the collection to check the lasttouch against
+    Checking,
+    Sending,
+}
+
 struct AppStateInner {
     notifier: Sender<()>,
 
     collection: InnerState<(), Vec<CollectionEntry>>,
     collection_detail: HashMap<u64, InnerState<(), Option<CollectionDetail>>>,
     subject: HashMap<u64, InnerState<(), SubjectSmall>>,
+    relations: HashMap<u64, InnerState<(), Vec<RelatedSubject>>>,
+    subject_characters: HashMap<u64, InnerState<(), Vec<SubjectCharacter>>>,
+    subject_persons: HashMap<u64, InnerState<(), Vec<SubjectPerson>>>,
+    episodes: HashMap<u64, InnerState<(), Vec<Episode>>>,
+    episode_progress: HashMap<u64, InnerState<(), HashMap<u64, EpisodeStatus>>>,
+    character: HashMap<u64, InnerState<(), Character>>,
+    character_subjects: HashMap<u64, InnerState<(), Vec<CreditedSubject>>>,
+    character_persons: HashMap<u64, InnerState<(), Vec<CharacterPerson>>>,
+    person: HashMap<u64, InnerState<(), Person>>,
+    person_subjects: HashMap<u64, InnerState<(), Vec<CreditedSubject>>>,
     search: HashMap<(String, usize), InnerState<(), ShallowSearchResult>>,
 
+    undo: Vec<Mutation>,
+    redo: Vec<Mutation>,
+
+    journal: Journal,
+    journal_path: PathBuf,
+    flush: Flush,
+    next_flush: Instant,
+
     messages: Vec<String>,
 }
 
-pub struct AppState {
-    client: Client,
+impl AppStateInner {
+    fn subject_name(&self, id: u64) -> String {
+        if let Some(InnerState::Fetched(_, subject)) = self.subject.get(&id) {
+            return subject.name.clone();
+        }
+
+        if let InnerState::Fetched(_, ref entries) = self.collection {
+            if let Some(entry) = entries.iter().find(|e| e.subject.id == id) {
+                return entry.subject.name.clone();
+            }
+        }
+
+        format!("#{}", id)
+    }
+
+    fn record(&mut self, mutation: Mutation, replay: Replay) {
+        match replay {
+            Replay::Do => {
+                self.undo.push(mutation);
+                self.redo.clear();
+            }
+            // Made before anything on the redo stack was undone
+            Replay::Journal => self.undo.push(mutation),
+            Replay::Undo => {
+                self.messages.push(fill(msgs().undone, &[&mutation.disp()]));
+                self.redo.push(mutation);
+            }
+            Replay::Redo => {
+                self.messages.push(fill(msgs().redone, &[&mutation.disp()]));
+                self.undo.push(mutation);
+            }
+        }
+
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /**
+     * Keeps a mutation which failed to be sent, so it can be retried.
+     * New changes are only journaled when bgm.tv could not be reached, rejected ones are dropped
+     */
+    fn restore(&mut self, mutation: Mutation, replay: Replay, error: &failure::Error) {
+        match replay {
+            Replay::Do => {
+                if is_connection_error(error) {
+                    self.enqueue(mutation);
+                    self.next_flush = Instant::now() + REPLAY_RETRY;
+                }
+            }
+            Replay::Undo => self.undo.push(mutation),
+            Replay::Redo => self.redo.push(mutation),
+            Replay::Journal if is_connection_error(error) => {
+                self.flush = Flush::Idle;
+                self.next_flush = Instant::now() + REPLAY_RETRY;
+            }
+            Replay::Journal => {
+                // Set aside, so that the rest of the journal goes on
+                if let Some(index) = self.journal.next() {
+                    self.journal.entries[index].rejected = Some(error.to_string());
+                    self.save_journal();
+                    self.messages
+                        .push(fill(msgs().replay_rejected, &[&mutation.disp()]));
+                }
+
+                // Shown as done in the meantime
+                self.collection = InnerState::Discarded;
+                discard(&mut self.collection_detail, mutation.subject());
+                self.flush = Flush::Idle;
+                self.next_flush = Instant::now();
+            }
+        }
+    }
+
+    /**
+     * Appends the mutation to the journal, showing it as done in the meantime
+     */
+    fn enqueue(&mut self, mutation: Mutation) {
+        let lasttouch = match mutation {
+            Mutation::Progress(ref before, _) => Some(before.lasttouch),
+            Mutation::Detail { id, .. } => match self.collection {
+                InnerState::Fetched(_, ref entries) => entries
+                    .iter()
+                    .find(|e| e.subject.id == id)
+                    .map(|e| e.lasttouch),
+                _ => None,
+            },
+        };
+
+        self.apply_locally(&mutation);
+        self.journal.entries.push(PendingWrite {
+            mutation,
+            time: chrono::Utc::now(),
+            lasttouch,
+            conflict: false,
+            rejected: None,
+        });
+        self.save_journal();
+
+        let count = self.journal.entries.len();
+        self.messages.push(fill(msgs().write_queued, &[&count]));
+    }
+
+    fn apply_locally(&mut self, mutation: &Mutation) {
+        match mutation {
+            Mutation::Progress(_, after) => {
+                if let InnerState::Fetched(_, ref mut entries) = self.collection {
+                    for entry in entries.iter_mut() {
+                        if entry.subject.id == after.subject.id {
+                            *entry = after.clone();
+                        }
+                    }
+                }
+            }
+            Mutation::Detail {
+                id,
+                before,
+                after: (status, detail),
+                ..
+            } => {
+                let detail = detail.clone().or_else(|| before.clone());
+                if let Some(mut detail) = detail {
+                    detail.status = status.clone();
+                    self.collection_detail
+                        .insert(*id, InnerState::Fetched((), Some(detail)));
+                }
+            }
+        }
+    }
+
+    /**
+     * After the first entry of the journal is sent, goes on with the next one
+     */
+    fn replayed(&mut self) {
+        if let Some(index) = self.journal.next() {
+            self.journal.remove_replayed(index);
+        }
+        self.save_journal();
+        self.flush = Flush::Idle;
+        self.next_flush = Instant::now();
+    }
+
+    /**
+     * Whether changing the entry would mix up which one is being sent.
+     * Retrying a rejected entry before it would make that one the next
+     */
+    fn is_replaying(&self, index: usize) -> bool {
+        match self.journal.next() {
+            Some(next) => self.flush == Flush::Sending && index <= next,
+            None => false,
+        }
+    }
+
+    fn save_journal(&mut self) {
+        if let Err(e) = self.journal.save_to(&self.journal_path) {
+            log::error!("{}", e);
+            self.messages.push(fill(msgs().journal_save_failed, &[&e]));
+        }
+    }
+}
+
+pub struct AppState<A = Client> {
+    client: A,
 
     inner: Arc<Mutex<AppStateInner>>,
 
@@ -87,8 +319,15 @@ pub struct AppState {
     fetching_collection: bool,
 }
 
-impl AppState {
-    pub fn create(notifier: Sender<()>, client: Client) -> AppState {
+impl<A: BangumiApi> AppState<A> {
+    pub fn create(notifier: Sender<()>, client: A, journal_path: PathBuf) -> AppState<A> {
+        let mut messages = ["Loading bgmTTY...".to_string()].to_vec();
+        let journal = Journal::load_from(&journal_path).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            messages.push(fill(msgs().journal_load_failed, &[&e]));
+            Journal::default()
+        });
+
         AppState {
             client,
 
@@ -97,8 +336,24 @@ impl AppState {
                 collection: InnerState::Discarded,
                 collection_detail: HashMap::new(),
                 subject: HashMap::new(),
+                relations: HashMap::new(),
+                subject_characters: HashMap::new(),
+                subject_persons: HashMap::new(),
+                episodes: HashMap::new(),
+                episode_progress: HashMap::new(),
+                character: HashMap::new(),
+                character_subjects: HashMap::new(),
+                character_persons: HashMap::new(),
+                person: HashMap::new(),
+                person_subjects: HashMap::new(),
                 search: HashMap::new(),
-                messages: ["Loading bgmTTY...".to_string()].to_vec(),
+                undo: Vec::new(),
+                redo: Vec::new(),
+                journal,
+                journal_path,
+                flush: Flush::Idle,
+                next_flush: Instant::now(),
+                messages,
             })),
 
             rt: tokio::runtime::Runtime::new().expect("Cannot create runtime!"),
@@ -121,7 +376,9 @@ impl AppState {
         }
 
         self.fetching_collection = true;
-        guard.messages.push("刷新收藏中...".to_string());
+        guard
+            .messages
+            .push(msgs().refreshing_collection.to_string());
         guard.notifier.send(()).unwrap();
         drop(guard);
 
@@ -134,18 +391,22 @@ impl AppState {
                 let mut inner = handle.lock().unwrap();
 
                 inner.collection = InnerState::Fetched((), resp);
-                inner.messages.push("收藏加载完成！".to_string());
+                inner.messages.push(msgs().collection_loaded.to_string());
                 inner
                     .notifier
                     .send(())
                     .expect("Unable to notify the main thread");
             })
             .map_err(move |e| {
-                err_handle
-                    .lock()
-                    .unwrap()
+                log::error!("{}", e);
+                let mut inner = err_handle.lock().unwrap();
+                if inner.flush == Flush::Checking {
+                    inner.flush = Flush::Idle;
+                    inner.next_flush = Instant::now() + REPLAY_RETRY;
+                }
+                inner
                     .messages
-                    .push(format!("请求失败！{}", e))
+                    .push(format!("{}{}", msgs().request_failed, e))
             });
 
         self.rt.spawn(fut);
@@ -154,37 +415,92 @@ impl AppState {
     }
 
     pub fn update_progress(&mut self, coll: &CollectionEntry, ep: Option<u64>, vol: Option<u64>) {
+        let mut after = coll.clone();
+        after.ep_status = ep.unwrap_or(coll.ep_status);
+        after.vol_status = vol.unwrap_or(coll.vol_status);
+
+        self.send_progress(Mutation::Progress(coll.clone(), after), Replay::Do);
+    }
+
+    fn send_progress(&mut self, mutation: Mutation, replay: Replay) {
         let mut guard = self.inner.lock().unwrap();
+        // Changes waiting in the journal go first
+        if replay == Replay::Do && guard.journal.next().is_some() {
+            guard.enqueue(mutation);
+            guard.notifier.send(()).unwrap();
+            return;
+        }
+
+        let target = match (&mutation, replay) {
+            (Mutation::Progress(before, _), Replay::Undo) => before,
+            (Mutation::Progress(_, after), _) => after,
+            _ => unreachable!(),
+        };
+
         guard
             .messages
-            .push(format!("更新进度: {}...", coll.subject.id));
+            .push(fill(msgs().updating_progress, &[&target.subject.id]));
         guard.notifier.send(()).unwrap();
 
-        let fut = self.client.progress(coll, ep, vol);
+        let fut = self
+            .client
+            .progress(target, Some(target.ep_status), Some(target.vol_status));
         let handle = self.inner.clone();
         let err_handle = self.inner.clone();
+        let failed = mutation.clone();
 
         let fut = fut
             .map(move |_| {
                 let mut inner = handle.lock().unwrap();
 
                 inner.collection = InnerState::Discarded;
+                inner.record(mutation, replay);
+                if replay == Replay::Journal {
+                    inner.replayed();
+                }
                 inner
                     .notifier
                     .send(())
                     .expect("Unable to notify the main thread");
             })
             .map_err(move |e| {
-                err_handle
-                    .lock()
-                    .unwrap()
+                log::error!("{}", e);
+                let mut inner = err_handle.lock().unwrap();
+                inner
                     .messages
-                    .push(format!("请求失败！{}", e))
+                    .push(format!("{}{}", msgs().request_failed, e));
+                inner.restore(failed, replay, &e);
             });
 
         self.rt.spawn(fut);
     }
 
+    /**
+     * Reverts the last change to the collection by sending what it was before
+     */
+    pub fn undo(&mut self) {
+        let mutation = self.inner.lock().unwrap().undo.pop();
+        match mutation {
+            None => self.publish_message(msgs().nothing_to_undo.to_string()),
+            Some(mutation @ Mutation::Progress(..)) => self.send_progress(mutation, Replay::Undo),
+            Some(Mutation::Detail {
+                ref name,
+                before: None,
+                ..
+            }) => self.publish_message(fill(msgs().undo_uncollect, &[name])),
+            Some(mutation) => self.send_collection_detail(mutation, Replay::Undo),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        let mutation = self.inner.lock().unwrap().redo.pop();
+        match mutation {
+            None => self.publish_message(msgs().nothing_to_redo.to_string()),
+            Some(mutation @ Mutation::Progress(..)) => self.send_progress(mutation, Replay::Redo),
+            Some(mutation) => self.send_collection_detail(mutation, Replay::Redo),
+        }
+    }
+
     pub fn publish_message(&mut self, msg: String) {
         let msgs = &mut self.inner.lock().unwrap().messages;
         msgs.push(msg);
@@ -212,7 +528,9 @@ impl AppState {
             },
         }
 
-        guard.messages.push("获取收藏状态...".to_string());
+        guard
+            .messages
+            .push(msgs().fetching_collection_detail.to_string());
         guard.notifier.send(()).unwrap();
         drop(guard);
 
@@ -227,18 +545,19 @@ impl AppState {
                 inner
                     .collection_detail
                     .insert(id, InnerState::Fetched((), resp));
-                inner.messages.push("收藏加载完成！".to_string());
+                inner.messages.push(msgs().collection_loaded.to_string());
                 inner
                     .notifier
                     .send(())
                     .expect("Unable to notify the main thread");
             })
             .map_err(move |e| {
+                log::error!("{}", e);
                 err_handle
                     .lock()
                     .unwrap()
                     .messages
-                    .push(format!("请求失败！{}", e))
+                    .push(format!("{}{}", msgs().request_failed, e))
             });
 
         self.rt.spawn(fut);
@@ -252,39 +571,219 @@ impl AppState {
         status: CollectionStatus,
         original: Option<CollectionDetail>,
     ) {
+        let guard = self.inner.lock().unwrap();
+        // Callers edit what they have fetched, so it's in the cache
+        let before = match guard.collection_detail.get(&id) {
+            Some(InnerState::Fetched(_, before)) => before.clone(),
+            _ => None,
+        };
+        let name = guard.subject_name(id);
+        drop(guard);
+
+        let mutation = Mutation::Detail {
+            id,
+            name,
+            before,
+            after: (status, original),
+        };
+        self.send_collection_detail(mutation, Replay::Do);
+    }
+
+    fn send_collection_detail(&mut self, mut mutation: Mutation, replay: Replay) {
+        let (id, status, original) = match (&mutation, replay) {
+            (
+                Mutation::Detail {
+                    id,
+                    before: Some(before),
+                    ..
+                },
+                Replay::Undo,
+            ) => (*id, before.status.clone(), Some(before.clone())),
+            (
+                Mutation::Detail {
+                    id,
+                    after: (status, original),
+                    ..
+                },
+                _,
+            ) => (*id, status.clone(), original.clone()),
+            _ => unreachable!(),
+        };
+
         let mut guard = self.inner.lock().unwrap();
-        guard.messages.push("更新更新...".to_string());
+        if replay == Replay::Do && guard.journal.next().is_some() {
+            guard.enqueue(mutation);
+            guard.notifier.send(()).unwrap();
+            return;
+        }
+
+        guard.messages.push(msgs().updating_collection.to_string());
         guard.notifier.send(()).unwrap();
         drop(guard);
 
         let fut = self.client.update_collection_detail(id, status, original);
         let handle = self.inner.clone();
         let err_handle = self.inner.clone();
+        let failed = mutation.clone();
 
         let fut = fut
             .map(move |resp| {
                 let mut inner = handle.lock().unwrap();
 
+                // Redo what bgm.tv has actually saved
+                if replay == Replay::Do || replay == Replay::Journal {
+                    if let Mutation::Detail { ref mut after, .. } = mutation {
+                        *after = (resp.status.clone(), Some(resp.clone()));
+                    }
+                }
+
                 inner
                     .collection_detail
                     .insert(id, InnerState::Fetched((), Some(resp)));
-                inner.messages.push("收藏更新完成！".to_string());
+                inner.messages.push(msgs().collection_updated.to_string());
+                inner.record(mutation, replay);
+                if replay == Replay::Journal {
+                    inner.replayed();
+                }
                 inner
                     .notifier
                     .send(())
                     .expect("Unable to notify the main thread");
             })
             .map_err(move |e| {
-                err_handle
-                    .lock()
-                    .unwrap()
+                log::error!("{}", e);
+                let mut inner = err_handle.lock().unwrap();
+                inner
                     .messages
-                    .push(format!("请求失败！{}", e))
+                    .push(format!("{}{}", msgs().request_failed, e));
+                inner.restore(failed, replay, &e);
             });
 
         self.rt.spawn(fut);
     }
 
+    /**
+     * Sends the first entry of the journal if it's time to. Called repeatedly by the main loop,
+     * each call moving the replay one step further
+     */
+    pub fn replay_journal(&mut self) {
+        let mut guard = self.inner.lock().unwrap();
+        match guard.flush {
+            Flush::Sending => return,
+            Flush::Idle => {
+                let ready = match guard.journal.next() {
+                    Some(index) => !guard.journal.entries[index].conflict,
+                    None => false,
+                };
+                if !ready || Instant::now() < guard.next_flush {
+                    return;
+                }
+
+                // Check against a fresh collection
+                guard.flush = Flush::Checking;
+                guard.collection = InnerState::Discarded;
+                return;
+            }
+            Flush::Checking => {}
+        }
+
+        let current = match guard.collection {
+            InnerState::Fetching(_) => return,
+            InnerState::Discarded => {
+                drop(guard);
+                self.fetch_collection();
+                return;
+            }
+            InnerState::Fetched(_, ref entries) => {
+                let write = match guard.journal.next() {
+                    Some(index) => &guard.journal.entries[index],
+                    None => {
+                        guard.flush = Flush::Idle;
+                        return;
+                    }
+                };
+                let subject = write.mutation.subject();
+                entries
+                    .iter()
+                    .find(|e| e.subject.id == subject)
+                    .map(|e| e.lasttouch)
+            }
+        };
+
+        let index = guard.journal.next().unwrap();
+        let write = guard.journal.entries[index].clone();
+        if write.lasttouch.is_some() && write.lasttouch != current {
+            guard.journal.entries[index].conflict = true;
+            guard.save_journal();
+            guard.flush = Flush::Idle;
+            guard
+                .messages
+                .push(fill(msgs().replay_conflict, &[&write.mutation.disp()]));
+            guard.notifier.send(()).unwrap();
+            return;
+        }
+
+        guard.flush = Flush::Sending;
+        drop(guard);
+
+        match write.mutation {
+            mutation @ Mutation::Progress(..) => self.send_progress(mutation, Replay::Journal),
+            mutation => self.send_collection_detail(mutation, Replay::Journal),
+        }
+    }
+
+    pub fn pending(&self) -> Vec<PendingWrite> {
+        self.inner.lock().unwrap().journal.entries.clone()
+    }
+
+    pub fn is_pending(&self, subject: u64) -> bool {
+        self.inner.lock().unwrap().journal.is_pending(subject)
+    }
+
+    /**
+     * Drops an entry of the journal, along with what it has changed locally
+     */
+    pub fn discard_pending(&mut self, index: usize) {
+        let mut guard = self.inner.lock().unwrap();
+        if index >= guard.journal.entries.len() || guard.is_replaying(index) {
+            return;
+        }
+
+        let write = guard.journal.entries.remove(index);
+        guard.save_journal();
+
+        let subject = write.mutation.subject();
+        guard.collection = InnerState::Discarded;
+        discard(&mut guard.collection_detail, subject);
+        guard
+            .messages
+            .push(fill(msgs().pending_discarded, &[&write.mutation.disp()]));
+        guard.notifier.send(()).unwrap();
+    }
+
+    /**
+     * Replays the journal right away. A conflicting entry is sent as is, overwriting
+     * whatever was changed elsewhere, and a rejected one is sent again
+     */
+    pub fn retry_pending(&mut self, index: usize) {
+        let mut guard = self.inner.lock().unwrap();
+        if guard.is_replaying(index) {
+            return;
+        }
+
+        if let Some(write) = guard.journal.entries.get_mut(index) {
+            if write.conflict {
+                write.conflict = false;
+                write.lasttouch = None;
+            }
+            write.rejected = None;
+        }
+
+        guard.save_journal();
+        guard.next_flush = Instant::now();
+        guard.notifier.send(()).unwrap();
+    }
+
     pub fn fetch_subject(&mut self, id: u64) -> FetchResult<SubjectSmall> {
         let mut guard = self.inner.lock().unwrap();
         let entry = guard.subject.entry(id);
@@ -302,7 +801,7 @@ impl AppState {
             },
         }
 
-        guard.messages.push(format!("获取条目中: {}...", id));
+        guard.messages.push(fill(msgs().fetching_subject, &[&id]));
         guard.notifier.send(()).unwrap();
         drop(guard);
 
@@ -315,18 +814,19 @@ impl AppState {
                 let mut inner = handle.lock().unwrap();
 
                 inner.subject.insert(id, InnerState::Fetched((), resp));
-                inner.messages.push("条目加载完成！".to_string());
+                inner.messages.push(msgs().subject_loaded.to_string());
                 inner
                     .notifier
                     .send(())
                     .expect("Unable to notify the main thread");
             })
             .map_err(move |e| {
+                log::error!("{}", e);
                 err_handle
                     .lock()
                     .unwrap()
                     .messages
-                    .push(format!("请求失败！{}", e))
+                    .push(format!("{}{}", msgs().request_failed, e))
             });
 
         self.rt.spawn(fut);
@@ -334,43 +834,31 @@ impl AppState {
         FetchResult::Deferred
     }
 
-    fn populate_search<'a>(
-        &self,
-        shallow: &'a ShallowSearchResult,
-    ) -> FetchResult<PopulatedSearchResult> {
-        let mut list = Vec::with_capacity(shallow.ids.len());
-        let count = shallow.count;
-
-        let guard = self.inner.lock().unwrap();
-
-        for id in shallow.ids.iter() {
-            if let Some(InnerState::Fetched((), ref content)) = guard.subject.get(id) {
-                list.push(content.clone());
-            } else {
-                return FetchResult::Deferred;
-            }
-        }
-
-        FetchResult::Direct(PopulatedSearchResult { count, list })
-    }
-
-    pub fn fetch_search(
+    /**
+     * Shared by all the caches keyed by a single ID, which only differ in
+     * the map they live in and the request used to populate them
+     */
+    fn fetch_keyed<T, F, M>(
         &mut self,
-        search: &str,
-        index: usize,
-    ) -> FetchResult<PopulatedSearchResult> {
+        id: u64,
+        field: fn(&mut AppStateInner) -> &mut HashMap<u64, InnerState<(), T>>,
+        loading: String,
+        done: &'static str,
+        make: M,
+    ) -> FetchResult<T>
+    where
+        T: Clone + Send + 'static,
+        F: Future<Item = T, Error = failure::Error> + Send + 'static,
+        M: FnOnce(&A) -> F,
+    {
         let mut guard = self.inner.lock().unwrap();
-        let entry = guard.search.entry((search.to_string(), index));
+        let entry = field(&mut guard).entry(id);
         match entry {
             hash_map::Entry::Vacant(entry) => {
                 entry.insert(InnerState::Fetching(()));
             }
             hash_map::Entry::Occupied(mut entry) => match entry.get_mut() {
-                InnerState::Fetched(_, ref result) => {
-                    let cloned = result.clone();
-                    drop(guard);
-                    return self.populate_search(&cloned);
-                }
+                InnerState::Fetched(_, ref result) => return FetchResult::Direct(result.clone()),
                 InnerState::Fetching(_) => return FetchResult::Deferred,
                 value => {
                     // Else: discarded or fetching another, restart fetch
@@ -379,48 +867,32 @@ impl AppState {
             },
         }
 
-        guard.messages.push(format!("搜索中: {}...", search));
+        guard.messages.push(loading);
         guard.notifier.send(()).unwrap();
         drop(guard);
 
-        let skip = index * SEARCH_PAGING;
-        let fut = self.client.search(search, SEARCH_PAGING, skip);
+        let fut = make(&self.client);
         let handle = self.inner.clone();
         let err_handle = self.inner.clone();
 
-        let search = search.to_string();
-
         let fut = fut
             .map(move |resp| {
                 let mut inner = handle.lock().unwrap();
 
-                let mut ids = Vec::with_capacity(resp.list.len());
-                let count = resp.count;
-
-                for subject in resp.list.into_iter() {
-                    ids.push(subject.id);
-                    inner
-                        .subject
-                        .insert(subject.id, InnerState::Fetched((), subject));
-                }
-
-                inner.search.insert(
-                    (search, index),
-                    InnerState::Fetched((), ShallowSearchResult { count, ids }),
-                );
-
-                inner.messages.push("搜索完成！".to_string());
+                field(&mut inner).insert(id, InnerState::Fetched((), resp));
+                inner.messages.push(done.to_string());
                 inner
                     .notifier
                     .send(())
                     .expect("Unable to notify the main thread");
             })
             .map_err(move |e| {
+                log::error!("{}", e);
                 err_handle
                     .lock()
                     .unwrap()
                     .messages
-                    .push(format!("请求失败！{}", e))
+                    .push(format!("{}{}", msgs().request_failed, e))
             });
 
         self.rt.spawn(fut);
@@ -428,43 +900,362 @@ impl AppState {
         FetchResult::Deferred
     }
 
-    pub fn refresh_collection(&mut self) {
-        self.inner.lock().unwrap().collection = InnerState::Discarded;
+    pub fn fetch_relations(&mut self, id: u64) -> FetchResult<Vec<RelatedSubject>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.relations,
+            fill(msgs().fetching_relations, &[&id]),
+            msgs().relations_loaded,
+            |client| client.related_subjects(id),
+        )
     }
 
-    pub fn refresh_search(&mut self, search: String, index: usize) {
-        self.inner
-            .lock()
-            .unwrap()
-            .search
-            .entry((search, index))
-            .and_modify(|s| *s = InnerState::Discarded);
+    pub fn fetch_subject_characters(&mut self, id: u64) -> FetchResult<Vec<SubjectCharacter>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.subject_characters,
+            fill(msgs().fetching_characters, &[&id]),
+            msgs().characters_loaded,
+            |client| client.subject_characters(id),
+        )
     }
 
-    pub fn refresh_subject(&mut self, id: u64) {
-        self.inner
-            .lock()
-            .unwrap()
-            .subject
-            .entry(id)
-            .and_modify(|s| *s = InnerState::Discarded);
+    pub fn fetch_subject_persons(&mut self, id: u64) -> FetchResult<Vec<SubjectPerson>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.subject_persons,
+            fill(msgs().fetching_staff, &[&id]),
+            msgs().staff_loaded,
+            |client| client.subject_persons(id),
+        )
     }
 
-    pub fn refresh_collection_detail(&mut self, id: u64) {
-        self.inner
-            .lock()
-            .unwrap()
-            .collection_detail
-            .entry(id)
-            .and_modify(|s| *s = InnerState::Discarded);
+    pub fn fetch_episodes(&mut self, id: u64) -> FetchResult<Vec<Episode>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.episodes,
+            fill(msgs().fetching_episodes, &[&id]),
+            msgs().episodes_loaded,
+            |client| client.episodes(id),
+        )
     }
-}
 
-pub const SELECTS: [SubjectType; 3] = [SubjectType::Anime, SubjectType::Book, SubjectType::Real];
+    pub fn fetch_episode_progress(&mut self, id: u64) -> FetchResult<HashMap<u64, EpisodeStatus>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.episode_progress,
+            fill(msgs().fetching_episode_progress, &[&id]),
+            msgs().episode_progress_loaded,
+            |client| client.episode_progress(id),
+        )
+    }
 
-#[derive(Clone)]
-pub struct ScrollState {
-    scroll: u16,
+    /**
+     * Marks a single episode of the subject, or removes its mark with `None`
+     */
+    pub fn mark_episode(&mut self, subject: u64, ep: u64, status: Option<EpisodeStatus>) {
+        let mut guard = self.inner.lock().unwrap();
+        guard.messages.push(fill(msgs().updating_episode, &[&ep]));
+        guard.notifier.send(()).unwrap();
+        drop(guard);
+
+        let fut = self.client.update_episode(ep, status);
+        let handle = self.inner.clone();
+        let err_handle = self.inner.clone();
+
+        let fut = fut
+            .map(move |_| {
+                let mut inner = handle.lock().unwrap();
+
+                if let Some(InnerState::Fetched(_, ref mut progress)) =
+                    inner.episode_progress.get_mut(&subject)
+                {
+                    match status {
+                        Some(status) => progress.insert(ep, status),
+                        None => progress.remove(&ep),
+                    };
+                }
+
+                // Watched episodes count towards the progress in the collection
+                inner.collection = InnerState::Discarded;
+                inner.messages.push(msgs().episode_updated.to_string());
+                inner
+                    .notifier
+                    .send(())
+                    .expect("Unable to notify the main thread");
+            })
+            .map_err(move |e| {
+                log::error!("{}", e);
+                err_handle
+                    .lock()
+                    .unwrap()
+                    .messages
+                    .push(format!("{}{}", msgs().request_failed, e))
+            });
+
+        self.rt.spawn(fut);
+    }
+
+    pub fn fetch_character(&mut self, id: u64) -> FetchResult<Character> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.character,
+            fill(msgs().fetching_character, &[&id]),
+            msgs().character_loaded,
+            |client| client.character(id),
+        )
+    }
+
+    pub fn fetch_character_subjects(&mut self, id: u64) -> FetchResult<Vec<CreditedSubject>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.character_subjects,
+            fill(msgs().fetching_appearances, &[&id]),
+            msgs().appearances_loaded,
+            |client| client.character_subjects(id),
+        )
+    }
+
+    pub fn fetch_character_persons(&mut self, id: u64) -> FetchResult<Vec<CharacterPerson>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.character_persons,
+            fill(msgs().fetching_voice_actors, &[&id]),
+            msgs().voice_actors_loaded,
+            |client| client.character_persons(id),
+        )
+    }
+
+    pub fn fetch_person(&mut self, id: u64) -> FetchResult<Person> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.person,
+            fill(msgs().fetching_person, &[&id]),
+            msgs().person_loaded,
+            |client| client.person(id),
+        )
+    }
+
+    pub fn fetch_person_subjects(&mut self, id: u64) -> FetchResult<Vec<CreditedSubject>> {
+        self.fetch_keyed(
+            id,
+            |inner| &mut inner.person_subjects,
+            fill(msgs().fetching_works, &[&id]),
+            msgs().works_loaded,
+            |client| client.person_subjects(id),
+        )
+    }
+
+    fn populate_search<'a>(
+        &self,
+        shallow: &'a ShallowSearchResult,
+    ) -> FetchResult<PopulatedSearchResult> {
+        let mut list = Vec::with_capacity(shallow.ids.len());
+        let count = shallow.count;
+
+        let guard = self.inner.lock().unwrap();
+
+        for id in shallow.ids.iter() {
+            if let Some(InnerState::Fetched((), ref content)) = guard.subject.get(id) {
+                list.push(content.clone());
+            } else {
+                return FetchResult::Deferred;
+            }
+        }
+
+        FetchResult::Direct(PopulatedSearchResult { count, list })
+    }
+
+    pub fn fetch_search(
+        &mut self,
+        search: &str,
+        index: usize,
+    ) -> FetchResult<PopulatedSearchResult> {
+        let mut guard = self.inner.lock().unwrap();
+        let entry = guard.search.entry((search.to_string(), index));
+        match entry {
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(InnerState::Fetching(()));
+            }
+            hash_map::Entry::Occupied(mut entry) => match entry.get_mut() {
+                InnerState::Fetched(_, ref result) => {
+                    let cloned = result.clone();
+                    drop(guard);
+                    return self.populate_search(&cloned);
+                }
+                InnerState::Fetching(_) => return FetchResult::Deferred,
+                value => {
+                    // Else: discarded or fetching another, restart fetch
+                    *value = InnerState::Fetching(());
+                }
+            },
+        }
+
+        guard.messages.push(fill(msgs().searching, &[&search]));
+        guard.notifier.send(()).unwrap();
+        drop(guard);
+
+        let skip = index * SEARCH_PAGING;
+        let fut = self.client.search(search, SEARCH_PAGING, skip);
+        let handle = self.inner.clone();
+        let err_handle = self.inner.clone();
+
+        let search = search.to_string();
+
+        let fut = fut
+            .map(move |resp| {
+                let mut inner = handle.lock().unwrap();
+
+                let mut ids = Vec::with_capacity(resp.list.len());
+                let count = resp.count;
+
+                for subject in resp.list.into_iter() {
+                    ids.push(subject.id);
+                    inner
+                        .subject
+                        .insert(subject.id, InnerState::Fetched((), subject));
+                }
+
+                inner.search.insert(
+                    (search, index),
+                    InnerState::Fetched((), ShallowSearchResult { count, ids }),
+                );
+
+                inner.messages.push(msgs().search_done.to_string());
+                inner
+                    .notifier
+                    .send(())
+                    .expect("Unable to notify the main thread");
+            })
+            .map_err(move |e| {
+                log::error!("{}", e);
+                err_handle
+                    .lock()
+                    .unwrap()
+                    .messages
+                    .push(format!("{}{}", msgs().request_failed, e))
+            });
+
+        self.rt.spawn(fut);
+
+        FetchResult::Deferred
+    }
+
+    pub fn refresh_collection(&mut self) {
+        self.inner.lock().unwrap().collection = InnerState::Discarded;
+    }
+
+    pub fn refresh_search(&mut self, search: String, index: usize) {
+        self.inner
+            .lock()
+            .unwrap()
+            .search
+            .entry((search, index))
+            .and_modify(|s| *s = InnerState::Discarded);
+    }
+
+    pub fn refresh_subject(&mut self, id: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .subject
+            .entry(id)
+            .and_modify(|s| *s = InnerState::Discarded);
+    }
+
+    pub fn refresh_relations(&mut self, id: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .relations
+            .entry(id)
+            .and_modify(|s| *s = InnerState::Discarded);
+    }
+
+    pub fn refresh_subject_credits(&mut self, id: u64) {
+        let mut guard = self.inner.lock().unwrap();
+        discard(&mut guard.subject_characters, id);
+        discard(&mut guard.subject_persons, id);
+    }
+
+    pub fn refresh_episodes(&mut self, id: u64) {
+        let mut guard = self.inner.lock().unwrap();
+        discard(&mut guard.episodes, id);
+        discard(&mut guard.episode_progress, id);
+    }
+
+    pub fn refresh_character(&mut self, id: u64) {
+        let mut guard = self.inner.lock().unwrap();
+        discard(&mut guard.character, id);
+        discard(&mut guard.character_subjects, id);
+        discard(&mut guard.character_persons, id);
+    }
+
+    pub fn refresh_person(&mut self, id: u64) {
+        let mut guard = self.inner.lock().unwrap();
+        discard(&mut guard.person, id);
+        discard(&mut guard.person_subjects, id);
+    }
+
+    pub fn refresh_collection_detail(&mut self, id: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .collection_detail
+            .entry(id)
+            .and_modify(|s| *s = InnerState::Discarded);
+    }
+}
+
+fn matches_query(entry: &CollectionEntry, query: &str) -> bool {
+    let (name, name_cn) = matched_names(entry, query);
+    name || name_cn
+}
+
+/**
+ * Whether the query is highlighted in the original name and in the Chinese name
+ */
+fn matched_names(entry: &CollectionEntry, query: &str) -> (bool, bool) {
+    (
+        fuzzy::find(&entry.subject.name, query).is_some(),
+        fuzzy::find(&entry.subject.name_cn, query).is_some(),
+    )
+}
+
+/**
+ * The closest entry in the given direction matched in other names than the focused one,
+ * wrapping around like searching in Vim. Without a focus, the first or the last entry
+ */
+fn next_match(matched: &[(bool, bool)], focus: Option<usize>, forward: bool) -> Option<usize> {
+    let count = matched.len();
+    let focus = match focus {
+        Some(focus) if focus < count => focus,
+        _ if count == 0 => return None,
+        _ => return Some(if forward { 0 } else { count - 1 }),
+    };
+
+    (1..count)
+        .map(|dist| {
+            if forward {
+                (focus + dist) % count
+            } else {
+                (focus + count - dist) % count
+            }
+        })
+        .find(|i| matched[*i] != matched[focus])
+}
+
+fn discard<T>(map: &mut HashMap<u64, InnerState<(), T>>, id: u64) {
+    map.entry(id).and_modify(|s| *s = InnerState::Discarded);
+}
+
+pub const SELECT_COUNT: usize = 3;
+pub const SELECTS: [SubjectType; SELECT_COUNT] =
+    [SubjectType::Anime, SubjectType::Book, SubjectType::Real];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScrollState {
+    scroll: u16,
 }
 
 impl Default for ScrollState {
@@ -488,7 +1279,7 @@ impl ScrollState {
     }
 }
 
-#[derive(Default, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct FocusState {
     focus: Option<usize>,
     limit: usize,
@@ -536,7 +1327,48 @@ impl FocusState {
     }
 }
 
-#[derive(Clone)]
+/**
+ * Selected cell of a grid. The number of columns is only known after rendering
+ */
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct GridState {
+    focus: FocusState,
+    columns: usize,
+}
+
+impl GridState {
+    pub fn get(&self) -> Option<usize> {
+        self.focus.get()
+    }
+
+    pub fn set(&mut self, focus: Option<usize>) {
+        self.focus.set(focus);
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.focus.set_limit(limit);
+    }
+
+    pub fn set_columns(&mut self, columns: usize) {
+        self.columns = columns;
+    }
+
+    /**
+     * Moves by `dx` cells and `dy` rows, staying put if that leaves the grid
+     */
+    pub fn step(&mut self, dx: isize, dy: isize) {
+        let target = match self.focus.get() {
+            Some(f) => f as isize + dx + dy * self.columns.max(1) as isize,
+            None => 0,
+        };
+
+        if target >= 0 && (target as usize) < self.focus.limit {
+            self.focus.set(Some(target as usize));
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub enum Tab {
     Collection,
 
@@ -547,6 +1379,22 @@ pub enum Tab {
     Subject {
         id: u64,
         scroll: ScrollState,
+        // Selected link, see Tab::links
+        focus: FocusState,
+        // Selected cell in the episode grid
+        episode: GridState,
+    },
+
+    Character {
+        id: u64,
+        scroll: ScrollState,
+        focus: FocusState,
+    },
+
+    Person {
+        id: u64,
+        scroll: ScrollState,
+        focus: FocusState,
     },
 
     SearchResult {
@@ -555,18 +1403,27 @@ pub enum Tab {
         scroll: ScrollState,
         focus: FocusState,
     },
+
+    // Entries of the journal
+    Pending {
+        scroll: ScrollState,
+        focus: FocusState,
+    },
 }
 
 impl Tab {
-    pub fn disp(&self, _app: &AppState) -> String {
+    pub fn disp<A: BangumiApi>(&self, _app: &AppState<A>) -> String {
         // TODO: truncate
         match self {
-            Tab::Collection => "格子".to_string(),
-            Tab::Search { .. } => "搜索".to_string(),
-            Tab::Subject { id, .. } => format!("条目: {}", id),
+            Tab::Collection => msgs().tab_collection.to_string(),
+            Tab::Search { .. } => msgs().tab_search.to_string(),
+            Tab::Subject { id, .. } => fill(msgs().tab_subject, &[id]),
+            Tab::Character { id, .. } => fill(msgs().tab_character, &[id]),
+            Tab::Person { id, .. } => fill(msgs().tab_person, &[id]),
             Tab::SearchResult { search, index, .. } => {
-                format!("搜索: {} / {}", search, index + 1)
+                fill(msgs().tab_search_result, &[search, &(index + 1)])
             }
+            Tab::Pending { .. } => msgs().tab_pending.to_string(),
         }
     }
 
@@ -597,6 +1454,137 @@ impl Tab {
             _ => false,
         }
     }
+
+    pub fn is_pending(&self) -> bool {
+        match self {
+            Tab::Pending { .. } => true,
+            _ => false,
+        }
+    }
+
+    /**
+     * Subject, character and person tabs are all pages with
+     * some text on top and a list of links below
+     */
+    pub fn is_page(&self) -> bool {
+        match self {
+            Tab::Subject { .. } | Tab::Character { .. } | Tab::Person { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn link(&self) -> Option<Link> {
+        match self {
+            Tab::Subject { id, .. } => Some(Link::Subject(*id)),
+            Tab::Character { id, .. } => Some(Link::Character(*id)),
+            Tab::Person { id, .. } => Some(Link::Person(*id)),
+            _ => None,
+        }
+    }
+
+    pub fn page_mut(&mut self) -> Option<(&mut ScrollState, &mut FocusState)> {
+        match self {
+            Tab::Subject {
+                ref mut scroll,
+                ref mut focus,
+                ..
+            }
+            | Tab::Character {
+                ref mut scroll,
+                ref mut focus,
+                ..
+            }
+            | Tab::Person {
+                ref mut scroll,
+                ref mut focus,
+                ..
+            } => Some((scroll, focus)),
+            _ => None,
+        }
+    }
+
+    pub fn links<A: BangumiApi>(&self, app: &mut AppState<A>) -> Vec<LinkSection> {
+        match self.link() {
+            Some(Link::Subject(id)) => {
+                let relations = app.fetch_relations(id).map(|list| {
+                    list.into_iter()
+                        .map(|r| LinkEntry {
+                            link: Link::Subject(r.id),
+                            tag: r.relation,
+                            name: r.name,
+                            extra: r.name_cn,
+                        })
+                        .collect()
+                });
+                let characters = app.fetch_subject_characters(id).map(|list| {
+                    list.into_iter()
+                        .map(|c| LinkEntry {
+                            link: Link::Character(c.id),
+                            tag: c.relation,
+                            name: c.name,
+                            extra: if c.actors.len() == 0 {
+                                String::new()
+                            } else {
+                                let actors = c.actors.into_iter().map(|a| a.name);
+                                format!("CV: {}", itertools::join(actors, ", "))
+                            },
+                        })
+                        .collect()
+                });
+                let persons = app.fetch_subject_persons(id).map(|list| {
+                    list.into_iter()
+                        .map(|p| LinkEntry {
+                            link: Link::Person(p.id),
+                            tag: p.relation,
+                            name: p.name,
+                            extra: String::new(),
+                        })
+                        .collect()
+                });
+
+                [
+                    LinkSection::new(msgs().section_relations, relations),
+                    LinkSection::new(msgs().section_characters, characters),
+                    LinkSection::new(msgs().section_staff, persons),
+                ]
+                .to_vec()
+            }
+            Some(Link::Character(id)) => {
+                let subjects = app
+                    .fetch_character_subjects(id)
+                    .map(|list| list.into_iter().map(LinkEntry::credited).collect());
+                let persons = app.fetch_character_persons(id).map(|list| {
+                    list.into_iter()
+                        .map(|p| LinkEntry {
+                            link: Link::Person(p.id),
+                            tag: String::new(),
+                            name: p.name,
+                            extra: if p.subject_name_cn != "" {
+                                p.subject_name_cn
+                            } else {
+                                p.subject_name
+                            },
+                        })
+                        .collect()
+                });
+
+                [
+                    LinkSection::new(msgs().section_appearances, subjects),
+                    LinkSection::new(msgs().section_voice_actors, persons),
+                ]
+                .to_vec()
+            }
+            Some(Link::Person(id)) => {
+                let subjects = app
+                    .fetch_person_subjects(id)
+                    .map(|list| list.into_iter().map(LinkEntry::credited).collect());
+
+                [LinkSection::new(msgs().section_works, subjects)].to_vec()
+            }
+            None => Vec::new(),
+        }
+    }
+
     pub fn subject_id(&self) -> Option<u64> {
         match self {
             Tab::Subject { id, .. } => Some(*id),
@@ -604,14 +1592,84 @@ impl Tab {
         }
     }
 
+    pub fn episode_mut(&mut self) -> Option<&mut GridState> {
+        match self {
+            Tab::Subject {
+                ref mut episode, ..
+            } => Some(episode),
+            _ => None,
+        }
+    }
+
+    pub fn get_episode(&self) -> Option<usize> {
+        match self {
+            Tab::Subject { episode, .. } => episode.get(),
+            _ => None,
+        }
+    }
+
     pub fn get_focus(&self) -> Option<usize> {
         match self {
-            Tab::SearchResult { focus, .. } => focus.get(),
+            Tab::SearchResult { focus, .. } | Tab::Pending { focus, .. } => focus.get(),
+            Tab::Subject { focus, .. }
+            | Tab::Character { focus, .. }
+            | Tab::Person { focus, .. } => focus.get(),
             _ => None,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum Link {
+    Subject(u64),
+    Character(u64),
+    Person(u64),
+}
+
+#[derive(Clone)]
+pub struct LinkEntry {
+    pub link: Link,
+    pub tag: String,
+    pub name: String,
+    pub extra: String,
+}
+
+impl LinkEntry {
+    fn credited(s: CreditedSubject) -> LinkEntry {
+        LinkEntry {
+            link: Link::Subject(s.id),
+            tag: s.staff,
+            name: s.name,
+            extra: s.name_cn,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LinkSection {
+    pub title: &'static str,
+    pub entries: FetchResult<Vec<LinkEntry>>,
+}
+
+impl LinkSection {
+    fn new(title: &'static str, entries: FetchResult<Vec<LinkEntry>>) -> LinkSection {
+        LinkSection { title, entries }
+    }
+}
+
+/**
+ * Links from all loaded sections, in display order. Focus indices of page tabs refer to this
+ */
+pub fn flatten_links(sections: &[LinkSection]) -> Vec<&LinkEntry> {
+    sections
+        .iter()
+        .flat_map(|s| match s.entries {
+            FetchResult::Direct(ref entries) => itertools::Either::Left(entries.iter()),
+            FetchResult::Deferred => itertools::Either::Right(std::iter::empty()),
+        })
+        .collect()
+}
+
 #[derive(Clone, PartialEq)]
 pub enum UIEvent {
     Key(termion::event::Key),
@@ -630,7 +1688,7 @@ pub enum PendingUIEvent {
 #[derive(Clone)]
 pub enum LongCommand {
     Absent,
-    Graphical,
+    Keys(Vec<termion::event::Key>),
     Command(String),
     Toggle,
 
@@ -638,6 +1696,8 @@ pub enum LongCommand {
     EditStatus(u64, Option<CollectionDetail>, CollectionStatus),
 
     SearchInput(String),
+    // Typed so far, and the query to restore on Esc
+    Filter(String, String),
 }
 
 impl LongCommand {
@@ -651,12 +1711,13 @@ impl LongCommand {
     pub fn prompt(&self) -> Option<String> {
         match self {
             LongCommand::Absent => None,
-            LongCommand::Graphical => Some("g".to_string()),
+            LongCommand::Keys(ref keys) => Some(format_keys(keys)),
             LongCommand::Command(ref inner) => Some(format!(":{}", inner)),
             LongCommand::Toggle => Some("t".to_string()),
-            LongCommand::EditRating(_, _, r) => Some(format!("评分 (1-10, 0=取消): {}", r)),
-            LongCommand::EditStatus(_, _, s) => Some(format!("状态: {} [Tab]", s.disp())),
-            LongCommand::SearchInput(ref inner) => Some(format!("搜索: {}", inner)),
+            LongCommand::EditRating(_, _, r) => Some(fill(msgs().prompt_rating, &[r])),
+            LongCommand::EditStatus(_, _, s) => Some(fill(msgs().prompt_status, &[&s.disp()])),
+            LongCommand::SearchInput(ref inner) => Some(fill(msgs().prompt_search, &[inner])),
+            LongCommand::Filter(ref inner, _) => Some(format!("/{}", inner)),
         }
     }
 }
@@ -669,9 +1730,15 @@ pub struct UIState<'u> {
     pub(crate) tab_scroll: ScrollState,
 
     // TODO: move to the collection tab
-    pub(crate) filters: [bool; SELECTS.len()],
+    pub(crate) filters: [bool; SELECT_COUNT],
+    // Names of the shown entries match this, see fuzzy::find
+    pub(crate) query: String,
+    // None for the order of the API
+    pub(crate) sort: Option<SortMode>,
     pub(crate) scroll: ScrollState,
     pub(crate) focus: FocusState,
+    // Subject ids of the entries the focus refers to, see sync_collection
+    shown: Vec<u64>,
 
     pub(crate) pending: Option<PendingUIEvent>,
 
@@ -688,10 +1755,25 @@ pub struct UIState<'u> {
     meaningless_count: usize,
 
     args: &'u Args,
+    keymap: &'u Keymap,
+    store: &'u FileStore,
 }
 
 impl<'u> UIState<'u> {
-    pub fn with(args: &'u Args, stdin_lock: Arc<Mutex<()>>) -> UIState<'u> {
+    pub fn with(
+        args: &'u Args,
+        keymap: &'u Keymap,
+        store: &'u FileStore,
+        stdin_lock: Arc<Mutex<()>>,
+    ) -> UIState<'u> {
+        // Written by bgmTTY itself, so a broken one is simply forgotten.
+        // null means the order of the API was chosen explicitly
+        let sort = store
+            .load()
+            .and_then(|settings| settings.section::<Option<SortMode>>("sort"))
+            .unwrap_or(None)
+            .unwrap_or(None);
+
         UIState {
             tabs: [
                 Tab::Collection,
@@ -703,9 +1785,12 @@ impl<'u> UIState<'u> {
             tab: 0,
             tab_scroll: Default::default(),
 
-            filters: [true; SELECTS.len()],
+            filters: [true; SELECT_COUNT],
+            query: String::new(),
+            sort,
             scroll: Default::default(),
             focus: Default::default(),
+            shown: Vec::new(),
 
             pending: None,
 
@@ -722,6 +1807,63 @@ impl<'u> UIState<'u> {
             meaningless_count: 0,
 
             args,
+            keymap,
+            store,
+        }
+    }
+
+    /**
+     * What `restore` brings back
+     */
+    pub fn session(&self) -> Session {
+        Session {
+            tabs: self.tabs.clone(),
+            tab: self.tab,
+            filters: self.filters,
+            query: self.query.clone(),
+            scroll: self.scroll.clone(),
+            focus: self.focus.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, session: Session) {
+        if session.tabs.len() == 0 {
+            return;
+        }
+
+        self.tab = std::cmp::min(session.tab, session.tabs.len() - 1);
+        self.tabs = session.tabs;
+        self.filters = session.filters;
+        self.query = session.query;
+        self.scroll = session.scroll;
+        self.focus = session.focus;
+        self.shown.clear();
+        self.pending = Some(PendingUIEvent::KBTabSelect);
+    }
+
+    fn make_session<A: BangumiApi>(&mut self, name: &str, app: &mut AppState<A>) {
+        let saved = named_session_path(&self.args.config, name)
+            .and_then(|path| self.session().save_to(&path).map(|_| path));
+
+        match saved {
+            Ok(path) => app.publish_message(fill(msgs().session_saved, &[&path.display()])),
+            Err(e) => app.publish_message(fill(msgs().session_save_failed, &[&e])),
+        }
+    }
+
+    fn load_session<A: BangumiApi>(&mut self, name: &str, app: &mut AppState<A>) {
+        let loaded = named_session_path(&self.args.config, name)
+            .and_then(|path| Session::load_from(&path).map(|session| (path, session)));
+
+        match loaded {
+            Ok((path, Some(session))) => {
+                self.restore(session);
+                app.publish_message(fill(msgs().session_loaded, &[&path.display()]));
+            }
+            Ok((path, None)) => {
+                app.publish_message(fill(msgs().session_missing, &[&path.display()]))
+            }
+            Err(e) => app.publish_message(fill(msgs().session_load_failed, &[&e])),
         }
     }
 
@@ -792,6 +1934,10 @@ impl<'u> UIState<'u> {
         }
     }
 
+    pub fn keymap(&self) -> &'u Keymap {
+        self.keymap
+    }
+
     pub fn active_tab(&self) -> &Tab {
         // This really should not break
         self.tabs.get(self.tab).unwrap()
@@ -807,6 +1953,34 @@ impl<'u> UIState<'u> {
             return;
         }
 
+        self.refilter(entries, |ui| {
+            if let Some(f) = ui.filters.get_mut(index) {
+                *f = !*f;
+            }
+        });
+    }
+
+    /**
+     * Filters the collection by the query, focusing on the first match
+     */
+    pub fn set_query<A: BangumiApi>(&mut self, query: String, app: &mut AppState<A>) {
+        self.query = query;
+
+        let collection = app.fetch_collection().into();
+        self.shown = self.do_filter(&collection).map(|e| e.subject.id).collect();
+        let count = self.shown.len();
+        self.focus.set_limit(count);
+        self.focus.set(if count > 0 { Some(0) } else { None });
+        self.scroll.set(0);
+    }
+
+    /**
+     * Changes the filters, keeping the focus on the same entry if it is still shown
+     */
+    fn refilter<F>(&mut self, entries: &Option<Vec<CollectionEntry>>, change: F)
+    where
+        F: FnOnce(&mut Self),
+    {
         // Get original index of the filter
         let original = self
             .focus
@@ -814,41 +1988,97 @@ impl<'u> UIState<'u> {
             .and_then(|focus| self.do_filter(entries).skip(focus).next())
             .map(|e| e.subject.id);
 
-        if let Some(f) = self.filters.get_mut(index) {
-            *f = !*f;
-        }
+        change(self);
 
-        let mut new_focus = None;
-        for (i, content) in self.do_filter(entries).enumerate() {
-            if Some(content.subject.id) == original {
-                new_focus = Some(i);
-            }
-        }
+        self.shown = self.do_filter(entries).map(|e| e.subject.id).collect();
+        let new_focus = self.shown.iter().position(|id| Some(*id) == original);
 
+        self.focus.set_limit(self.shown.len());
         self.focus.set(new_focus);
     }
 
+    /**
+     * Keeps the focus on the same entry when the shown entries change without the filters changing,
+     * e.g. when the collection reloads in another order after an update.
+     *
+     * Returns the new focus if it moved
+     */
+    pub fn sync_collection(&mut self, visible: &[&CollectionEntry]) -> Option<usize> {
+        let ids = visible.iter().map(|e| e.subject.id).collect::<Vec<_>>();
+        if ids == self.shown {
+            return None;
+        }
+
+        let original = self
+            .focus
+            .get()
+            .and_then(|focus| self.shown.get(focus))
+            .cloned();
+        self.shown = ids;
+        self.focus.set_limit(self.shown.len());
+
+        let new_focus = self.shown.iter().position(|id| Some(*id) == original);
+        if new_focus.is_some() && new_focus != self.focus.get() {
+            self.focus.set(new_focus);
+            new_focus
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Entries shown in the collection tab, filtered and sorted
+     */
     pub fn do_filter<'s, 'a>(
         &'s self,
         entries: &'a Option<Vec<CollectionEntry>>,
     ) -> impl Iterator<Item = &'a CollectionEntry> {
-        match entries {
-            None => itertools::Either::Left(std::iter::empty()),
+        let mut result = match entries {
+            None => Vec::new(),
             Some(entries) => {
                 let filters = self.filters.clone();
-                itertools::Either::Right(entries.iter().filter(move |e| {
-                    for (i, t) in SELECTS.iter().enumerate() {
-                        if t == &e.subject.subject_type {
-                            return filters[i];
+                let query = &self.query;
+                entries
+                    .iter()
+                    .filter(move |e| {
+                        for (i, t) in SELECTS.iter().enumerate() {
+                            if t == &e.subject.subject_type {
+                                return filters[i] && matches_query(e, query);
+                            }
                         }
-                    }
-                    return false;
-                }))
+                        return false;
+                    })
+                    .collect()
             }
+        };
+
+        if let Some(ref sort) = self.sort {
+            sort.apply(&mut result);
+        }
+
+        result.into_iter()
+    }
+
+    /**
+     * Sorts the collection tab and remembers the choice in the config file
+     */
+    pub fn set_sort<A: BangumiApi>(&mut self, sort: Option<SortMode>, app: &mut AppState<A>) {
+        let collection = app.fetch_collection().into();
+        self.refilter(&collection, |ui| ui.sort = sort);
+
+        let store = self.store;
+        let saved = store
+            .load()
+            .and_then(|settings| settings.set_section("sort", &sort))
+            .and_then(|settings| store.save(&settings));
+
+        if let Err(e) = saved {
+            log::error!("{}", e);
+            app.publish_message(fill(msgs().sort_save_failed, &[&e]));
         }
     }
 
-    pub fn reduce(&mut self, ev: UIEvent, app: &mut AppState) -> &mut Self {
+    pub fn reduce<A: BangumiApi>(&mut self, ev: UIEvent, app: &mut AppState<A>) -> &mut Self {
         use termion::event::{Key, MouseEvent};
 
         if self.last_input_meaningless {
@@ -862,53 +2092,16 @@ impl<'u> UIState<'u> {
         // Second: match long command input
         if self.command.present() {
             if ev == UIEvent::Key(Key::Esc) {
+                if let LongCommand::Filter(_, ref previous) = self.command {
+                    let previous = previous.clone();
+                    self.set_query(previous, app);
+                }
+
                 self.command = LongCommand::Absent;
                 return self;
             }
 
             match self.command {
-                LongCommand::Graphical => match ev {
-                    UIEvent::Key(Key::Char('t')) => {
-                        self.rotate_tab();
-                        self.pending = Some(PendingUIEvent::KBTabSelect);
-                        self.command = LongCommand::Absent;
-                        return self;
-                    }
-                    UIEvent::Key(Key::Char('T')) => {
-                        self.rotate_tab_rev();
-                        self.pending = Some(PendingUIEvent::KBTabSelect);
-                        self.command = LongCommand::Absent;
-                        return self;
-                    }
-                    UIEvent::Key(Key::Char('g')) => {
-                        match self.active_tab_mut() {
-                            Tab::Collection => {
-                                self.scroll.set(0);
-                                self.focus.set(Some(0));
-                            }
-                            Tab::Subject { ref mut scroll, .. } => {
-                                scroll.set(0);
-                            }
-                            Tab::SearchResult {
-                                ref mut scroll,
-                                ref mut focus,
-                                ..
-                            } => {
-                                scroll.set(0);
-                                focus.set(Some(0));
-                            }
-                            _ => {}
-                        }
-                        self.command = LongCommand::Absent;
-                        return self;
-                    }
-                    UIEvent::Key(_) => {
-                        self.command = LongCommand::Absent;
-                        return self;
-                    }
-                    _ => {}
-                },
-
                 LongCommand::Command(ref mut cmd) => match ev {
                     UIEvent::Key(Key::Char('\n')) => {
                         match cmd as &str {
@@ -927,6 +2120,29 @@ impl<'u> UIState<'u> {
                                 )
                             }
                             "tabe coll" => self.tab = self.open_tab(Tab::Collection, None),
+                            "pending" => {
+                                self.tab = self.open_tab(
+                                    Tab::Pending {
+                                        scroll: Default::default(),
+                                        focus: Default::default(),
+                                    },
+                                    None,
+                                )
+                            }
+                            ref e if *e == "mksession" || e.starts_with("mksession ") => {
+                                let name = e[9..].trim().to_string();
+                                self.make_session(&name, app);
+                            }
+                            ref e if *e == "session" || e.starts_with("session ") => {
+                                let name = e[7..].trim().to_string();
+                                self.load_session(&name, app);
+                            }
+                            ref e if *e == "sort" || e.starts_with("sort ") => {
+                                match SortMode::parse(&e[4..]) {
+                                    Ok(sort) => self.set_sort(sort, app),
+                                    Err(e) => app.publish_message(e.to_string()),
+                                }
+                            }
                             ref e if e.starts_with("tabm ") => {
                                 let index = e[5..].parse::<usize>();
                                 match index {
@@ -934,13 +2150,11 @@ impl<'u> UIState<'u> {
                                         self.tab = self.move_tab(index);
                                         self.pending = Some(PendingUIEvent::KBTabSelect);
                                     }
-                                    _ => app.publish_message(format!(
-                                        "{} 是不认识的数字!",
-                                        &e[5..]
-                                    )),
+                                    _ => app
+                                        .publish_message(fill(msgs().unknown_number, &[&&e[5..]])),
                                 }
                             }
-                            _ => app.publish_message("是不认识的命令!".to_string()),
+                            _ => app.publish_message(msgs().unknown_command.to_string()),
                         }
 
                         self.command = LongCommand::Absent;
@@ -962,7 +2176,7 @@ impl<'u> UIState<'u> {
                 },
 
                 LongCommand::Toggle => match ev {
-                    UIEvent::Key(Key::Char(i @ '1'...'9')) => {
+                    UIEvent::Key(Key::Char(i @ '1'..='9')) => {
                         let i = i.to_digit(10).unwrap() as usize;
                         let collection = app.fetch_collection().into();
                         self.toggle_filter(i - 1, &collection);
@@ -998,7 +2212,7 @@ impl<'u> UIState<'u> {
                         rating.pop();
                         return self;
                     }
-                    UIEvent::Key(Key::Char(c @ '0'...'9')) => {
+                    UIEvent::Key(Key::Char(c @ '0'..='9')) => {
                         if rating == "" || (rating == "1" && c == '0') {
                             rating.push(c);
                         } else if rating == "0" {
@@ -1028,6 +2242,27 @@ impl<'u> UIState<'u> {
                     _ => {}
                 },
 
+                LongCommand::Filter(ref mut input, _) => match ev {
+                    UIEvent::Key(Key::Char('\n')) => {
+                        self.command = LongCommand::Absent;
+                        return self;
+                    }
+                    UIEvent::Key(Key::Backspace) => {
+                        input.pop();
+                        let query = input.clone();
+                        self.set_query(query, app);
+                        return self;
+                    }
+                    UIEvent::Key(Key::Char(c)) => {
+                        input.push(c);
+                        let query = input.clone();
+                        self.set_query(query, app);
+                        return self;
+                    }
+                    UIEvent::Key(_) => return self,
+                    _ => {}
+                },
+
                 LongCommand::SearchInput(ref mut staging) => match ev {
                     UIEvent::Key(Key::Char('\n')) => {
                         let cloned = staging.to_string();
@@ -1037,96 +2272,229 @@ impl<'u> UIState<'u> {
                         self.command = LongCommand::Absent;
                         return self;
                     }
-                    UIEvent::Key(Key::Backspace) => {
-                        staging.pop();
-                        return self;
+                    UIEvent::Key(Key::Backspace) => {
+                        staging.pop();
+                        return self;
+                    }
+                    UIEvent::Key(Key::Char(c)) => {
+                        staging.push(c);
+                        return self;
+                    }
+                    UIEvent::Key(_) => return self,
+                    _ => {}
+                },
+
+                _ => {}
+            }
+        }
+
+        // No long command transfer possible, proceed to normal dispatch
+
+        match ev {
+            UIEvent::Key(key) => self.dispatch_key(key, app),
+
+            UIEvent::Mouse(m) => match m {
+                MouseEvent::Press(btn, x, y) => {
+                    self.pending = Some(PendingUIEvent::Click(x - 1, y - 1, btn));
+                    self.update_click(x, y);
+                }
+                MouseEvent::Hold(x, y) => {
+                    self.pending = Some(PendingUIEvent::Click(
+                        x - 1,
+                        y - 1,
+                        termion::event::MouseButton::Left,
+                    ));
+                    self.last_click_interval = None;
+                    self.last_click = None;
+                }
+                _ => {}
+            },
+        }
+
+        self
+    }
+
+    /**
+     * Collects multi-key sequences such as `gt`, then runs the first bound action
+     * available in the current tab
+     */
+    fn dispatch_key<A: BangumiApi>(&mut self, key: termion::event::Key, app: &mut AppState<A>) {
+        let mut seq = match std::mem::replace(&mut self.command, LongCommand::Absent) {
+            LongCommand::Keys(seq) => seq,
+            _ => Vec::new(),
+        };
+        seq.push(key);
+
+        let keymap = self.keymap;
+        let action = keymap.lookup(&seq).find(|action| action.available(self));
+
+        if let Some(action) = action {
+            self.run_action(action, app);
+        } else if keymap.is_prefix(&seq) {
+            self.command = LongCommand::Keys(seq);
+        } else if seq.len() == 1 {
+            self.last_input_meaningless = true;
+        }
+    }
+
+    pub fn run_action<A: BangumiApi>(&mut self, action: Action, app: &mut AppState<A>) {
+        match action {
+            Action::Quit => self.pending = Some(PendingUIEvent::Quit),
+            Action::Command => self.command = LongCommand::Command(String::new()),
+            Action::HelpToggle => self.help = !self.help,
+            Action::HelpUp => self.help_scroll.delta(-1),
+            Action::HelpDown => self.help_scroll.delta(1),
+
+            Action::Refresh => match self.active_tab_mut() {
+                Tab::Collection => {
+                    app.refresh_collection();
+                }
+                Tab::Subject { id, .. } => {
+                    app.refresh_subject(*id);
+                    app.refresh_collection_detail(*id);
+                    app.refresh_relations(*id);
+                    app.refresh_subject_credits(*id);
+                    app.refresh_episodes(*id);
+                }
+                Tab::Character { id, .. } => {
+                    app.refresh_character(*id);
+                }
+                Tab::Person { id, .. } => {
+                    app.refresh_person(*id);
+                }
+                Tab::SearchResult {
+                    ref search, index, ..
+                } => {
+                    app.refresh_search(search.clone(), *index);
+                }
+                _ => {}
+            },
+
+            Action::CursorDown | Action::CursorUp => {
+                let down = action == Action::CursorDown;
+                let focus = if self.active_tab().is_collection() {
+                    Some(&mut self.focus)
+                } else {
+                    match self.active_tab_mut() {
+                        Tab::SearchResult { ref mut focus, .. }
+                        | Tab::Pending { ref mut focus, .. } => Some(focus),
+                        tab => {
+                            if let Some((scroll, _)) = tab.page_mut() {
+                                scroll.delta(if down { 1 } else { -1 });
+                            }
+                            None
+                        }
+                    }
+                };
+
+                if let Some(focus) = focus {
+                    if down {
+                        focus.next();
+                    } else {
+                        focus.prev();
+                    }
+
+                    if let Some(f) = focus.get() {
+                        self.pending = Some(PendingUIEvent::ScrollIntoView(f));
+                    }
+                }
+            }
+
+            Action::CursorOpen => match self.active_tab() {
+                Tab::Collection => {
+                    if let Some(t) = self.collection_focus(app) {
+                        self.goto_detail(t.subject.id);
+                    }
+                }
+                Tab::SearchResult {
+                    ref search,
+                    index,
+                    ref focus,
+                    ..
+                } => {
+                    let focus = focus.get().unwrap();
+                    let result: Option<_> = app.fetch_search(search, *index).into();
+                    let target = result.as_ref().and_then(|result: &PopulatedSearchResult| {
+                        result.list.iter().skip(focus).next()
+                    });
+
+                    if let Some(t) = target {
+                        self.goto_detail(t.id);
+                    }
+                }
+                Tab::Pending { ref focus, .. } => {
+                    let pending = app.pending();
+                    if let Some(write) = focus.get().and_then(|f| pending.get(f)) {
+                        self.goto_detail(write.mutation.subject());
                     }
-                    UIEvent::Key(Key::Char(c)) => {
-                        staging.push(c);
-                        return self;
+                }
+                tab => {
+                    let focus = tab.get_focus().unwrap();
+                    let sections = tab.links(app);
+                    let target = flatten_links(&sections).get(focus).map(|e| e.link);
+
+                    if let Some(link) = target {
+                        self.open_link(link);
                     }
-                    UIEvent::Key(_) => return self,
-                    _ => {}
-                },
+                }
+            },
 
-                _ => {}
+            Action::CursorClear => {
+                if self.active_tab().is_collection() {
+                    self.focus.set(None);
+                } else if let Tab::SearchResult { ref mut focus, .. } = self.active_tab_mut() {
+                    focus.set(None);
+                } else if let Tab::Pending { ref mut focus, .. } = self.active_tab_mut() {
+                    focus.set(None);
+                } else if let Some((_, focus)) = self.active_tab_mut().page_mut() {
+                    focus.set(None);
+                }
             }
-        }
 
-        // No long command transfer possible, proceed to normal dispatch
+            Action::FilterToggle => self.command = LongCommand::Toggle,
 
-        match ev {
-            UIEvent::Key(Key::Ctrl('q')) => self.pending = Some(PendingUIEvent::Quit),
-
-            UIEvent::Key(Key::Down) | UIEvent::Key(Key::Char('j'))
-                if self.active_tab().is_collection() =>
-            {
-                self.focus.next();
-                if let Some(f) = self.focus.get() {
-                    self.pending = Some(PendingUIEvent::ScrollIntoView(f));
-                }
-            }
-            UIEvent::Key(Key::Up) | UIEvent::Key(Key::Char('k'))
-                if self.active_tab().is_collection() =>
-            {
-                self.focus.prev();
-                if let Some(f) = self.focus.get() {
-                    self.pending = Some(PendingUIEvent::ScrollIntoView(f));
-                }
+            Action::FilterInput => {
+                self.command = LongCommand::Filter(self.query.clone(), self.query.clone());
             }
-            UIEvent::Key(Key::Char('t')) if self.active_tab().is_collection() => {
-                self.command = LongCommand::Toggle;
-            }
-            UIEvent::Key(Key::Char('+'))
-                if self.active_tab().is_collection() && self.focus.get().is_some() =>
-            {
-                let focus = self.focus.get().unwrap();
-                let collection = app.fetch_collection().into();
-                let target = self.do_filter(&collection).skip(focus).next();
 
-                if let Some(t) = target {
-                    let (ep, vol) = match t.subject.subject_type {
-                        SubjectType::Book => (None, Some(t.step_vol(1))),
-                        _ => (Some(t.step_ep(1)), None),
-                    };
+            Action::MatchNext | Action::MatchPrev => {
+                // Every shown entry matches, so these skip those matched in the same names
+                let collection = app.fetch_collection().into();
+                let matched = self
+                    .do_filter(&collection)
+                    .map(|e| matched_names(e, &self.query))
+                    .collect::<Vec<_>>();
 
-                    app.update_progress(t, ep, vol);
+                if let Some(next) =
+                    next_match(&matched, self.focus.get(), action == Action::MatchNext)
+                {
+                    self.focus.set(Some(next));
+                    self.pending = Some(PendingUIEvent::ScrollIntoView(next));
                 }
             }
-            UIEvent::Key(Key::Char('-'))
-                if self.active_tab().is_collection() && self.focus.get().is_some() =>
-            {
-                let focus = self.focus.get().unwrap();
+
+            Action::FilterClear => {
                 let collection = app.fetch_collection().into();
-                let target = self.do_filter(&collection).skip(focus).next();
+                self.refilter(&collection, |ui| ui.query.clear());
+            }
 
-                if let Some(t) = target {
+            Action::Undo => app.undo(),
+            Action::Redo => app.redo(),
+
+            Action::ProgressInc | Action::ProgressDec => {
+                let dist = if action == Action::ProgressInc { 1 } else { -1 };
+
+                if let Some(t) = self.collection_focus(app) {
                     let (ep, vol) = match t.subject.subject_type {
-                        SubjectType::Book => (None, Some(t.step_vol(-1))),
-                        _ => (Some(t.step_ep(-1)), None),
+                        SubjectType::Book => (None, Some(t.step_vol(dist))),
+                        _ => (Some(t.step_ep(dist)), None),
                     };
 
-                    app.update_progress(t, ep, vol);
-                }
-            }
-            UIEvent::Key(Key::Char('\n'))
-                if self.active_tab().is_collection() && self.focus.get().is_some() =>
-            {
-                let focus = self.focus.get().unwrap();
-                let collection = app.fetch_collection().into();
-                let target = self.do_filter(&collection).skip(focus).next();
-
-                if let Some(t) = target {
-                    self.goto_detail(t.subject.id);
+                    app.update_progress(&t, ep, vol);
                 }
             }
-            UIEvent::Key(Key::Esc)
-                if self.active_tab().is_collection() && self.focus.get().is_some() =>
-            {
-                self.focus.set(None)
-            }
 
-            UIEvent::Key(Key::Char('s')) if self.active_tab().is_subject() => {
+            Action::EditStatus => {
                 let id = self.active_tab().subject_id().unwrap();
                 if let FetchResult::Direct(coll) = app.fetch_collection_detail(id) {
                     let initial = if let Some(ref coll) = coll {
@@ -1138,7 +2506,7 @@ impl<'u> UIState<'u> {
                 }
             }
 
-            UIEvent::Key(Key::Char('r')) if self.active_tab().is_subject() => {
+            Action::EditRating => {
                 let id = self.active_tab().subject_id().unwrap();
                 if let FetchResult::Direct(Some(coll)) = app.fetch_collection_detail(id) {
                     let rating = coll.rating.to_string();
@@ -1146,7 +2514,7 @@ impl<'u> UIState<'u> {
                 }
             }
 
-            UIEvent::Key(Key::Char('t')) if self.active_tab().is_subject() => {
+            Action::EditTags => {
                 let id = self.active_tab().subject_id().unwrap();
                 if let FetchResult::Direct(Some(mut coll)) = app.fetch_collection_detail(id) {
                     let initial = coll.tag.join("\n");
@@ -1162,7 +2530,7 @@ impl<'u> UIState<'u> {
                 }
             }
 
-            UIEvent::Key(Key::Char('c')) if self.active_tab().is_subject() => {
+            Action::EditComment => {
                 let id = self.active_tab().subject_id().unwrap();
                 if let FetchResult::Direct(Some(mut coll)) = app.fetch_collection_detail(id) {
                     if let Ok(Some(content)) = self.edit(&coll.comment, app) {
@@ -1174,25 +2542,60 @@ impl<'u> UIState<'u> {
                 }
             }
 
-            UIEvent::Key(Key::Down) | UIEvent::Key(Key::Char('j'))
-                if self.active_tab().is_subject() =>
-            {
-                if let Tab::Subject { ref mut scroll, .. } = self.active_tab_mut() {
-                    scroll.delta(1)
+            Action::LinkNext | Action::LinkPrev => {
+                if let Some((_, focus)) = self.active_tab_mut().page_mut() {
+                    if action == Action::LinkNext {
+                        focus.next();
+                    } else {
+                        focus.prev();
+                    }
+
+                    if let Some(f) = focus.get() {
+                        self.pending = Some(PendingUIEvent::ScrollIntoView(f));
+                    }
+                }
+            }
+
+            Action::EpisodeLeft
+            | Action::EpisodeRight
+            | Action::EpisodeUp
+            | Action::EpisodeDown => {
+                let (dx, dy) = match action {
+                    Action::EpisodeLeft => (-1, 0),
+                    Action::EpisodeRight => (1, 0),
+                    Action::EpisodeUp => (0, -1),
+                    _ => (0, 1),
+                };
+
+                if let Some(grid) = self.active_tab_mut().episode_mut() {
+                    grid.step(dx, dy);
                 }
             }
 
-            UIEvent::Key(Key::Up) | UIEvent::Key(Key::Char('k'))
-                if self.active_tab().is_subject() =>
-            {
-                if let Tab::Subject { ref mut scroll, .. } = self.active_tab_mut() {
-                    scroll.delta(-1)
+            Action::EpisodeWatched
+            | Action::EpisodeQueued
+            | Action::EpisodeDropped
+            | Action::EpisodeRemove => {
+                let status = match action {
+                    Action::EpisodeWatched => Some(EpisodeStatus::Watched),
+                    Action::EpisodeQueued => Some(EpisodeStatus::Queued),
+                    Action::EpisodeDropped => Some(EpisodeStatus::Dropped),
+                    _ => None,
+                };
+
+                let tab = self.active_tab();
+                if let (Some(id), Some(index)) = (tab.subject_id(), tab.get_episode()) {
+                    self.mark_episode(id, index, |_| status, app);
                 }
             }
 
-            UIEvent::Key(Key::Esc) if self.active_tab().is_subject() => self.close_tab(self.tab),
+            Action::SearchEdit => {
+                if let Tab::Search { ref text } = self.active_tab() {
+                    self.command = LongCommand::SearchInput(text.clone());
+                }
+            }
 
-            UIEvent::Key(Key::Char('\n')) if self.active_tab().is_search() => {
+            Action::SearchSubmit => {
                 if let Tab::Search { ref text } = self.active_tab() {
                     if text == "" {
                         self.command = LongCommand::SearchInput(String::new());
@@ -1207,143 +2610,118 @@ impl<'u> UIState<'u> {
                 }
             }
 
-            UIEvent::Key(Key::Char('e')) if self.active_tab().is_search() => {
-                if let Tab::Search { ref text } = self.active_tab() {
-                    self.command = LongCommand::SearchInput(text.clone());
-                }
-            }
-
-            UIEvent::Key(Key::Down) | UIEvent::Key(Key::Char('j'))
-                if self.active_tab().is_search_result() =>
-            {
-                if let Tab::SearchResult { ref mut focus, .. } = self.active_tab_mut() {
-                    focus.next();
-                    if let Some(f) = focus.get() {
-                        self.pending = Some(PendingUIEvent::ScrollIntoView(f));
-                    }
+            Action::PageNext => {
+                if let Tab::SearchResult { ref mut index, .. } = self.active_tab_mut() {
+                    *index += 1;
                 }
             }
 
-            UIEvent::Key(Key::Up) | UIEvent::Key(Key::Char('k'))
-                if self.active_tab().is_search_result() =>
-            {
-                if let Tab::SearchResult { ref mut focus, .. } = self.active_tab_mut() {
-                    focus.prev();
-                    if let Some(f) = focus.get() {
-                        self.pending = Some(PendingUIEvent::ScrollIntoView(f));
+            Action::PagePrev => {
+                if let Tab::SearchResult { ref mut index, .. } = self.active_tab_mut() {
+                    if *index > 0 {
+                        *index -= 1;
                     }
                 }
             }
 
-            UIEvent::Key(Key::Char('\n'))
-                if self.active_tab().is_search_result()
-                    && self.active_tab().get_focus().is_some() =>
-            {
-                if let Tab::SearchResult {
-                    ref search,
-                    index,
-                    ref focus,
-                    ..
-                } = self.active_tab()
-                {
-                    let focus = focus.get().unwrap();
-                    let result: Option<_> = app.fetch_search(search, *index).into();
-                    let target = result.as_ref().and_then(|result: &PopulatedSearchResult| {
-                        result.list.iter().skip(focus).next()
-                    });
-
-                    if let Some(t) = target {
-                        self.goto_detail(t.id);
+            Action::PendingDiscard | Action::PendingRetry => {
+                if let Some(index) = self.active_tab().get_focus() {
+                    if action == Action::PendingDiscard {
+                        app.discard_pending(index);
+                    } else {
+                        app.retry_pending(index);
                     }
                 }
             }
 
-            UIEvent::Key(Key::Esc)
-                if self.active_tab().is_search_result()
-                    && self.active_tab().get_focus().is_some() =>
-            {
-                if let Tab::SearchResult { ref mut focus, .. } = self.active_tab_mut() {
-                    focus.set(None);
-                }
+            Action::TabNext => {
+                self.rotate_tab();
+                self.pending = Some(PendingUIEvent::KBTabSelect);
             }
 
-            UIEvent::Key(Key::Char('n')) if self.active_tab().is_search_result() => {
-                if let Tab::SearchResult { ref mut index, .. } = self.active_tab_mut() {
-                    *index += 1;
-                }
+            Action::TabPrev => {
+                self.rotate_tab_rev();
+                self.pending = Some(PendingUIEvent::KBTabSelect);
             }
 
-            UIEvent::Key(Key::Char('N')) if self.active_tab().is_search_result() => {
-                if let Tab::SearchResult { ref mut index, .. } = self.active_tab_mut() {
-                    if *index > 0 {
-                        *index -= 1;
-                    }
-                }
+            Action::TabClose => {
+                self.close_tab(self.tab);
+                self.pending = Some(PendingUIEvent::KBTabSelect);
             }
 
-            UIEvent::Key(Key::Char('\t')) => self.rotate_tab(),
-            UIEvent::Key(Key::Char('g')) => self.command = LongCommand::Graphical,
-            UIEvent::Key(Key::Char('G')) => match self.active_tab_mut() {
+            Action::ScrollTop => match self.active_tab_mut() {
                 Tab::Collection => {
-                    self.scroll.set(std::u16::MAX - 1000);
-                    self.focus.set(Some(std::usize::MAX));
+                    self.scroll.set(0);
+                    self.focus.set(Some(0));
                 }
-                Tab::Subject { ref mut scroll, .. } => {
-                    scroll.set(std::u16::MAX - 1000);
+                Tab::Subject { ref mut scroll, .. }
+                | Tab::Character { ref mut scroll, .. }
+                | Tab::Person { ref mut scroll, .. } => {
+                    scroll.set(0);
                 }
                 Tab::SearchResult {
                     ref mut scroll,
                     ref mut focus,
                     ..
                 } => {
-                    scroll.set(std::u16::MAX - 1000);
-                    focus.set(Some(std::usize::MAX));
+                    scroll.set(0);
+                    focus.set(Some(0));
                 }
                 _ => {}
             },
-            UIEvent::Key(Key::Char('R')) => match self.active_tab_mut() {
+
+            Action::ScrollBottom => match self.active_tab_mut() {
                 Tab::Collection => {
-                    app.refresh_collection();
+                    self.scroll.set(std::u16::MAX - 1000);
+                    self.focus.set(Some(std::usize::MAX));
                 }
-                Tab::Subject { id, .. } => {
-                    app.refresh_subject(*id);
-                    app.refresh_collection_detail(*id);
+                Tab::Subject { ref mut scroll, .. }
+                | Tab::Character { ref mut scroll, .. }
+                | Tab::Person { ref mut scroll, .. } => {
+                    scroll.set(std::u16::MAX - 1000);
                 }
                 Tab::SearchResult {
-                    ref search, index, ..
+                    ref mut scroll,
+                    ref mut focus,
+                    ..
                 } => {
-                    app.refresh_search(search.clone(), *index);
+                    scroll.set(std::u16::MAX - 1000);
+                    focus.set(Some(std::usize::MAX));
                 }
                 _ => {}
             },
-            UIEvent::Key(Key::Char(':')) => self.command = LongCommand::Command(String::new()),
-            UIEvent::Key(Key::Char('?')) | UIEvent::Key(Key::Char('h')) => self.help = !self.help,
-            UIEvent::Key(Key::Char('J')) if self.help => self.help_scroll.delta(1),
-            UIEvent::Key(Key::Char('K')) if self.help => self.help_scroll.delta(-1),
+        }
+    }
 
-            UIEvent::Mouse(m) => match m {
-                MouseEvent::Press(btn, x, y) => {
-                    self.pending = Some(PendingUIEvent::Click(x - 1, y - 1, btn));
-                    self.update_click(x, y);
-                }
-                MouseEvent::Hold(x, y) => {
-                    self.pending = Some(PendingUIEvent::Click(
-                        x - 1,
-                        y - 1,
-                        termion::event::MouseButton::Left,
-                    ));
-                    self.last_click_interval = None;
-                    self.last_click = None;
-                }
-                _ => {}
-            },
+    /**
+     * The focused entry in the collection tab, after filtering
+     */
+    fn collection_focus<A: BangumiApi>(&self, app: &mut AppState<A>) -> Option<CollectionEntry> {
+        let focus = self.focus.get()?;
+        let collection = app.fetch_collection().into();
+        let target = self.do_filter(&collection).skip(focus).next().cloned();
+        target
+    }
 
-            _ => {
-                self.last_input_meaningless = true;
+    /**
+     * Marks the episode at the grid index, with the status computed from its current one
+     */
+    pub fn mark_episode<A, F>(&mut self, id: u64, index: usize, status: F, app: &mut AppState<A>)
+    where
+        A: BangumiApi,
+        F: FnOnce(Option<EpisodeStatus>) -> Option<EpisodeStatus>,
+    {
+        if let FetchResult::Direct((episodes, progress)) =
+            app.fetch_episodes(id) + app.fetch_episode_progress(id)
+        {
+            if let Some(ep) = episodes.get(index) {
+                let current = progress.get(&ep.id).cloned();
+                let status = status(current);
+                if status != current {
+                    app.mark_episode(id, ep.id, status);
+                }
             }
         }
-
-        self
     }
 
     pub fn clear_pending(&mut self) -> bool {
@@ -1384,11 +2762,57 @@ impl<'u> UIState<'u> {
             Tab::Subject {
                 id,
                 scroll: ScrollState::default(),
+                focus: FocusState::default(),
+                episode: GridState::default(),
+            },
+            None,
+        );
+    }
+
+    pub fn goto_character(&mut self, id: u64) {
+        for (i, t) in self.tabs.iter().enumerate() {
+            if t.link() == Some(Link::Character(id)) {
+                self.tab = i;
+                return;
+            }
+        }
+
+        self.tab = self.open_tab(
+            Tab::Character {
+                id,
+                scroll: ScrollState::default(),
+                focus: FocusState::default(),
+            },
+            None,
+        );
+    }
+
+    pub fn goto_person(&mut self, id: u64) {
+        for (i, t) in self.tabs.iter().enumerate() {
+            if t.link() == Some(Link::Person(id)) {
+                self.tab = i;
+                return;
+            }
+        }
+
+        self.tab = self.open_tab(
+            Tab::Person {
+                id,
+                scroll: ScrollState::default(),
+                focus: FocusState::default(),
             },
             None,
         );
     }
 
+    pub fn open_link(&mut self, link: Link) {
+        match link {
+            Link::Subject(id) => self.goto_detail(id),
+            Link::Character(id) => self.goto_character(id),
+            Link::Person(id) => self.goto_person(id),
+        }
+    }
+
     pub fn needs_help(&self) -> bool {
         self.meaningless_count + 2 > HELP_THRESHOLD
     }
@@ -1399,7 +2823,11 @@ impl<'u> UIState<'u> {
      * this will effectively blocks the rendering, so bgmTTY won't interfere with
      * whatever editor the user uses
      */
-    pub fn edit(&mut self, content: &str, app: &mut AppState) -> std::io::Result<Option<String>> {
+    pub fn edit<A: BangumiApi>(
+        &mut self,
+        content: &str,
+        app: &mut AppState<A>,
+    ) -> std::io::Result<Option<String>> {
         self.pending = Some(PendingUIEvent::Reset);
 
         let mut temp = tempfile::NamedTempFile::new()?;
@@ -1412,10 +2840,7 @@ impl<'u> UIState<'u> {
                 .arg(path.deref())
                 .status();
             if result.is_err() {
-                app.publish_message(
-                    "找不到编辑器啦！参数 -e 指定编辑器，或者试试 Vim 嘛？"
-                        .to_string(),
-                );
+                app.publish_message(msgs().editor_missing.to_string());
             }
             result?
         };
@@ -1429,3 +2854,457 @@ impl<'u> UIState<'u> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::entry;
+    use bgmtv::api::{FakeApi, FakeData};
+    use crossbeam_channel::{unbounded, Receiver};
+    use serde_json::json;
+
+    struct Harness {
+        app: AppState<FakeApi>,
+        api: FakeApi,
+        rx: Receiver<()>,
+        dir: tempfile::TempDir,
+    }
+
+    fn harness(data: FakeData) -> Harness {
+        let api = FakeApi::with_data(data);
+        let (tx, rx) = unbounded();
+        let dir = tempfile::tempdir().unwrap();
+        let app = AppState::create(tx, api.clone(), dir.path().join("journal.yml"));
+
+        Harness {
+            app,
+            api,
+            rx,
+            dir: dir,
+        }
+    }
+
+    impl Harness {
+        /**
+         * Polls like the main loop does, until the result is there
+         */
+        fn settle<T, F: FnMut(&mut AppState<FakeApi>) -> FetchResult<T>>(&mut self, mut f: F) -> T {
+            for _ in 0..50 {
+                if let FetchResult::Direct(result) = f(&mut self.app) {
+                    return result;
+                }
+                let _ = self.rx.recv_timeout(Duration::from_millis(100));
+            }
+            panic!("Never settled");
+        }
+
+        /**
+         * Drives the replay like the main loop does, until the condition holds
+         */
+        fn replay_until<F: FnMut(&Harness) -> bool>(&mut self, mut cond: F) {
+            for _ in 0..50 {
+                self.app.replay_journal();
+                if cond(self) {
+                    return;
+                }
+                let _ = self.rx.recv_timeout(Duration::from_millis(100));
+            }
+            panic!("Replay never finished");
+        }
+
+        fn pending(&self) -> Vec<PendingWrite> {
+            self.app.pending()
+        }
+
+        /**
+         * Makes a change while bgm.tv is unreachable
+         */
+        fn update_offline(&mut self, entry: &CollectionEntry, ep: u64) {
+            self.api.offline(true);
+            let count = self.pending().len();
+            self.app.update_progress(entry, Some(ep), None);
+            self.wait_until(|h| h.pending().len() == count + 1);
+            self.api.offline(false);
+        }
+
+        fn wait_until<F: FnMut(&Harness) -> bool>(&self, mut cond: F) {
+            for _ in 0..50 {
+                if cond(self) {
+                    return;
+                }
+                let _ = self.rx.recv_timeout(Duration::from_millis(100));
+            }
+            panic!("Condition never met");
+        }
+    }
+
+    #[test]
+    fn fetch_is_direct_once_loaded() {
+        let mut data = FakeData::default();
+        data.subject.insert(1, entry(1).subject());
+        let mut h = harness(data);
+
+        match h.app.fetch_subject(1) {
+            FetchResult::Deferred => {}
+            FetchResult::Direct(_) => panic!("Served before fetching"),
+        }
+
+        let subject = h.settle(|app| app.fetch_subject(1));
+        assert_eq!(subject.id, 1);
+        assert_eq!(h.app.last_message(), msgs().subject_loaded);
+
+        // Cached from now on
+        h.settle(|app| app.fetch_subject(1));
+        assert_eq!(h.api.calls(), vec!["subject"]);
+    }
+
+    #[test]
+    fn update_progress_reaches_api() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+
+        let collection = h.settle(|app| app.fetch_collection());
+        h.app.update_progress(&collection[0], Some(4), None);
+
+        h.wait_until(|h| h.app.inner.lock().unwrap().undo.len() == 1);
+        assert_eq!(h.api.script(|data| data.collection[0].ep_status), 4);
+
+        // The cached collection is dropped and fetched again
+        let collection = h.settle(|app| app.fetch_collection());
+        assert_eq!(collection[0].ep_status, 4);
+        let calls = h.api.calls();
+        assert_eq!(calls.iter().filter(|c| **c == "progress").count(), 1);
+        assert_eq!(calls.last(), Some(&"collection"));
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        let mut data = FakeData::default();
+        data.subject.insert(1, entry(1).subject());
+        let mut h = harness(data);
+        h.api.fail_next("subject", "Boom");
+
+        h.app.fetch_subject(1);
+        let expected = format!("{}{}", msgs().request_failed, "Boom");
+        h.wait_until(|h| h.app.last_message() == expected);
+    }
+
+    #[test]
+    fn focus_follows_entry_when_collection_reorders() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        data.collection.push(entry(2).ep_status(5).build());
+        let mut h = harness(data);
+
+        let args = Args {
+            editor: String::new(),
+            config: h.dir.path().join("settings.yml"),
+            no_session: true,
+        };
+        let keymap = Keymap::defaults();
+        let store = FileStore::new(&args.config);
+        let mut ui = UIState::with(&args, &keymap, &store, Default::default());
+        ui.sort = SortMode::parse("progress desc").unwrap();
+
+        let collection = Some(h.settle(|app| app.fetch_collection()));
+        let visible = ui.do_filter(&collection).collect::<Vec<_>>();
+        assert_eq!(ui.sync_collection(&visible), None);
+        ui.focus.set(Some(1));
+        assert_eq!(ui.collection_focus(&mut h.app).unwrap().subject.id, 1);
+
+        h.app.update_progress(&visible[1], Some(6), None);
+        h.wait_until(|h| h.app.inner.lock().unwrap().undo.len() == 1);
+
+        // Now sorted first, and still focused
+        let collection = Some(h.settle(|app| app.fetch_collection()));
+        let visible = ui.do_filter(&collection).collect::<Vec<_>>();
+        assert_eq!(visible[0].subject.id, 1);
+        assert_eq!(ui.sync_collection(&visible), Some(0));
+        assert_eq!(ui.collection_focus(&mut h.app).unwrap().subject.id, 1);
+
+        // Cursor moves within the same entries are left alone
+        ui.focus.set(Some(1));
+        assert_eq!(ui.sync_collection(&visible), None);
+        assert_eq!(ui.focus.get(), Some(1));
+    }
+
+    #[test]
+    fn jumps_between_matches_in_other_names() {
+        let named = |id: u64, name: &str, name_cn: &str| {
+            let mut entry = entry(id).build();
+            entry.subject.name = name.to_string();
+            entry.subject.name_cn = name_cn.to_string();
+            entry
+        };
+        let mut data = FakeData::default();
+        data.collection
+            .push(named(1, "Shingeki no Kyojin", "进击的巨人"));
+        data.collection
+            .push(named(2, "Shoujo Shuumatsu Ryokou", "少女终末旅行"));
+        data.collection
+            .push(named(3, "Shoujo Kageki Revue Starlight", "少女☆歌剧"));
+        data.collection
+            .push(named(4, "Plastic Memories", "可塑性记忆"));
+        data.collection
+            .push(named(5, "Ansatsu Kyoushitsu", "暗杀教室"));
+        let mut h = harness(data);
+        h.settle(|app| app.fetch_collection());
+
+        let args = Args {
+            editor: String::new(),
+            config: h.dir.path().join("settings.yml"),
+            no_session: true,
+        };
+        let keymap = Keymap::defaults();
+        let store = FileStore::new(&args.config);
+        let mut ui = UIState::with(&args, &keymap, &store, Default::default());
+
+        // "sj" is in the first two original names, the pinyin initials of the last two
+        // Chinese names, and both names of the third
+        ui.set_query("sj".to_string(), &mut h.app);
+        assert_eq!(ui.focus.get(), Some(0));
+        assert!(Action::MatchNext.available(&ui));
+
+        ui.run_action(Action::MatchNext, &mut h.app);
+        assert_eq!(ui.focus.get(), Some(2));
+        ui.run_action(Action::MatchNext, &mut h.app);
+        assert_eq!(ui.focus.get(), Some(3));
+        ui.run_action(Action::MatchNext, &mut h.app);
+        assert_eq!(ui.focus.get(), Some(0));
+        ui.run_action(Action::MatchPrev, &mut h.app);
+        assert_eq!(ui.focus.get(), Some(4));
+        ui.run_action(Action::MatchPrev, &mut h.app);
+        assert_eq!(ui.focus.get(), Some(2));
+
+        ui.set_query(String::new(), &mut h.app);
+        assert!(!Action::MatchNext.available(&ui));
+    }
+
+    #[test]
+    fn only_unreachable_writes_are_journaled() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.api.fail_next("progress", "Rejected");
+        h.app.update_progress(&collection[0], Some(4), None);
+        let expected = format!("{}{}", msgs().request_failed, "Rejected");
+        h.wait_until(|h| h.app.last_message() == expected);
+        assert_eq!(h.app.inner.lock().unwrap().journal.entries.len(), 0);
+
+        h.api.offline(true);
+        h.app.update_progress(&collection[0], Some(4), None);
+        let expected = fill(msgs().write_queued, &[&1]);
+        h.wait_until(|h| h.app.last_message() == expected);
+        assert_eq!(h.app.inner.lock().unwrap().journal.entries.len(), 1);
+        assert_eq!(h.api.script(|data| data.collection[0].ep_status), 3);
+    }
+
+    #[test]
+    fn replays_offline_changes() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.update_offline(&collection[0], 4);
+        // Shown as done in the meantime
+        let shown = h.settle(|app| app.fetch_collection());
+        assert_eq!(shown[0].ep_status, 4);
+        assert!(h.app.is_pending(1));
+
+        h.app.retry_pending(0);
+        h.replay_until(|h| h.pending().is_empty());
+        assert_eq!(h.api.script(|data| data.collection[0].ep_status), 4);
+    }
+
+    #[test]
+    fn queued_changes_to_one_subject_do_not_conflict() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.update_offline(&collection[0], 4);
+        let shown = h.settle(|app| app.fetch_collection());
+        h.update_offline(&shown[0], 5);
+        assert_eq!(h.pending().len(), 2);
+
+        // Replaying the first one touches the subject on bgm.tv
+        let before = h.api.calls().len();
+        h.app.retry_pending(0);
+        h.replay_until(|h| h.pending().is_empty());
+        assert_eq!(h.api.script(|data| data.collection[0].ep_status), 5);
+        let calls = h.api.calls().split_off(before);
+        assert_eq!(calls.iter().filter(|c| **c == "progress").count(), 2);
+    }
+
+    #[test]
+    fn changes_made_elsewhere_conflict() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.update_offline(&collection[0], 4);
+        h.api.script(|data| {
+            data.collection[0].ep_status = 7;
+            data.collection[0].lasttouch = chrono::Utc::now() + chrono::Duration::hours(1);
+        });
+
+        h.app.retry_pending(0);
+        h.replay_until(|h| h.pending()[0].conflict);
+        let expected = fill(msgs().replay_conflict, &[&h.pending()[0].mutation.disp()]);
+        assert_eq!(h.app.last_message(), expected);
+        assert_eq!(h.api.script(|data| data.collection[0].ep_status), 7);
+
+        // Retrying overwrites it
+        h.app.retry_pending(0);
+        h.replay_until(|h| h.pending().is_empty());
+        assert_eq!(h.api.script(|data| data.collection[0].ep_status), 4);
+    }
+
+    #[test]
+    fn rejected_changes_are_set_aside() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        data.collection.push(entry(2).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.update_offline(&collection[0], 4);
+        h.update_offline(&collection[1], 6);
+        h.api.fail_next("progress", "Rejected");
+
+        h.app.retry_pending(0);
+        h.replay_until(|h| h.pending().len() == 1);
+        let pending = h.pending();
+        assert_eq!(pending[0].mutation.subject(), 1);
+        assert_eq!(pending[0].rejected, Some("Rejected".to_string()));
+        assert_eq!(h.api.script(|data| data.collection[1].ep_status), 6);
+
+        // No longer holds up new changes
+        let shown = h.settle(|app| app.fetch_collection());
+        let entry = shown.iter().find(|e| e.subject.id == 2).unwrap().clone();
+        h.app.update_progress(&entry, Some(7), None);
+        h.wait_until(|h| h.api.script(|data| data.collection[1].ep_status) == 7);
+        assert_eq!(h.pending().len(), 1);
+    }
+
+    #[test]
+    fn discarding_reverts_the_change() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.update_offline(&collection[0], 4);
+        let disp = h.pending()[0].mutation.disp();
+        h.app.discard_pending(0);
+
+        assert!(h.pending().is_empty());
+        assert_eq!(
+            h.app.last_message(),
+            fill(msgs().pending_discarded, &[&disp])
+        );
+        let shown = h.settle(|app| app.fetch_collection());
+        assert_eq!(shown[0].ep_status, 3);
+        assert!(!h.app.is_pending(1));
+    }
+
+    fn stacks(h: &Harness) -> (usize, usize) {
+        let inner = h.app.inner.lock().unwrap();
+        (inner.undo.len(), inner.redo.len())
+    }
+
+    fn ep_status(h: &Harness) -> u64 {
+        h.api.script(|data| data.collection[0].ep_status)
+    }
+
+    #[test]
+    fn undo_and_redo_progress() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.app.update_progress(&collection[0], Some(4), None);
+        h.wait_until(|h| stacks(h) == (1, 0));
+        assert_eq!(ep_status(&h), 4);
+
+        h.app.undo();
+        h.wait_until(|h| stacks(h) == (0, 1));
+        assert_eq!(ep_status(&h), 3);
+
+        h.app.redo();
+        h.wait_until(|h| stacks(h) == (1, 0));
+        assert_eq!(ep_status(&h), 4);
+    }
+
+    #[test]
+    fn failed_undo_can_be_tried_again() {
+        let mut data = FakeData::default();
+        data.collection.push(entry(1).ep_status(3).build());
+        let mut h = harness(data);
+        let collection = h.settle(|app| app.fetch_collection());
+
+        h.app.update_progress(&collection[0], Some(4), None);
+        h.wait_until(|h| stacks(h) == (1, 0));
+
+        h.api.fail_next("progress", "Boom");
+        h.app.undo();
+        let expected = format!("{}{}", msgs().request_failed, "Boom");
+        h.wait_until(|h| h.app.last_message() == expected);
+        assert_eq!(stacks(&h), (1, 0));
+        assert_eq!(ep_status(&h), 4);
+
+        h.app.undo();
+        h.wait_until(|h| stacks(h) == (0, 1));
+        assert_eq!(ep_status(&h), 3);
+    }
+
+    #[test]
+    fn history_is_limited() {
+        let h = harness(FakeData::default());
+        let mut inner = h.app.inner.lock().unwrap();
+        for ep in 0..=HISTORY_LIMIT as u64 {
+            inner.record(
+                Mutation::Progress(
+                    entry(1).ep_status(ep).build(),
+                    entry(1).ep_status(ep + 1).build(),
+                ),
+                Replay::Do,
+            );
+        }
+
+        assert_eq!(inner.undo.len(), HISTORY_LIMIT);
+        match inner.undo[0] {
+            Mutation::Progress(ref before, _) => assert_eq!(before.ep_status, 1),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn replaying_keeps_redo() {
+        let h = harness(FakeData::default());
+        let mut inner = h.app.inner.lock().unwrap();
+        inner.redo.push(Mutation::Progress(
+            entry(1).ep_status(3).build(),
+            entry(1).ep_status(4).build(),
+        ));
+
+        inner.record(
+            Mutation::Progress(entry(2).ep_status(3).build(), entry(2).ep_status(4).build()),
+            Replay::Journal,
+        );
+        assert_eq!((inner.undo.len(), inner.redo.len()), (1, 1));
+
+        inner.record(
+            Mutation::Progress(entry(2).ep_status(4).build(), entry(2).ep_status(5).build()),
+            Replay::Do,
+        );
+        assert_eq!((inner.undo.len(), inner.redo.len()), (2, 0));
+    }
+}
@@ -0,0 +1,915 @@
+use crate::i18n::{fill, msgs};
+use crate::yaml;
+use crate::CollectionStatusExt;
+use bgmtv::client::{Client, CollectionDetail, CollectionEntry, CollectionStatus, SubjectType};
+use bgmtv::transport::Transport;
+use colored::*;
+use failure::{format_err, Error};
+use futures::future::Future;
+use reqwest::r#async as req;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+pub const ANILIST_ENDPOINT: &str = "https://graphql.anilist.co";
+
+const VIEWER_QUERY: &str = "query { Viewer { id name } }";
+
+const LIST_QUERY: &str = "query ($userId: Int) {
+  MediaListCollection(userId: $userId, type: ANIME) {
+    lists { entries { mediaId status score(format: POINT_10) progress updatedAt } }
+  }
+}";
+
+const SEARCH_QUERY: &str = "query ($search: String) {
+  Media(search: $search, type: ANIME) { id }
+}";
+
+const SAVE_MUTATION: &str =
+    "mutation ($mediaId: Int, $status: MediaListStatus, $scoreRaw: Int, $progress: Int) {
+  SaveMediaListEntry(mediaId: $mediaId, status: $status, scoreRaw: $scoreRaw, progress: $progress) {
+    mediaId status score(format: POINT_10) progress updatedAt
+  }
+}";
+
+#[derive(Deserialize)]
+struct GraphQLError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct GraphQLResp<T> {
+    data: Option<T>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize)]
+struct Viewer {
+    id: u64,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ViewerData {
+    viewer: Viewer,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaListEntry {
+    pub media_id: u64,
+    pub status: String,
+    pub score: Option<f64>,
+    pub progress: Option<u64>,
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct MediaList {
+    entries: Vec<MediaListEntry>,
+}
+
+#[derive(Deserialize)]
+struct MediaListCollection {
+    lists: Vec<MediaList>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListData {
+    media_list_collection: MediaListCollection,
+}
+
+#[derive(Deserialize)]
+struct Media {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SearchData {
+    media: Option<Media>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SaveData {
+    save_media_list_entry: MediaListEntry,
+}
+
+pub struct AniList {
+    endpoint: String,
+    token: String,
+}
+
+impl AniList {
+    pub fn new(endpoint: String, token: String) -> AniList {
+        AniList { endpoint, token }
+    }
+
+    fn query<T: DeserializeOwned + Send + 'static>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> impl Future<Item = T, Error = Error> {
+        let c = req::Client::new();
+        c.post(&self.endpoint)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .and_then(|mut resp| resp.json())
+            .map_err(|e| e.into())
+            .and_then(|resp: GraphQLResp<T>| {
+                if let Some(errors) = resp.errors {
+                    let msgs = errors.into_iter().map(|e| e.message).collect::<Vec<_>>();
+                    return Err(format_err!("AniList: {}", msgs.join("; ")));
+                }
+
+                resp.data
+                    .ok_or_else(|| format_err!("{}", msgs().anilist_empty))
+            })
+    }
+
+    fn viewer(&self) -> impl Future<Item = Viewer, Error = Error> {
+        self.query(VIEWER_QUERY, json!({}))
+            .map(|data: ViewerData| data.viewer)
+    }
+
+    fn list(&self, user: u64) -> impl Future<Item = Vec<MediaListEntry>, Error = Error> {
+        self.query(LIST_QUERY, json!({ "userId": user }))
+            .map(|data: ListData| {
+                data.media_list_collection
+                    .lists
+                    .into_iter()
+                    .flat_map(|l| l.entries)
+                    .collect()
+            })
+    }
+
+    fn search(&self, title: &str) -> impl Future<Item = Option<u64>, Error = Error> {
+        self.query(SEARCH_QUERY, json!({ "search": title }))
+            .map(|data: SearchData| data.media.map(|m| m.id))
+    }
+
+    fn save(
+        &self,
+        media: u64,
+        state: &SyncState,
+    ) -> impl Future<Item = MediaListEntry, Error = Error> {
+        self.query(
+            SAVE_MUTATION,
+            json!({
+                "mediaId": media,
+                "status": anilist_status(&state.status),
+                "scoreRaw": state.rating as u64 * 10,
+                "progress": state.progress,
+            }),
+        )
+        .map(|data: SaveData| data.save_media_list_entry)
+    }
+}
+
+/**
+ * Statuses AniList has no counterpart for are sent as null, leaving the remote one untouched
+ */
+fn anilist_status(status: &CollectionStatus) -> Option<&'static str> {
+    match status {
+        CollectionStatus::Wished => Some("PLANNING"),
+        CollectionStatus::Doing => Some("CURRENT"),
+        CollectionStatus::Done => Some("COMPLETED"),
+        CollectionStatus::OnHold => Some("PAUSED"),
+        CollectionStatus::Dropped => Some("DROPPED"),
+        CollectionStatus::Unknown(_) => None,
+    }
+}
+
+fn bangumi_status(status: &str) -> Option<CollectionStatus> {
+    match status {
+        "PLANNING" => Some(CollectionStatus::Wished),
+        "CURRENT" | "REPEATING" => Some(CollectionStatus::Doing),
+        "COMPLETED" => Some(CollectionStatus::Done),
+        "PAUSED" => Some(CollectionStatus::OnHold),
+        "DROPPED" => Some(CollectionStatus::Dropped),
+        _ => None,
+    }
+}
+
+/**
+ * bgm.tv subject ID -> AniList media ID, persisted between runs so that
+ * each title only has to be resolved once
+ */
+#[derive(Serialize, Deserialize, Default)]
+pub struct Mapping {
+    pub subjects: BTreeMap<u64, u64>,
+
+    #[serde(default)]
+    pub last_sync: Option<i64>,
+}
+
+impl Mapping {
+    pub fn load_from<P: AsRef<Path>>(file: P) -> Result<Mapping, Error> {
+        Ok(yaml::load(file)?.unwrap_or_default())
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, file: P) -> Result<(), Error> {
+        yaml::save(file, self)
+    }
+
+    fn media_of(&self, subject: u64) -> Option<u64> {
+        self.subjects.get(&subject).cloned()
+    }
+
+    fn subject_of(&self, media: u64) -> Option<u64> {
+        self.subjects
+            .iter()
+            .find(|(_, m)| **m == media)
+            .map(|(s, _)| *s)
+    }
+}
+
+pub fn default_mapping_path(config: &Path) -> PathBuf {
+    config.with_file_name("bgmtty-anilist.yml")
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyncState {
+    pub status: CollectionStatus,
+    pub rating: u8,
+    pub progress: u64,
+}
+
+impl SyncState {
+    fn from_bangumi(entry: &CollectionEntry, detail: &CollectionDetail) -> SyncState {
+        SyncState {
+            status: detail.status.clone(),
+            rating: detail.rating,
+            progress: entry.ep_status,
+        }
+    }
+
+    fn from_anilist(entry: &MediaListEntry) -> Option<SyncState> {
+        Some(SyncState {
+            status: bangumi_status(&entry.status)?,
+            rating: entry.score.unwrap_or(0.0).round() as u8,
+            progress: entry.progress.unwrap_or(0),
+        })
+    }
+
+    fn describe(&self) -> String {
+        fill(
+            msgs().sync_state,
+            &[&self.status.disp(), &self.rating, &self.progress],
+        )
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Direction {
+    ToAniList,
+    ToBangumi,
+}
+
+pub struct Change {
+    pub subject: u64,
+    pub media: u64,
+    pub name: String,
+    pub direction: Direction,
+    pub from: Option<SyncState>,
+    pub to: SyncState,
+}
+
+impl Change {
+    fn describe(&self) -> String {
+        let (arrow, target) = match self.direction {
+            Direction::ToAniList => ("bgm.tv -> AniList", "AniList"),
+            Direction::ToBangumi => ("AniList -> bgm.tv", "bgm.tv"),
+        };
+
+        let from = self
+            .from
+            .as_ref()
+            .map(SyncState::describe)
+            .unwrap_or_else(|| fill(msgs().sync_absent, &[&target]));
+
+        format!(
+            "[{} / {}] {} {}: {} => {}",
+            self.subject,
+            self.media,
+            self.name,
+            arrow,
+            from,
+            self.to.describe()
+        )
+    }
+}
+
+struct Local {
+    entry: Option<CollectionEntry>,
+    detail: Option<CollectionDetail>,
+}
+
+/**
+ * Last-modified wins. bgm.tv exposes `lasttouch` on collection entries,
+ * AniList exposes `updatedAt` on media list entries.
+ *
+ * Subjects which left the watching list have neither progress nor a timestamp on bgm.tv,
+ * so only their status and rating are compared, and AniList wins if it moved since the last sync
+ */
+fn plan(
+    subject: u64,
+    media: u64,
+    name: String,
+    local: &Local,
+    remote: Option<&MediaListEntry>,
+    last_sync: Option<i64>,
+) -> Option<Change> {
+    let remote_state = remote.and_then(SyncState::from_anilist);
+    let local_state = match (&local.entry, &local.detail) {
+        (Some(entry), Some(detail)) => Some(SyncState::from_bangumi(entry, detail)),
+        (None, Some(detail)) => Some(SyncState {
+            status: detail.status.clone(),
+            rating: detail.rating,
+            progress: remote_state.as_ref().map(|r| r.progress).unwrap_or(0),
+        }),
+        _ => None,
+    };
+
+    match (local_state, remote_state) {
+        (None, None) => None,
+        (Some(l), None) => Some(Change {
+            subject,
+            media,
+            name,
+            direction: Direction::ToAniList,
+            from: None,
+            to: l,
+        }),
+        (None, Some(r)) => Some(Change {
+            subject,
+            media,
+            name,
+            direction: Direction::ToBangumi,
+            from: None,
+            to: r,
+        }),
+        (Some(l), Some(r)) => {
+            if l == r {
+                return None;
+            }
+
+            let remote_touch = remote.and_then(|r| r.updated_at).unwrap_or(0);
+            let local_newer = match (&local.entry, last_sync) {
+                (Some(entry), _) => entry.lasttouch.timestamp() >= remote_touch,
+                // Both sides agreed at the last sync, so whichever didn't move since then is stale
+                (None, Some(last_sync)) => remote_touch <= last_sync,
+                (None, None) => false,
+            };
+
+            if local_newer {
+                Some(Change {
+                    subject,
+                    media,
+                    name,
+                    direction: Direction::ToAniList,
+                    from: Some(r),
+                    to: l,
+                })
+            } else {
+                Some(Change {
+                    subject,
+                    media,
+                    name,
+                    direction: Direction::ToBangumi,
+                    from: Some(l),
+                    to: r,
+                })
+            }
+        }
+    }
+}
+
+pub fn sync_anilist<T: Transport>(
+    client: &Client<T>,
+    anilist: &AniList,
+    mapping_path: &Path,
+    dry_run: bool,
+    accept_new: bool,
+) -> Result<(), Error> {
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let mut mapping = Mapping::load_from(mapping_path)?;
+    let last_sync = mapping.last_sync;
+
+    let viewer = runtime.block_on(anilist.viewer())?;
+    println!("{}", fill(msgs().anilist_user, &[&viewer.name]));
+
+    let remote = runtime
+        .block_on(anilist.list(viewer.id))?
+        .into_iter()
+        .map(|e| (e.media_id, e))
+        .collect::<HashMap<_, _>>();
+
+    let collection = runtime.block_on(client.collection(None))?;
+    let mut changes = Vec::new();
+    let mut seen = Vec::new();
+    let mut unconfirmed = 0;
+
+    for entry in collection.into_iter() {
+        if entry.subject.subject_type != SubjectType::Anime {
+            continue;
+        }
+
+        let subject = entry.subject.id;
+        let media = match mapping.media_of(subject) {
+            Some(media) => media,
+            None => match runtime.block_on(anilist.search(&entry.subject.name))? {
+                // Searches may pick the wrong title, so new matches are only saved once confirmed
+                Some(media) => {
+                    println!(
+                        "{}",
+                        fill(msgs().anilist_new_match, &[&entry.subject.name, &media])
+                    );
+                    if !accept_new {
+                        unconfirmed += 1;
+                        continue;
+                    }
+                    mapping.subjects.insert(subject, media);
+                    media
+                }
+                None => {
+                    println!(
+                        "{} {}",
+                        msgs().anilist_not_found.yellow(),
+                        entry.subject.name
+                    );
+                    continue;
+                }
+            },
+        };
+
+        seen.push(media);
+
+        let detail = runtime.block_on(client.collection_detail(subject))?;
+        let name = entry.subject.name.clone();
+        let local = Local {
+            entry: Some(entry),
+            detail,
+        };
+
+        if let Some(change) = plan(subject, media, name, &local, remote.get(&media), last_sync) {
+            changes.push(change);
+        }
+    }
+
+    // Mapped titles which already left the watching list on bgm.tv
+    for (media, remote_entry) in remote.iter() {
+        if seen.contains(media) {
+            continue;
+        }
+
+        let subject = match mapping.subject_of(*media) {
+            Some(subject) => subject,
+            None => continue,
+        };
+
+        let detail = runtime.block_on(client.collection_detail(subject))?;
+        let local = Local {
+            entry: None,
+            detail,
+        };
+        let name = format!("AniList {}", media);
+
+        if let Some(change) = plan(subject, *media, name, &local, Some(remote_entry), last_sync) {
+            changes.push(change);
+        }
+    }
+
+    if changes.len() == 0 {
+        println!("{}", msgs().sync_up_to_date.green());
+    }
+
+    for change in changes.iter() {
+        println!("{}", change.describe());
+    }
+
+    if unconfirmed > 0 {
+        println!(
+            "{}",
+            fill(msgs().anilist_unconfirmed, &[&unconfirmed]).yellow()
+        );
+    }
+
+    if dry_run {
+        println!("{}", msgs().dry_run.green());
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for change in changes.iter() {
+        if let Err(e) = apply(&mut runtime, client, anilist, change) {
+            println!("{}{}", msgs().request_failed.red(), e);
+            failed += 1;
+        }
+    }
+
+    // Changes which failed are only picked up again if they look newer than the last sync
+    if failed == 0 {
+        mapping.last_sync = Some(chrono::Utc::now().timestamp());
+    }
+    mapping.save_to(mapping_path)?;
+
+    if failed > 0 {
+        Err(format_err!("{}", fill(msgs().sync_partial, &[&failed])))
+    } else {
+        println!("{}", msgs().sync_done.green());
+        Ok(())
+    }
+}
+
+fn apply<T: Transport>(
+    runtime: &mut tokio::runtime::Runtime,
+    client: &Client<T>,
+    anilist: &AniList,
+    change: &Change,
+) -> Result<(), Error> {
+    match change.direction {
+        Direction::ToAniList => {
+            runtime.block_on(anilist.save(change.media, &change.to))?;
+        }
+        Direction::ToBangumi => {
+            let existing = runtime.block_on(client.collection_detail(change.subject))?;
+            let mut detail = existing.unwrap_or_else(|| CollectionDetail {
+                status: change.to.status.clone(),
+                rating: 0,
+                comment: String::new(),
+                tag: Vec::new(),
+            });
+            detail.status = change.to.status.clone();
+            detail.rating = change.to.rating;
+
+            runtime.block_on(client.update_collection_detail(
+                change.subject,
+                change.to.status.clone(),
+                Some(detail),
+            ))?;
+
+            let subject = runtime.block_on(client.subject(change.subject))?;
+            let coll = CollectionEntry {
+                ep_status: 0,
+                vol_status: 0,
+                lasttouch: chrono::Utc::now(),
+                subject,
+            };
+            runtime.block_on(client.progress(&coll, Some(change.to.progress), None))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::entry;
+    use bgmtv::settings::Settings;
+    use bgmtv::transport::{FakeTransport, Method};
+    use std::io::{BufRead, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    fn detail(status: CollectionStatus, rating: u8) -> CollectionDetail {
+        CollectionDetail {
+            status,
+            rating,
+            comment: String::new(),
+            tag: Vec::new(),
+        }
+    }
+
+    fn remote(status: &str, score: f64, progress: u64, updated_at: i64) -> MediaListEntry {
+        MediaListEntry {
+            media_id: 101,
+            status: status.to_string(),
+            score: Some(score),
+            progress: Some(progress),
+            updated_at: Some(updated_at),
+        }
+    }
+
+    fn run_plan(
+        local: Local,
+        remote: Option<MediaListEntry>,
+        last_sync: Option<i64>,
+    ) -> Option<Change> {
+        plan(
+            1,
+            101,
+            "Subject 1".to_string(),
+            &local,
+            remote.as_ref(),
+            last_sync,
+        )
+    }
+
+    #[test]
+    fn plan_skips_equal_states() {
+        let local = Local {
+            entry: Some(entry(1).ep_status(5).lasttouch(1000).build()),
+            detail: Some(detail(CollectionStatus::Doing, 7)),
+        };
+        assert!(run_plan(local, Some(remote("CURRENT", 7.0, 5, 2000)), None).is_none());
+    }
+
+    #[test]
+    fn plan_prefers_the_newer_side() {
+        let local = || Local {
+            entry: Some(entry(1).ep_status(6).lasttouch(2000).build()),
+            detail: Some(detail(CollectionStatus::Doing, 7)),
+        };
+
+        let change = run_plan(local(), Some(remote("CURRENT", 7.0, 5, 1000)), None).unwrap();
+        assert_eq!(change.direction, Direction::ToAniList);
+        assert_eq!(change.to.progress, 6);
+
+        let change = run_plan(local(), Some(remote("CURRENT", 8.0, 9, 3000)), None).unwrap();
+        assert_eq!(change.direction, Direction::ToBangumi);
+        assert_eq!(change.to.progress, 9);
+        assert_eq!(change.to.rating, 8);
+    }
+
+    #[test]
+    fn plan_fills_the_missing_side() {
+        let local = Local {
+            entry: Some(entry(1).ep_status(3).lasttouch(1000).build()),
+            detail: Some(detail(CollectionStatus::Doing, 0)),
+        };
+        let change = run_plan(local, None, None).unwrap();
+        assert_eq!(change.direction, Direction::ToAniList);
+        assert!(change.from.is_none());
+
+        let local = Local {
+            entry: None,
+            detail: None,
+        };
+        let change = run_plan(local, Some(remote("PLANNING", 0.0, 0, 1000)), None).unwrap();
+        assert_eq!(change.direction, Direction::ToBangumi);
+        assert_eq!(change.to.status, CollectionStatus::Wished);
+    }
+
+    #[test]
+    fn plan_keeps_remote_progress_after_leaving_watching_list() {
+        let local = Local {
+            entry: None,
+            detail: Some(detail(CollectionStatus::Done, 8)),
+        };
+        let remote = remote("COMPLETED", 8.0, 12, 1000);
+
+        assert!(run_plan(local, Some(remote), Some(2000)).is_none());
+    }
+
+    #[test]
+    fn plan_compares_status_after_leaving_watching_list() {
+        let local = || Local {
+            entry: None,
+            detail: Some(detail(CollectionStatus::Done, 9)),
+        };
+
+        // Finished on bgm.tv after the last sync
+        let change = run_plan(local(), Some(remote("CURRENT", 8.0, 11, 1000)), Some(2000)).unwrap();
+        assert_eq!(change.direction, Direction::ToAniList);
+        assert_eq!(change.to.status, CollectionStatus::Done);
+        assert_eq!(change.to.rating, 9);
+        assert_eq!(change.to.progress, 11);
+
+        // AniList moved since then
+        let change = run_plan(local(), Some(remote("CURRENT", 8.0, 11, 3000)), Some(2000)).unwrap();
+        assert_eq!(change.direction, Direction::ToBangumi);
+
+        // Never synced, so there is nothing telling bgm.tv is newer
+        let change = run_plan(local(), Some(remote("CURRENT", 8.0, 11, 1000)), None).unwrap();
+        assert_eq!(change.direction, Direction::ToBangumi);
+    }
+
+    /**
+     * A local stand-in for the AniList GraphQL API, answering each query by its root field.
+     * Returns the endpoint and the bodies of the requests received
+     */
+    fn anilist_stand_in(list: serde_json::Value) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_lowercase();
+                    if line == "" {
+                        break;
+                    }
+                    if line.starts_with("content-length:") {
+                        length = line["content-length:".len()..].trim().parse().unwrap();
+                    }
+                }
+
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let query = body["query"].as_str().unwrap().to_string();
+                log.lock().unwrap().push(body.clone());
+
+                let data = if query.contains("Viewer") {
+                    json!({ "Viewer": { "id": 7, "name": "tester" } })
+                } else if query.contains("MediaListCollection") {
+                    json!({ "MediaListCollection": { "lists": [{ "entries": list.clone() }] } })
+                } else if query.contains("SaveMediaListEntry") {
+                    let vars = &body["variables"];
+                    json!({ "SaveMediaListEntry": {
+                        "mediaId": vars["mediaId"],
+                        "status": vars["status"],
+                        "score": 0,
+                        "progress": vars["progress"],
+                        "updatedAt": 0,
+                    } })
+                } else if body["variables"]["search"] == "Subject 3" {
+                    json!({ "Media": { "id": 103 } })
+                } else {
+                    json!({ "Media": null })
+                };
+
+                let resp = serde_json::to_vec(&json!({ "data": data })).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    resp.len()
+                )
+                .unwrap();
+                stream.write_all(&resp).unwrap();
+            }
+        });
+
+        (endpoint, received)
+    }
+
+    fn bangumi() -> (Client<FakeTransport>, FakeTransport) {
+        let settings: Settings = serde_yaml::from_str(
+            "credentials: { client_id: id, client_secret: secret }
+auth:
+  info: { access_token: token, user_id: 42, refresh_token: refresh, expires_in: 604800 }
+  time: 1546300800
+  redirect: ''",
+        )
+        .unwrap();
+        let transport = FakeTransport::new();
+        (
+            Client::with_transport(settings, transport.clone()),
+            transport,
+        )
+    }
+
+    struct Synced {
+        result: Result<(), Error>,
+        transport: FakeTransport,
+        received: Vec<serde_json::Value>,
+        mapping: Mapping,
+    }
+
+    /**
+     * Syncs one show in progress, one finished and one not mapped yet,
+     * answering the progress update with `progress`
+     */
+    fn sync_against_stand_in(progress: &str, accept_new: bool) -> Synced {
+        let (endpoint, received) = anilist_stand_in(json!([
+            { "mediaId": 101, "status": "CURRENT", "score": 7, "progress": 5, "updatedAt": 3000 },
+            { "mediaId": 102, "status": "COMPLETED", "score": 8, "progress": 12, "updatedAt": 1500 },
+        ]));
+
+        let (client, transport) = bangumi();
+        let root = "https://api.bgm.tv";
+        transport
+            .respond_json(
+                Method::Get,
+                &format!("{}/user/42/collection", root),
+                &json!([
+                    entry(1).ep_status(3).lasttouch(1000).build(),
+                    entry(3).ep_status(2).lasttouch(1000).build(),
+                ]),
+            )
+            .respond_json(
+                Method::Get,
+                &format!("{}/collection/1", root),
+                &detail(CollectionStatus::Doing, 7),
+            )
+            .respond_json(
+                Method::Get,
+                &format!("{}/collection/2", root),
+                &detail(CollectionStatus::Done, 8),
+            )
+            .respond_json(
+                Method::Get,
+                &format!("{}/collection/3", root),
+                &detail(CollectionStatus::Doing, 6),
+            )
+            .respond_json(
+                Method::Post,
+                &format!("{}/collection/1/update", root),
+                &detail(CollectionStatus::Doing, 7),
+            )
+            .respond_json(
+                Method::Get,
+                &format!("{}/subject/1", root),
+                &entry(1).lasttouch(0).build().subject,
+            )
+            .respond(
+                Method::Post,
+                &format!("{}/subject/1/update/watched_eps", root),
+                200,
+                progress,
+            );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mapping_path = dir.path().join("bgmtty-anilist.yml");
+        Mapping {
+            subjects: vec![(1, 101), (2, 102)].into_iter().collect(),
+            last_sync: Some(2000),
+        }
+        .save_to(&mapping_path)
+        .unwrap();
+
+        let anilist = AniList::new(endpoint, "token".to_string());
+        let result = sync_anilist(&client, &anilist, &mapping_path, false, accept_new);
+        let received = received.lock().unwrap().clone();
+        Synced {
+            result,
+            transport,
+            received,
+            mapping: Mapping::load_from(&mapping_path).unwrap(),
+        }
+    }
+
+    #[test]
+    fn sync_anilist_against_stand_in() {
+        let Synced {
+            result,
+            transport,
+            received,
+            mapping,
+        } = sync_against_stand_in(r#"{"code":202,"error":"Accepted"}"#, false);
+        result.unwrap();
+
+        // Progress on AniList was newer, and the finished show is left alone
+        assert!(received.iter().all(|body| !body["query"]
+            .as_str()
+            .unwrap()
+            .contains("SaveMediaListEntry")));
+
+        let progress = transport
+            .requests()
+            .into_iter()
+            .find(|req| req.url.ends_with("/subject/1/update/watched_eps"))
+            .unwrap();
+        assert_eq!(String::from_utf8(progress.body).unwrap(), "watched_eps=5");
+        assert!(!transport
+            .requests()
+            .iter()
+            .any(|req| req.url.ends_with("/collection/2/update")));
+        assert!(mapping.last_sync.unwrap() > 2000);
+    }
+
+    #[test]
+    fn failed_syncs_are_tried_again() {
+        let synced = sync_against_stand_in(r#"{"code":400,"error":"Bad Request"}"#, false);
+        assert!(synced.result.is_err());
+        assert_eq!(synced.mapping.last_sync, Some(2000));
+    }
+
+    #[test]
+    fn new_matches_wait_for_confirmation() {
+        let accepted = r#"{"code":202,"error":"Accepted"}"#;
+
+        let synced = sync_against_stand_in(accepted, false);
+        synced.result.unwrap();
+        assert_eq!(synced.mapping.subjects.get(&3), None);
+        assert!(!synced
+            .transport
+            .requests()
+            .iter()
+            .any(|req| req.url.ends_with("/collection/3")));
+
+        let synced = sync_against_stand_in(accepted, true);
+        synced.result.unwrap();
+        assert_eq!(synced.mapping.subjects.get(&3), Some(&103));
+        assert!(synced
+            .received
+            .iter()
+            .any(|body| body["variables"]["mediaId"] == 103));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::i18n::{fill, msgs};
+use bgmtv::settings::Settings;
+use failure::{format_err, Error};
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use tui::style::{Color, Modifier, Style};
+
+/**
+ * Named style slots used by the UI
+ */
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /**
+     * Emphasized text, e.g. the Chinese name of a subject
+     */
+    pub text: Style,
+
+    /**
+     * Names of subjects, characters and persons
+     */
+    pub title: Style,
+
+    /**
+     * Field labels, section titles and subject types
+     */
+    pub label: Style,
+
+    /**
+     * What the user typed, e.g. the search keywords
+     */
+    pub accent: Style,
+
+    /**
+     * Counts and page numbers
+     */
+    pub number: Style,
+
+    /**
+     * Airing schedules
+     */
+    pub info: Style,
+
+    /**
+     * The selected entry, tab or link
+     */
+    pub selection: Style,
+
+    /**
+     * Keys in the help panel and the filter checkboxes
+     */
+    pub key: Style,
+
+    /**
+     * Watched episodes in progress bars
+     */
+    pub progress: Style,
+
+    /**
+     * Episodes in the episode grid, by their marks
+     */
+    pub watched: Style,
+    pub queued: Style,
+    pub dropped: Style,
+
+    /**
+     * Episodes not on air yet and not marked
+     */
+    pub unaired: Style,
+
+    /**
+     * Parts of names matching the filter in the collection tab
+     */
+    pub matched: Style,
+
+    /**
+     * Failed requests in the status line
+     */
+    pub error: Style,
+}
+
+pub const BUILTIN_THEMES: [&str; 3] = ["dark", "light", "mono"];
+
+fn fg(color: Color) -> Style {
+    Style::default().fg(color)
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            text: fg(Color::White),
+            title: fg(Color::Yellow),
+            label: fg(Color::Blue),
+            accent: fg(Color::Green),
+            number: fg(Color::Yellow),
+            info: fg(Color::Cyan),
+            selection: fg(Color::Green),
+            key: fg(Color::Red).modifier(Modifier::Bold),
+            progress: fg(Color::White),
+            watched: fg(Color::Black).bg(Color::Green),
+            queued: fg(Color::Black).bg(Color::Yellow),
+            dropped: fg(Color::Black).bg(Color::Red),
+            unaired: fg(Color::DarkGray),
+            matched: fg(Color::Black).bg(Color::Yellow),
+            error: fg(Color::Red),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            text: fg(Color::Black),
+            title: fg(Color::Magenta),
+            label: fg(Color::Blue),
+            accent: fg(Color::Green),
+            number: fg(Color::Red),
+            info: fg(Color::DarkGray),
+            selection: fg(Color::Green).modifier(Modifier::Bold),
+            key: fg(Color::Red).modifier(Modifier::Bold),
+            progress: fg(Color::Black),
+            watched: fg(Color::Black).bg(Color::LightGreen),
+            queued: fg(Color::Black).bg(Color::LightYellow),
+            dropped: fg(Color::Black).bg(Color::LightRed),
+            unaired: fg(Color::Gray),
+            matched: fg(Color::Black).bg(Color::LightYellow),
+            error: fg(Color::Red),
+        }
+    }
+
+    /**
+     * No colors at all, only modifiers
+     */
+    pub fn mono() -> Theme {
+        let plain = Style::default();
+        let bold = Style::default().modifier(Modifier::Bold);
+
+        Theme {
+            text: plain,
+            title: bold,
+            label: plain,
+            accent: bold,
+            number: plain,
+            info: plain,
+            selection: Style::default().modifier(Modifier::Invert),
+            key: bold,
+            progress: plain,
+            watched: Style::default().modifier(Modifier::Invert),
+            queued: Style::default().modifier(Modifier::Underline),
+            dropped: Style::default().modifier(Modifier::CrossedOut),
+            unaired: Style::default().modifier(Modifier::Faint),
+            matched: Style::default().modifier(Modifier::Underline),
+            error: bold,
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "mono" => Some(Theme::mono()),
+            _ => None,
+        }
+    }
+
+    /**
+     * Picks the theme named by the `theme` section, which is either a
+     * built-in theme or one defined in the `themes` section.
+     *
+     * `NO_COLOR` wins over both, see https://no-color.org
+     */
+    pub fn from_settings(settings: &Settings) -> Result<Theme, Error> {
+        if std::env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty()) {
+            return Ok(Theme::mono());
+        }
+
+        let name = settings
+            .section::<String>("theme")?
+            .unwrap_or_else(|| "dark".to_string());
+        let custom = settings
+            .section::<BTreeMap<String, ThemeSpec>>("themes")?
+            .unwrap_or_default();
+
+        Theme::resolve(&name, &custom, 0)
+    }
+
+    fn resolve(
+        name: &str,
+        custom: &BTreeMap<String, ThemeSpec>,
+        depth: usize,
+    ) -> Result<Theme, Error> {
+        // Guards against themes based on each other
+        if depth > custom.len() {
+            return Err(format_err!("{}", fill(msgs().theme_cycle, &[&name])));
+        }
+
+        let spec = match custom.get(name) {
+            Some(spec) => spec,
+            None => {
+                return Theme::builtin(name).ok_or_else(|| {
+                    let builtin = BUILTIN_THEMES.join(", ");
+                    format_err!("{}", fill(msgs().unknown_theme, &[&name, &builtin]))
+                });
+            }
+        };
+
+        let mut theme = match spec.base {
+            Some(ref base) => Theme::resolve(base, custom, depth + 1)?,
+            None => Theme::dark(),
+        };
+
+        for (slot, style) in spec.slots.iter() {
+            let target = theme
+                .slot_mut(slot)
+                .ok_or_else(|| format_err!("{}", fill(msgs().unknown_style, &[&name, slot])))?;
+            *target = style.to_style()?;
+        }
+
+        Ok(theme)
+    }
+
+    fn slot_mut(&mut self, name: &str) -> Option<&mut Style> {
+        match name {
+            "text" => Some(&mut self.text),
+            "title" => Some(&mut self.title),
+            "label" => Some(&mut self.label),
+            "accent" => Some(&mut self.accent),
+            "number" => Some(&mut self.number),
+            "info" => Some(&mut self.info),
+            "selection" => Some(&mut self.selection),
+            "key" => Some(&mut self.key),
+            "progress" => Some(&mut self.progress),
+            "watched" => Some(&mut self.watched),
+            "queued" => Some(&mut self.queued),
+            "dropped" => Some(&mut self.dropped),
+            "unaired" => Some(&mut self.unaired),
+            "matched" => Some(&mut self.matched),
+            "error" => Some(&mut self.error),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * A user-defined theme. Slots not listed are taken from `base`, or the dark theme
+ */
+#[derive(Deserialize)]
+pub struct ThemeSpec {
+    base: Option<String>,
+
+    #[serde(flatten)]
+    slots: BTreeMap<String, StyleSpec>,
+}
+
+/**
+ * Either a foreground color, or a table with `fg`, `bg` and `modifier`
+ */
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum StyleSpec {
+    Color(String),
+    Full {
+        fg: Option<String>,
+        bg: Option<String>,
+        modifier: Option<String>,
+    },
+}
+
+impl StyleSpec {
+    fn to_style(&self) -> Result<Style, Error> {
+        let mut style = Style::default();
+
+        match self {
+            StyleSpec::Color(color) => style = style.fg(parse_color(color)?),
+            StyleSpec::Full { fg, bg, modifier } => {
+                if let Some(fg) = fg {
+                    style = style.fg(parse_color(fg)?);
+                }
+                if let Some(bg) = bg {
+                    style = style.bg(parse_color(bg)?);
+                }
+                if let Some(modifier) = modifier {
+                    style = style.modifier(parse_modifier(modifier)?);
+                }
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+/**
+ * Accepts color names, `#rrggbb` and 256-color indexes
+ */
+pub fn parse_color(color: &str) -> Result<Color, Error> {
+    let normalized = color.to_lowercase().replace(|c| c == '-' || c == '_', "");
+
+    let result = match normalized.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 && hex.is_ascii() => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            match (channel(1), channel(3), channel(5)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format_err!("{}", fill(msgs().unknown_color, &[&color]))),
+            }
+        }
+        index => match index.parse() {
+            Ok(i) => Color::Indexed(i),
+            Err(_) => return Err(format_err!("{}", fill(msgs().unknown_color, &[&color]))),
+        },
+    };
+
+    Ok(result)
+}
+
+fn parse_modifier(modifier: &str) -> Result<Modifier, Error> {
+    match modifier.to_lowercase().as_str() {
+        "bold" => Ok(Modifier::Bold),
+        "faint" | "dim" => Ok(Modifier::Faint),
+        "italic" => Ok(Modifier::Italic),
+        "underline" => Ok(Modifier::Underline),
+        "blink" => Ok(Modifier::Blink),
+        "invert" | "reverse" => Ok(Modifier::Invert),
+        "crossedout" | "strikethrough" => Ok(Modifier::CrossedOut),
+        _ => Err(format_err!(
+            "{}",
+            fill(msgs().unknown_modifier, &[&modifier])
+        )),
+    }
+}
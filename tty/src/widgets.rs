@@ -1,10 +1,17 @@
-use crate::state::ScrollState;
-use crate::SubjectTypeExt;
-use bgmtv::client::{CollectionEntry, SubjectSmall, SubjectType};
+use crate::fuzzy;
+use crate::i18n::{fill, msgs};
+use crate::state::{GridState, ScrollState};
+use crate::theme::Theme;
+use crate::{SubjectTypeExt, WeekdayExt};
+use bgmtv::client::{
+    CollectionEntry, Episode, EpisodeStatus, EpisodeType, SubjectSmall, SubjectType,
+};
+use chrono::Datelike;
+use std::collections::HashMap;
 use termion::event::MouseButton;
 use tui::buffer::Buffer;
 use tui::layout::Rect;
-use tui::style::{Color, Style};
+use tui::style::Style;
 use tui::symbols;
 use tui::widgets::Widget;
 use tui::widgets::{Block, Borders};
@@ -33,7 +40,7 @@ pub enum ScrollEvent {
 }
 
 pub struct Scroll<'a> {
-    content: Vec<&'a mut DynHeight>,
+    content: Vec<&'a mut dyn DynHeight>,
     bound: Rect,
     scroll: &'a mut ScrollState,
 }
@@ -54,7 +61,7 @@ impl<'a> Scroll<'a> {
         self.content.iter().fold(0, |acc, e| acc + e.height(width))
     }
 
-    pub fn push(&mut self, comp: &'a mut DynHeight) {
+    pub fn push(&mut self, comp: &'a mut dyn DynHeight) {
         self.content.push(comp);
     }
 
@@ -65,11 +72,7 @@ impl<'a> Scroll<'a> {
             index
         };
 
-        let mut start = 0;
-        for i in 0..index {
-            start += self.content[i].height(self.bound.width - 1);
-        }
-
+        let start = self.child_offset(index);
         let end = start + self.content[index].height(self.bound.width - 1);
 
         let new_offset = if start < self.scroll.get() {
@@ -82,6 +85,23 @@ impl<'a> Scroll<'a> {
 
         self.scroll.set(new_offset);
     }
+
+    /**
+     * Where the child starts, counted from the top of the whole content
+     */
+    pub fn child_offset(&self, index: usize) -> u16 {
+        self.content[..index]
+            .iter()
+            .fold(0, |acc, e| acc + e.height(self.bound.width - 1))
+    }
+
+    /**
+     * Translates a point in the bound into the coordinates inside the child
+     */
+    pub fn locate(&self, index: usize, x: u16, y: u16) -> (u16, u16) {
+        let y = y - self.bound.y + self.scroll.get();
+        (x - self.bound.x, y - self.child_offset(index))
+    }
 }
 
 impl<'a> Widget for Scroll<'a> {
@@ -351,66 +371,128 @@ pub struct ViewingEntry<'a> {
     subject: &'a SubjectSmall,
     coll: Option<&'a CollectionEntry>,
     selected: bool,
+    pending: bool,
+    query: &'a str,
+    theme: &'a Theme,
 }
 
 impl<'a> ViewingEntry<'a> {
     pub fn progress(&self) -> Option<ViewProgress> {
+        let style = self.theme.progress;
         self.coll.map(|coll| match self.subject.subject_type {
-            SubjectType::Book => ViewProgress::new(self.subject.vols_count, coll.vol_status),
-            _ => ViewProgress::new(self.subject.eps_count, coll.ep_status),
+            SubjectType::Book => ViewProgress::new(self.subject.vols_count, coll.vol_status, style),
+            _ => ViewProgress::new(self.subject.eps_count, coll.ep_status, style),
         })
     }
 
+    /**
+     * Airing status of collected episodic subjects
+     */
+    pub fn schedule(&self) -> Option<String> {
+        if self.coll.is_none() || self.subject.subject_type == SubjectType::Book {
+            return None;
+        }
+
+        let today = chrono::Local::now().naive_local().date();
+        let weekday = self
+            .subject
+            .air_weekday
+            .get()
+            .or_else(|| self.subject.air_date.date.map(|d| d.weekday()))?;
+
+        if self.subject.is_airing(today) {
+            Some(fill(
+                msgs().airing,
+                &[&weekday.disp(), &self.subject.aired_eps(today)?],
+            ))
+        } else {
+            self.subject
+                .next_airing(today)
+                .map(|date| fill(msgs().premiere, &[&date, &weekday.disp()]))
+        }
+    }
+
+    /**
+     * The text in the given style, with the parts matching the query highlighted
+     */
+    fn highlighted(&self, text: &'a str, style: Style) -> Vec<(&'a str, Style)> {
+        match fuzzy::find(text, self.query) {
+            Some(ref ranges) if !ranges.is_empty() => fuzzy::split(text, ranges)
+                .into_iter()
+                .map(|(part, matched)| (part, if matched { self.theme.matched } else { style }))
+                .collect(),
+            _ => vec![(text, style)],
+        }
+    }
+
     pub fn apply_text<R, F>(&'a self, cb: F) -> R
     where
         for<'b> F: FnOnce(CJKText<'b>) -> R,
     {
         let id = self.subject.id.to_string();
+        let schedule = self.schedule();
+
+        let mut content = [
+            (self.subject.subject_type.disp(), self.theme.label),
+            (" ", Style::default()),
+            (&id, Style::default()),
+        ]
+        .to_vec();
+
+        if self.pending {
+            content.push(("  ", Style::default()));
+            content.push((msgs().pending_mark, self.theme.accent));
+        }
+        content.push(("\n\n", Style::default()));
+
+        content.extend(self.highlighted(&self.subject.name, self.theme.title));
+        content.push(("\n", Style::default()));
+        content.extend(self.highlighted(&self.subject.name_cn, self.theme.text));
 
-        let text = CJKText::raw(
-            [
-                (
-                    self.subject.subject_type.disp(),
-                    Style::default().fg(Color::Blue),
-                ),
-                (" ", Style::default()),
-                (&id, Style::default()),
-                ("\n\n", Style::default()),
-                (
-                    self.subject.name.as_str(),
-                    Style::default().fg(Color::Yellow),
-                ),
-                ("\n", Style::default()),
-                (
-                    self.subject.name_cn.as_str(),
-                    Style::default().fg(Color::White),
-                ),
-            ]
-            .to_vec(),
-        );
-
-        cb(text)
-    }
-
-    pub fn with_coll(ent: &'a CollectionEntry) -> Self {
+        if let Some(ref schedule) = schedule {
+            content.push(("\n", Style::default()));
+            content.push((schedule.as_str(), self.theme.info));
+        }
+
+        cb(CJKText::raw(content))
+    }
+
+    pub fn with_coll(ent: &'a CollectionEntry, theme: &'a Theme) -> Self {
         Self {
             subject: &ent.subject,
             coll: Some(ent),
             selected: false,
+            pending: false,
+            query: "",
+            theme,
         }
     }
 
-    pub fn with_subject(sub: &'a SubjectSmall) -> Self {
+    pub fn with_subject(sub: &'a SubjectSmall, theme: &'a Theme) -> Self {
         Self {
             subject: sub,
             coll: None,
             selected: false,
+            pending: false,
+            query: "",
+            theme,
         }
     }
 
     pub fn select(&mut self, s: bool) {
         self.selected = s;
     }
+
+    pub fn highlight(&mut self, query: &'a str) {
+        self.query = query;
+    }
+
+    /**
+     * Marks the entry as having changes waiting in the journal
+     */
+    pub fn mark_pending(&mut self, pending: bool) {
+        self.pending = pending;
+    }
 }
 
 impl<'a> Widget for ViewingEntry<'a> {
@@ -420,7 +502,7 @@ impl<'a> Widget for ViewingEntry<'a> {
         }
 
         let bs = if self.selected {
-            Style::default().fg(Color::Green)
+            self.theme.selection
         } else {
             Style::default()
         };
@@ -478,16 +560,18 @@ pub enum TabberEvent {
 pub struct Tabber<'a> {
     tabs: &'a [&'a str],
     selected: Option<usize>,
+    theme: &'a Theme,
 
     bound: Rect,
     scroll: &'a mut ScrollState,
 }
 
 impl<'a> Tabber<'a> {
-    pub fn with(tabs: &'a [&'a str], scroll: &'a mut ScrollState) -> Self {
+    pub fn with(tabs: &'a [&'a str], scroll: &'a mut ScrollState, theme: &'a Theme) -> Self {
         Self {
             tabs,
             selected: None,
+            theme,
             bound: Rect::default(),
             scroll,
         }
@@ -539,7 +623,7 @@ impl<'a> Widget for Tabber<'a> {
             let mut text = CJKText::new(tab);
 
             if Some(i) == self.selected {
-                text.set_style(Style::default().fg(Color::Green));
+                text.set_style(self.theme.selection);
             }
 
             let width = text.oneline_min_width();
@@ -639,24 +723,43 @@ pub struct FilterList<'a> {
     tabs: &'a [&'a str],
     state: &'a [bool],
     count: Option<&'a [usize]>,
+    sort: Option<&'a str>,
+    query: &'a str,
+    theme: &'a Theme,
 
     bound: Rect,
 }
 
 impl<'a> FilterList<'a> {
-    pub fn with(tabs: &'a [&'a str], state: &'a [bool]) -> Self {
+    pub fn with(tabs: &'a [&'a str], state: &'a [bool], theme: &'a Theme) -> Self {
         Self {
             tabs,
             state,
+            theme,
             bound: Rect::default(),
             count: None,
+            sort: None,
+            query: "",
         }
     }
 
+    pub fn sorting(mut self, sort: Option<&'a str>) -> Self {
+        self.sort = sort;
+        self
+    }
+
     pub fn counting(mut self, c: &'a [usize]) -> Self {
         self.count = Some(c);
         self
     }
+
+    /**
+     * Shows the query filtering the entries below the toggles
+     */
+    pub fn searching(mut self, query: &'a str) -> Self {
+        self.query = query;
+        self
+    }
 }
 
 const VACANT_UNICODE: &str = "☐";
@@ -672,12 +775,12 @@ impl<'a> Widget for FilterList<'a> {
                 CJKText::new(VACANT_UNICODE)
             };
 
-            symbol.set_style(Style::default().fg(Color::Red));
+            symbol.set_style(self.theme.key);
             symbol.draw(Rect::new(viewport.x, viewport.y + dy, 2, 1), buf);
 
             let width = viewport.width - 2;
             let text_style = if Some(&true) == self.state.get(i) {
-                Style::default().fg(Color::White)
+                self.theme.text
             } else {
                 Style::default()
             };
@@ -691,7 +794,7 @@ impl<'a> Widget for FilterList<'a> {
                     [
                         (*tab, text_style),
                         (" ", Style::default()),
-                        (count, Style::default().fg(Color::Yellow)),
+                        (count, self.theme.number),
                     ]
                     .to_vec(),
                 )
@@ -707,6 +810,39 @@ impl<'a> Widget for FilterList<'a> {
 
             dy += height;
         }
+
+        let mut extra = Vec::new();
+        if let Some(sort) = self.sort {
+            extra.push(CJKText::raw(
+                [
+                    (msgs().label_sort, self.theme.label),
+                    (sort, Style::default()),
+                ]
+                .to_vec(),
+            ));
+        }
+        if self.query != "" {
+            extra.push(CJKText::raw(
+                [("/", self.theme.key), (self.query, self.theme.accent)].to_vec(),
+            ));
+        }
+
+        // Leave a blank line between the toggles and the rest
+        dy += 1;
+        for mut text in extra.into_iter() {
+            if dy >= viewport.height {
+                break;
+            }
+
+            let area = Rect::new(
+                viewport.x,
+                viewport.y + dy,
+                viewport.width,
+                viewport.height - dy,
+            );
+            text.draw(area, buf);
+            dy += text.height(viewport.width);
+        }
     }
 }
 
@@ -748,14 +884,161 @@ impl<'a> Intercept<FilterListEvent> for FilterList<'a> {
     }
 }
 
+pub enum EpisodeGridEvent {
+    Select(usize),
+}
+
+/**
+ * One cell per episode, colored by its mark. The selected cell is put in brackets
+ */
+pub struct EpisodeGrid<'a> {
+    episodes: &'a [Episode],
+    progress: &'a HashMap<u64, EpisodeStatus>,
+    state: &'a mut GridState,
+    theme: &'a Theme,
+
+    labels: Vec<String>,
+    cell_width: u16,
+    bound: Rect,
+}
+
+impl<'a> EpisodeGrid<'a> {
+    pub fn with(
+        episodes: &'a [Episode],
+        progress: &'a HashMap<u64, EpisodeStatus>,
+        state: &'a mut GridState,
+        theme: &'a Theme,
+    ) -> Self {
+        let labels = episodes.iter().map(episode_label).collect::<Vec<_>>();
+        // Room for the brackets around the selected cell
+        let cell_width = labels.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 2;
+        state.set_limit(episodes.len());
+
+        Self {
+            episodes,
+            progress,
+            state,
+            theme,
+            labels,
+            cell_width,
+            bound: Rect::default(),
+        }
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.get()
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.state.set(Some(index));
+    }
+
+    fn columns(&self, width: u16) -> u16 {
+        (width / self.cell_width).max(1)
+    }
+
+    fn style(&self, ep: &Episode) -> Style {
+        match self.progress.get(&ep.id) {
+            Some(EpisodeStatus::Watched) => self.theme.watched,
+            Some(EpisodeStatus::Queued) => self.theme.queued,
+            Some(EpisodeStatus::Dropped) => self.theme.dropped,
+            None if !ep.is_aired() => self.theme.unaired,
+            None => Style::default(),
+        }
+    }
+}
+
+/**
+ * The number of the episode, prefixed by its type unless it is a main one
+ */
+fn episode_label(ep: &Episode) -> String {
+    let prefix = match ep.episode_type {
+        EpisodeType::Main => "",
+        EpisodeType::Special => "SP",
+        EpisodeType::Opening => "OP",
+        EpisodeType::Ending => "ED",
+        EpisodeType::Trailer => "PV",
+        EpisodeType::Mad => "MAD",
+        EpisodeType::Other | EpisodeType::Unknown(_) => "?",
+    };
+
+    format!("{}{}", prefix, ep.number())
+}
+
+impl<'a> Widget for EpisodeGrid<'a> {
+    fn draw(&mut self, viewport: Rect, buf: &mut Buffer) {
+        let columns = self.columns(viewport.width);
+        self.state.set_columns(columns as usize);
+
+        for (i, ep) in self.episodes.iter().enumerate() {
+            let row = i as u16 / columns;
+            if row >= viewport.height {
+                break;
+            }
+
+            let x = viewport.x + (i as u16 % columns) * self.cell_width;
+            let y = viewport.y + row;
+
+            if self.state.get() == Some(i) {
+                buf.set_string(x, y, "[", self.theme.selection);
+                buf.set_string(x + self.cell_width - 1, y, "]", self.theme.selection);
+            }
+
+            // Right-aligned between the brackets
+            let label = &self.labels[i];
+            let dx = self.cell_width - 1 - label.width() as u16;
+            buf.set_string(x + dx, y, label, self.style(ep));
+        }
+    }
+}
+
+impl<'a> DynHeight for EpisodeGrid<'a> {
+    fn height(&self, width: u16) -> u16 {
+        if width == 0 {
+            0
+        } else {
+            let columns = self.columns(width);
+            (self.episodes.len() as u16 + columns - 1) / columns
+        }
+    }
+}
+
+impl<'a> Intercept<EpisodeGridEvent> for EpisodeGrid<'a> {
+    fn intercept(&mut self, x: u16, y: u16, _: MouseButton) -> Option<EpisodeGridEvent> {
+        let columns = self.columns(self.bound.width);
+        let dx = (x - self.bound.x) / self.cell_width;
+        let dy = y - self.bound.y;
+
+        if dx >= columns {
+            return None;
+        }
+
+        let index = (dy * columns + dx) as usize;
+        if index < self.episodes.len() {
+            Some(EpisodeGridEvent::Select(index))
+        } else {
+            None
+        }
+    }
+
+    fn set_bound(&mut self, area: Rect) {
+        self.bound = area;
+    }
+}
+
 pub struct ViewProgress {
     total: Option<u64>,
     current: u64,
+    style: Style,
 }
 
 impl ViewProgress {
-    fn new(total: Option<u64>, current: u64) -> Self {
-        Self { total, current }
+    fn new(total: Option<u64>, current: u64, style: Style) -> Self {
+        Self {
+            total,
+            current,
+            style,
+        }
     }
 
     fn text_hint(&self) -> String {
@@ -787,7 +1070,7 @@ impl Widget for ViewProgress {
             }
 
             let (style, symbol) = if (i as u64) < self.current {
-                (Style::default().fg(Color::White), symbols::block::FULL)
+                (self.style, symbols::block::FULL)
             } else {
                 (Style::default(), SHADE)
             };
@@ -0,0 +1,78 @@
+use failure::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/**
+ * Reads a file written by `save`. None if there is no such file yet
+ */
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(file: P) -> Result<Option<T>, Error> {
+    let mut buf = String::new();
+    match File::open(file) {
+        Ok(mut f) => f.read_to_string(&mut buf)?,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(Some(serde_yaml::from_str(&buf)?))
+}
+
+/**
+ * Writes into a temporary file next to the target and renames it into place,
+ * so that a crash while writing leaves the old content intact
+ */
+pub fn save<T: Serialize, P: AsRef<Path>>(file: P, value: &T) -> Result<(), Error> {
+    let file = file.as_ref();
+    let serialized = serde_yaml::to_vec(value)?;
+
+    // Renaming only works within the same file system
+    let dir = match file.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+    temp.write_all(&serialized)?;
+    temp.persist(file)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn missing_file_is_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded: Option<Vec<u64>> = load(dir.path().join("missing.yml")).unwrap();
+        assert_eq!(loaded, None);
+    }
+
+    #[test]
+    fn replaces_without_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.yml");
+
+        let mut value = HashMap::new();
+        value.insert(1u64, 2u64);
+        save(&path, &value).unwrap();
+        value.insert(3, 4);
+        save(&path, &value).unwrap();
+
+        assert_eq!(load(&path).unwrap(), Some(value));
+        let files = std::fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn reports_broken_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.yml");
+        std::fs::write(&path, "{ not yaml").unwrap();
+
+        assert!(load::<Vec<u64>, _>(&path).is_err());
+    }
+}